@@ -0,0 +1,13 @@
+#version 450
+
+layout(location = 0) in float v_brightness;
+layout(location = 0) out vec4 f_color;
+
+// the color of the triangle in the scene file
+layout(push_constant) uniform PushConstants {
+    vec4 color;
+} push_constants;
+
+void main() {
+    f_color = vec4(push_constants.color.rgb * v_brightness, push_constants.color.a);
+}
@@ -0,0 +1,16 @@
+#version 450
+
+layout(location = 0) in vec2 position;
+layout(location = 0) out float v_brightness;
+
+layout(set = 0, binding = 0) uniform Data {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+    float time;
+} uniforms;
+
+void main() {
+    v_brightness = 0.75 + 0.25 * sin(uniforms.time * 2.0);
+    gl_Position = uniforms.proj * uniforms.view * uniforms.model * vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,82 @@
+use cgmath::{Angle, Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, Vector3};
+
+/// A perspective camera looking from `eye` at `target`.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub eye: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+    // vertical field of view
+    pub fovy: Deg<f32>,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera {
+            eye: Point3::new(0.0, 0.0, 3.0),
+            target: Point3::new(0.0, 0.0, 0.0),
+            up: Vector3::unit_y(),
+            fovy: Deg(45.0),
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    /// Projection for a viewport with the given aspect ratio (width / height).
+    pub fn projection(&self, aspect: f32) -> Matrix4<f32> {
+        vulkan_clip() * cgmath::perspective(self.fovy, aspect, self.near, self.far)
+    }
+
+    pub fn view_projection(&self, aspect: f32) -> Matrix4<f32> {
+        self.projection(aspect) * self.view()
+    }
+
+    /// Moves the camera back along +z until the bounding box `(min, max)` fills the view, and
+    /// fits the near and far plane around it.
+    pub fn frame_bounds(&mut self, min: [f32; 3], max: [f32; 3]) {
+        let (center, radius) = bounding_sphere(min, max);
+
+        // distance at which the bounding sphere touches the top and bottom of the view
+        let distance = radius / (Rad::from(self.fovy) * 0.5).sin();
+
+        self.target = center;
+        self.eye = center + Vector3::unit_z() * distance;
+        self.fit_depth_range(min, max);
+    }
+
+    /// Chooses the near and far plane, so the bounding box `(min, max)` is not clipped from
+    /// where the camera currently is.
+    pub fn fit_depth_range(&mut self, min: [f32; 3], max: [f32; 3]) {
+        let (center, radius) = bounding_sphere(min, max);
+        let distance = (center - self.eye).magnitude();
+
+        // the near plane can't be at 0, and the closer it is the less precise the depth buffer
+        self.near = ((distance - radius) * 0.5).max(radius * 0.01);
+        self.far = (distance + radius) * 2.0;
+    }
+}
+
+/// cgmath follows the OpenGL conventions (y pointing up, depth from -1 to 1), while Vulkan's
+/// clip space has y pointing down and depth from 0 to 1.
+fn vulkan_clip() -> Matrix4<f32> {
+    Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, -1.0, 0.0, 0.0,
+        0.0, 0.0, 0.5, 0.0,
+        0.0, 0.0, 0.5, 1.0,
+    )
+}
+
+fn bounding_sphere(min: [f32; 3], max: [f32; 3]) -> (Point3<f32>, f32) {
+    let min = Point3::from(min);
+    let max = Point3::from(max);
+    (min.midpoint(max), (max - min).magnitude().max(f32::EPSILON) * 0.5)
+}
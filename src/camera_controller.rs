@@ -0,0 +1,222 @@
+use cgmath::{InnerSpace, Point3, Vector3, Zero};
+
+use crate::camera::Camera;
+use crate::input::{Action, Input};
+
+// radians the cameras turn per pixel the mouse moves
+const TURN_SPEED: f32 = 0.005;
+// radians per second the orbit camera turns while a key is held
+const KEY_TURN_SPEED: f32 = 1.5;
+// the cameras stop a little before looking straight up or down, where their up vector would be
+// parallel to the view direction
+const MAX_PITCH: f32 = 1.55;
+// the orbit distance is multiplied by this per line scrolled towards the user
+const ZOOM_STEP: f32 = 1.1;
+// the fly speed is multiplied by this per line scrolled away from the user
+const SPEED_STEP: f32 = 1.2;
+// how much faster the fly camera is while `Action::Fast` is held
+const FAST_FACTOR: f32 = 4.0;
+
+/// How the windowed samples move their camera, chosen with `--camera`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    Orbit,
+    Fly,
+}
+
+/// Turns around a target while the mouse is dragged with `Action::Rotate` and moves the target
+/// with `Action::Pan` (or rotate with shift held). Scrolling or `Action::Forward`/`Back` moves
+/// closer or further away, `Action::Left`/`Right` turn around the target.
+#[derive(Debug, Clone)]
+pub struct OrbitController {
+    pub target: Point3<f32>,
+    pub distance: f32,
+    // in radians, 0 looks along -z from +z
+    pub yaw: f32,
+    // in radians, positive looks down from above
+    pub pitch: f32,
+}
+
+impl OrbitController {
+    /// Orbits around the target of `camera`, starting where the camera is.
+    pub fn from_camera(camera: &Camera) -> OrbitController {
+        let offset = camera.eye - camera.target;
+        let distance = offset.magnitude().max(f32::EPSILON);
+        OrbitController {
+            target: camera.target,
+            distance,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).asin(),
+        }
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let offset = Vector3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw);
+        self.target + offset * self.distance
+    }
+
+    pub fn update(&mut self, input: &Input, dt: f32) {
+        let [dx, dy] = input.mouse_delta();
+        let panning = input.action(Action::Pan) || (input.action(Action::Rotate) && input.modifiers().shift());
+
+        if panning {
+            // the target moves faster the further away the camera is, so it keeps up with the mouse
+            let forward = (self.target - self.eye()).normalize();
+            let right = forward.cross(Vector3::unit_y()).normalize();
+            let up = right.cross(forward);
+            let scale = self.distance * TURN_SPEED * 0.2;
+            self.target += (up * dy - right * dx) * scale;
+        } else if input.action(Action::Rotate) {
+            self.yaw -= dx * TURN_SPEED;
+            self.pitch += dy * TURN_SPEED;
+        }
+
+        let turn = axis(input, Action::Right, Action::Left);
+        self.yaw += turn * KEY_TURN_SPEED * dt;
+        self.pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+
+        // the keys zoom by one step every tenth of a second
+        let zoom = -input.scroll() + axis(input, Action::Back, Action::Forward) * dt * 10.0;
+        self.distance = (self.distance * ZOOM_STEP.powf(zoom)).max(0.01);
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.eye = self.eye();
+        camera.target = self.target;
+        camera.up = Vector3::unit_y();
+    }
+}
+
+/// Moves with `Action::Forward`, `Back`, `Left`, `Right`, `Up` and `Down` and turns while the
+/// mouse is dragged with `Action::Look`. Scrolling changes the speed.
+#[derive(Debug, Clone)]
+pub struct FlyController {
+    pub position: Point3<f32>,
+    // in radians, 0 looks along -z
+    pub yaw: f32,
+    // in radians, positive looks up
+    pub pitch: f32,
+    // units per second
+    pub speed: f32,
+    // how far ahead the target of the camera is, kept for switching back to orbiting
+    distance: f32,
+}
+
+impl FlyController {
+    /// Flies from where `camera` is, in the direction it looks.
+    pub fn from_camera(camera: &Camera) -> FlyController {
+        let view = camera.target - camera.eye;
+        let distance = view.magnitude().max(f32::EPSILON);
+        let direction = view / distance;
+        FlyController {
+            position: camera.eye,
+            yaw: (-direction.x).atan2(-direction.z),
+            pitch: direction.y.asin(),
+            // crosses the distance to the target in a second
+            speed: distance,
+            distance,
+        }
+    }
+
+    pub fn forward(&self) -> Vector3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vector3::new(-cos_pitch * sin_yaw, sin_pitch, -cos_pitch * cos_yaw)
+    }
+
+    pub fn update(&mut self, input: &Input, dt: f32) {
+        if input.action(Action::Look) {
+            let [dx, dy] = input.mouse_delta();
+            self.yaw -= dx * TURN_SPEED;
+            self.pitch = (self.pitch - dy * TURN_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        self.speed *= SPEED_STEP.powf(input.scroll());
+
+        // forward follows the view, up and down the world
+        let forward = self.forward();
+        let right = forward.cross(Vector3::unit_y()).normalize();
+        let direction = forward * axis(input, Action::Forward, Action::Back)
+            + right * axis(input, Action::Right, Action::Left)
+            + Vector3::unit_y() * axis(input, Action::Up, Action::Down);
+        if direction.is_zero() {
+            return;
+        }
+
+        let fast = if input.action(Action::Fast) { FAST_FACTOR } else { 1.0 };
+        self.position += direction.normalize() * self.speed * fast * dt;
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.eye = self.position;
+        camera.target = self.position + self.forward() * self.distance;
+        camera.up = Vector3::unit_y();
+    }
+}
+
+/// Moves a camera from the input of a window, with either controller. `Action::SwitchCamera`
+/// switches between them, keeping the view.
+#[derive(Debug, Clone)]
+pub enum CameraController {
+    Orbit(OrbitController),
+    Fly(FlyController),
+}
+
+impl CameraController {
+    /// A controller starting from the view of `camera`.
+    pub fn new(mode: CameraMode, camera: &Camera) -> CameraController {
+        match mode {
+            CameraMode::Orbit => CameraController::Orbit(OrbitController::from_camera(camera)),
+            CameraMode::Fly => CameraController::Fly(FlyController::from_camera(camera)),
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        match self {
+            CameraController::Orbit(_) => CameraMode::Orbit,
+            CameraController::Fly(_) => CameraMode::Fly,
+        }
+    }
+
+    /// Moves the camera by what happened during the last `dt` seconds.
+    pub fn update(&mut self, input: &Input, dt: f32) {
+        if input.action_pressed(Action::SwitchCamera) {
+            let mut camera = Camera::default();
+            self.apply(&mut camera);
+            let mode = match self.mode() {
+                CameraMode::Orbit => CameraMode::Fly,
+                CameraMode::Fly => CameraMode::Orbit,
+            };
+            *self = CameraController::new(mode, &camera);
+            println!("{:?} camera", mode);
+        }
+
+        match self {
+            CameraController::Orbit(orbit) => orbit.update(input, dt),
+            CameraController::Fly(fly) => fly.update(input, dt),
+        }
+    }
+
+    /// Moves `camera` to the view of the controller, its projection is kept.
+    pub fn apply(&self, camera: &mut Camera) {
+        match self {
+            CameraController::Orbit(orbit) => orbit.apply(camera),
+            CameraController::Fly(fly) => fly.apply(camera),
+        }
+    }
+}
+
+/// The camera mode named `name` on the command line.
+pub fn parse_camera_mode(name: &str) -> Option<CameraMode> {
+    match name {
+        "orbit" => Some(CameraMode::Orbit),
+        "fly" => Some(CameraMode::Fly),
+        _ => None,
+    }
+}
+
+// 1 while only `positive` is held, -1 while only `negative` is, 0 otherwise
+fn axis(input: &Input, positive: Action, negative: Action) -> f32 {
+    input.action(positive) as i32 as f32 - input.action(negative) as i32 as f32
+}
@@ -0,0 +1,238 @@
+use vulkano::sampler::{BorderColor, Filter, SamplerAddressMode};
+
+use crate::camera_controller::{self, CameraMode};
+use crate::input::{self, Bindings};
+use crate::swapchain_config::{self, SwapchainOptions};
+use crate::texture::SamplerOptions;
+
+/// Options given on the command line:
+///
+/// `rust-vulkan [COMMAND] [ARGS...] [--samples N] [--size WxH] [--output PATH]
+///  [--eye X,Y,Z] [--target X,Y,Z] [--fov DEGREES]
+///  [--filter nearest|linear] [--address-mode repeat|mirror|clamp|border] [--anisotropy N]
+///  [--instances N] [--particles N] [--frames N] [--shader-dir DIR]
+///  [--cache-dir DIR] [--threads N] [--frames-in-flight N]
+///  [--present-mode MODE,...] [--surface-format FORMAT,...] [--images N]
+///  [--stats SECONDS] [--stats-csv PATH] [--show-fps]
+///  [--camera orbit|fly] [--bind ACTION=INPUT,...] [--debug-window] [--screenshot-dir DIR]
+///  [--overlay] [--font PATH] [--gui] [--scene PATH] [--dot PATH]`
+///
+/// Without a command the `--scene` file (the triangle by default) is drawn into a window, like
+/// with `triangle`. The commands `cube`, `texture`, `instancing`, `particles`, `scene` and
+/// `mandelbrot` open a window as well, `window-test` an empty one. Any other command runs the
+/// headless sample or test with the given name. Arguments after the command are passed to it, e.g. the model file for
+/// `render-mesh`.
+pub struct Options {
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    // number of samples per pixel used for multisample anti-aliasing
+    pub samples: u32,
+    // size of images rendered by headless commands
+    pub size: [u32; 2],
+    // file headless commands save their image to, instead of their default
+    pub output: Option<String>,
+    // camera overrides, unset values are chosen by the command
+    pub eye: Option<[f32; 3]>,
+    pub target: Option<[f32; 3]>,
+    pub fov: Option<f32>,
+    // how textures are sampled
+    pub sampler: SamplerOptions,
+    // number of instances drawn by the instancing sample
+    pub instances: u32,
+    // number of particles simulated by the particle samples
+    pub particles: u32,
+    // number of frames the animated headless samples export
+    pub frames: u32,
+    // directory with the GLSL files of the windowed samples, which are reloaded on changes
+    pub shader_dir: Option<String>,
+    // directory the pipeline cache is kept in between runs
+    pub cache_dir: String,
+    // number of threads recording secondary command buffers, one per cpu core if unset
+    pub threads: Option<usize>,
+    // number of frames the windowed samples record ahead of the gpu
+    pub frames_in_flight: usize,
+    // preferred configuration of the swapchain of the windowed samples
+    pub swapchain: SwapchainOptions,
+    // how often the windowed samples print a summary of their frame times, never if unset
+    pub stats_interval: Option<f32>,
+    // file the windowed samples write the times of every frame to
+    pub stats_csv: Option<String>,
+    // whether the windowed samples show their frame rate in the title
+    pub show_fps: bool,
+    // how the windowed samples move their camera
+    pub camera_mode: CameraMode,
+    // the keys and mouse buttons of the windowed samples, `--bind` replaces the defaults of the
+    // actions it binds
+    pub bindings: Bindings,
+    // whether the windowed samples open a second window, showing them from a fixed camera
+    pub debug_window: bool,
+    // directory the screenshots of the windowed samples are saved to
+    pub screenshot_dir: String,
+    // whether the frame rate, the device and the parameters of the sample are drawn on top of
+    // it, the windowed samples toggle it with `Action::ToggleOverlay`
+    pub overlay: bool,
+    // TrueType font the overlay is drawn with, instead of the default one
+    pub font: Option<String>,
+    // whether the window with the parameters of the sample is shown, the windowed samples
+    // toggle it with `Action::ToggleGui`
+    pub gui: bool,
+    // scene file (see `scene_file`) the window is opened for and draws, instead of the default
+    // scene of the triangle
+    pub scene: Option<String>,
+    // file the render graph samples write their graph to, in the Graphviz DOT language
+    pub dot: Option<String>,
+}
+
+impl Options {
+    pub fn from_args() -> Options {
+        Options::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<I>(args: I) -> Options
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Options {
+            command: None,
+            args: Vec::new(),
+            samples: 1,
+            size: [1024, 1024],
+            output: None,
+            eye: None,
+            target: None,
+            fov: None,
+            sampler: SamplerOptions::default(),
+            instances: 10_000,
+            particles: 65_536,
+            frames: 60,
+            shader_dir: None,
+            cache_dir: String::from("pipeline-cache"),
+            threads: None,
+            frames_in_flight: 2,
+            swapchain: SwapchainOptions::default(),
+            stats_interval: None,
+            stats_csv: None,
+            show_fps: false,
+            camera_mode: CameraMode::Orbit,
+            bindings: Bindings::default(),
+            debug_window: false,
+            screenshot_dir: String::from("."),
+            overlay: false,
+            font: None,
+            gui: false,
+            scene: None,
+            dot: None,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--samples" => options.samples = parse_value(&arg, args.next()),
+                "--size" => options.size = parse_list(&arg, args.next(), 'x'),
+                "--output" => options.output = Some(parse_value(&arg, args.next())),
+                "--eye" => options.eye = Some(parse_list(&arg, args.next(), ',')),
+                "--target" => options.target = Some(parse_list(&arg, args.next(), ',')),
+                "--fov" => options.fov = Some(parse_value(&arg, args.next())),
+                "--filter" => {
+                    let value: String = parse_value(&arg, args.next());
+                    options.sampler.filter = match value.as_str() {
+                        "nearest" => Filter::Nearest,
+                        "linear" => Filter::Linear,
+                        _ => panic!("invalid value for {}: {}", arg, value),
+                    };
+                }
+                "--address-mode" => {
+                    let value: String = parse_value(&arg, args.next());
+                    options.sampler.address_mode = match value.as_str() {
+                        "repeat" => SamplerAddressMode::Repeat,
+                        "mirror" => SamplerAddressMode::MirroredRepeat,
+                        "clamp" => SamplerAddressMode::ClampToEdge,
+                        "border" => SamplerAddressMode::ClampToBorder(BorderColor::FloatOpaqueBlack),
+                        _ => panic!("invalid value for {}: {}", arg, value),
+                    };
+                }
+                "--anisotropy" => options.sampler.anisotropy = parse_value(&arg, args.next()),
+                "--instances" => options.instances = parse_value(&arg, args.next()),
+                "--particles" => options.particles = parse_value(&arg, args.next()),
+                "--frames" => options.frames = parse_value(&arg, args.next()),
+                "--shader-dir" => options.shader_dir = Some(parse_value(&arg, args.next())),
+                "--cache-dir" => options.cache_dir = parse_value(&arg, args.next()),
+                "--threads" => options.threads = Some(parse_value(&arg, args.next())),
+                "--frames-in-flight" => options.frames_in_flight = parse_value(&arg, args.next()),
+                "--present-mode" => {
+                    options.swapchain.present_modes =
+                        parse_names(&arg, args.next(), swapchain_config::parse_present_mode);
+                }
+                "--surface-format" => {
+                    options.swapchain.formats = parse_names(&arg, args.next(), swapchain_config::parse_format);
+                }
+                "--images" => options.swapchain.image_count = parse_value(&arg, args.next()),
+                "--stats" => options.stats_interval = Some(parse_value(&arg, args.next())),
+                "--stats-csv" => options.stats_csv = Some(parse_value(&arg, args.next())),
+                "--show-fps" => options.show_fps = true,
+                "--camera" => {
+                    let value: String = parse_value(&arg, args.next());
+                    options.camera_mode = camera_controller::parse_camera_mode(&value)
+                        .unwrap_or_else(|| panic!("invalid value for {}: {}", arg, value));
+                }
+                "--bind" => {
+                    let bindings = parse_names(&arg, args.next(), input::parse_binding);
+                    // an action bound several times in one list keeps every binding
+                    for &(action, _) in &bindings {
+                        options.bindings.unbind(action);
+                    }
+                    for (action, binding) in bindings {
+                        options.bindings.bind(action, binding);
+                    }
+                }
+                "--debug-window" => options.debug_window = true,
+                "--screenshot-dir" => options.screenshot_dir = parse_value(&arg, args.next()),
+                "--overlay" => options.overlay = true,
+                "--font" => options.font = Some(parse_value(&arg, args.next())),
+                "--gui" => options.gui = true,
+                "--scene" => options.scene = Some(parse_value(&arg, args.next())),
+                "--dot" => options.dot = Some(parse_value(&arg, args.next())),
+                _ if arg.starts_with("--") => panic!("unknown option: {}", arg),
+                _ if options.command.is_none() => options.command = Some(arg),
+                _ => options.args.push(arg),
+            }
+        }
+
+        options
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(option: &str, value: Option<String>) -> T {
+    let value = value.unwrap_or_else(|| panic!("missing value for {}", option));
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("invalid value for {}: {}", option, value))
+}
+
+// parses `N` values separated by `separator`, like `1,2,3` or `800x600`
+fn parse_list<T: std::str::FromStr + Default + Copy, const N: usize>(
+    option: &str,
+    value: Option<String>,
+    separator: char,
+) -> [T; N] {
+    let value: String = parse_value(option, value);
+    let parts: Vec<&str> = value.split(separator).collect();
+    if parts.len() != N {
+        panic!("invalid value for {}: {}", option, value);
+    }
+
+    let mut list = [T::default(); N];
+    for (item, part) in list.iter_mut().zip(parts) {
+        *item = parse_value(option, Some(part.trim().to_string()));
+    }
+    list
+}
+
+// parses a list of names separated by commas, like `mailbox,fifo`, in order
+fn parse_names<T>(option: &str, value: Option<String>, parse: fn(&str) -> Option<T>) -> Vec<T> {
+    let value: String = parse_value(option, value);
+    value
+        .split(',')
+        .map(|name| parse(name.trim()).unwrap_or_else(|| panic!("invalid value for {}: {}", option, name)))
+        .collect()
+}
@@ -0,0 +1,222 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::render_pass::Subpass;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use cgmath::{Matrix4, Rad};
+use image::{Rgba, ImageBuffer};
+
+use crate::camera::Camera;
+use crate::depth;
+use crate::index::{self, IndexBuffer};
+use crate::offscreen::OffscreenTarget;
+use crate::pipeline_cache;
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec3 position;
+layout(location=1) in vec3 color;
+
+layout(location=0) out vec3 v_color;
+
+// model-view-projection matrix, updated for every draw
+layout(push_constant) uniform PushConstants {
+    mat4 mvp;
+} push_constants;
+
+void main() {
+    v_color = color;
+    gl_Position = push_constants.mvp * vec4(position, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec3 v_color;
+layout(location=0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(v_color, 1.0);
+}
+"
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct CubeVertex {
+    pub position: [f32; 3],
+    color: [f32; 3],
+}
+
+vulkano::impl_vertex!(CubeVertex, position, color);
+
+// the color of each face, in the order the faces are drawn
+pub const FRONT: [f32; 3] = [0.0, 1.0, 0.0];
+pub const RIGHT: [f32; 3] = [1.0, 1.0, 0.0];
+pub const TOP: [f32; 3] = [0.0, 1.0, 1.0];
+pub const LEFT: [f32; 3] = [1.0, 0.0, 1.0];
+pub const BOTTOM: [f32; 3] = [1.0, 1.0, 1.0];
+pub const BACK: [f32; 3] = [1.0, 0.0, 0.0];
+
+/// A unit cube with a differently colored face on each side, four vertices per face.
+///
+/// The front face (+z) is drawn first and the back face (-z) last, so without a depth test the
+/// back face ends up on top when looking at the front.
+pub fn cube_vertices() -> Vec<CubeVertex> {
+    // corners of each face, counter-clockwise seen from outside of the cube
+    let faces = [
+        ([[-0.5, -0.5, 0.5], [0.5, -0.5, 0.5], [0.5, 0.5, 0.5], [-0.5, 0.5, 0.5]], FRONT),
+        ([[0.5, -0.5, 0.5], [0.5, -0.5, -0.5], [0.5, 0.5, -0.5], [0.5, 0.5, 0.5]], RIGHT),
+        ([[-0.5, 0.5, 0.5], [0.5, 0.5, 0.5], [0.5, 0.5, -0.5], [-0.5, 0.5, -0.5]], TOP),
+        ([[-0.5, -0.5, -0.5], [-0.5, -0.5, 0.5], [-0.5, 0.5, 0.5], [-0.5, 0.5, -0.5]], LEFT),
+        ([[-0.5, -0.5, -0.5], [0.5, -0.5, -0.5], [0.5, -0.5, 0.5], [-0.5, -0.5, 0.5]], BOTTOM),
+        ([[0.5, -0.5, -0.5], [-0.5, -0.5, -0.5], [-0.5, 0.5, -0.5], [0.5, 0.5, -0.5]], BACK),
+    ];
+
+    faces
+        .iter()
+        .flat_map(|&(corners, color)| {
+            (0..4).map(move |i| CubeVertex { position: corners[i], color })
+        })
+        .collect()
+}
+
+/// One triangle strip per face of `cube_vertices`, separated by primitive restarts.
+pub fn cube_indices() -> Vec<u32> {
+    // the corners are counter-clockwise, so the strip zig-zags from the first over the second
+    // and last to the third corner
+    index::join_strips((0..6).map(|face| {
+        let first = face * 4;
+        vec![first, first + 1, first + 3, first + 2]
+    }))
+}
+
+/// Pipeline, vertex and index buffer to draw the cube into a subpass.
+///
+/// The depth test is enabled, if the subpass has a depth attachment.
+pub struct Cube {
+    pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[CubeVertex]>>,
+    index_buffer: IndexBuffer,
+}
+
+impl Cube {
+    pub fn new(device: Arc<Device>, subpass: Subpass) -> Cube {
+        // load shader for device
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        let builder = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<CubeVertex>()
+            .vertex_shader(vertex_shader.main_entry_point(), ())
+            .triangle_strip()
+            // the maximum index ends a strip, so all faces are drawn with one call
+            .primitive_restart(true)
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fragment_shader.main_entry_point(), ());
+
+        // only fragments closer than what is already in the depth buffer are written, and
+        // they update the depth buffer
+        let builder = if subpass.has_depth() {
+            builder.depth_stencil_simple_depth()
+        } else {
+            builder
+        };
+
+        let pipeline = Arc::new(
+            builder
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::all(),
+            false,
+            cube_vertices().into_iter(),
+        )
+        .unwrap();
+
+        Cube {
+            pipeline,
+            vertex_buffer,
+            index_buffer: IndexBuffer::new(device, &cube_indices()),
+        }
+    }
+
+    /// Draws the cube rotated by `angle` around the y axis (and half of it around the x axis),
+    /// as seen by `camera`.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+        camera: &Camera,
+        aspect: f32,
+        angle: Rad<f32>,
+    ) {
+        let model = Matrix4::from_angle_y(angle) * Matrix4::from_angle_x(angle * 0.5);
+        self.draw_transformed(builder, dynamic_state, camera.view_projection(aspect) * model);
+    }
+
+    /// Draws the cube transformed by `mvp` into a primary or secondary command buffer.
+    pub fn draw_transformed<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        dynamic_state: &DynamicState,
+        mvp: Matrix4<f32>,
+    ) {
+        let push_constants = vs::ty::PushConstants { mvp: mvp.into() };
+
+        self.index_buffer.draw(
+            builder,
+            self.pipeline.clone(),
+            dynamic_state,
+            self.vertex_buffer.clone(),
+            (),
+            push_constants,
+        );
+    }
+}
+
+/// Renders the cube headlessly into `target`, rotated by `angle`.
+pub fn render(queue: Arc<Queue>, device: Arc<Device>, target: &OffscreenTarget, angle: Rad<f32>) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let cube = Cube::new(device, target.subpass());
+    let dynamic_state = target.dynamic_state();
+    let [width, height] = target.dimensions();
+    let camera = Camera::default();
+
+    target.render(queue, [0.0, 0.0, 0.0, 1.0], |builder| {
+        cube.draw(builder, &dynamic_state, &camera, width as f32 / height as f32, angle);
+    })
+}
+
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32) {
+    let target = OffscreenTarget::start(device.clone(), [1024, 1024])
+        .samples(samples)
+        .depth_format(depth::choose_depth_format(device.physical_device()))
+        .build();
+
+    let image_buffer = render(queue, device, &target, Rad(0.6));
+
+    image_buffer.save("cube.png").unwrap();
+}
@@ -0,0 +1,100 @@
+use vulkano::command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::render_pass::Subpass;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::ops::Range;
+
+use cgmath::{Matrix4, Rad, Vector3};
+use rayon::ThreadPool;
+
+use crate::cube_sample::Cube;
+use crate::secondary;
+
+/// Many copies of the cube on a grid, each with its own transform and draw call, as a scene
+/// whose recording takes a noticeable amount of time.
+pub struct CubeScene {
+    cube: Cube,
+    models: Vec<Matrix4<f32>>,
+}
+
+impl CubeScene {
+    /// `count` cubes, filling a cube of side length 1.4 around the origin.
+    pub fn new(device: Arc<Device>, subpass: Subpass, count: u32) -> CubeScene {
+        let side = (count as f32).cbrt().ceil().max(1.0) as u32;
+        let cell = 1.4 / side as f32;
+
+        let models = (0..count)
+            .map(|i| {
+                let [x, y, z] = [i % side, (i / side) % side, i / (side * side)];
+                let [x, y, z] = [x, y, z].map(|c| -0.7 + (c as f32 + 0.5) * cell);
+                let angle = Rad(i as f32 * 0.1);
+                Matrix4::from_translation(Vector3::new(x, y, z))
+                    * Matrix4::from_scale(cell * 0.6)
+                    * Matrix4::from_angle_y(angle)
+                    * Matrix4::from_angle_x(angle * 0.5)
+            })
+            .collect();
+
+        CubeScene {
+            cube: Cube::new(device, subpass),
+            models,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.models.len()
+    }
+
+    /// Draws the cubes in `range`, one draw call each, into a primary or secondary command
+    /// buffer.
+    pub fn draw_range<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        dynamic_state: &DynamicState,
+        view_projection: Matrix4<f32>,
+        range: Range<usize>,
+    ) {
+        for model in &self.models[range] {
+            self.cube.draw_transformed(builder, dynamic_state, view_projection * model);
+        }
+    }
+
+    /// Draws all cubes, recorded inline on the calling thread.
+    pub fn draw<L>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        dynamic_state: &DynamicState,
+        view_projection: Matrix4<f32>,
+    ) {
+        self.draw_range(builder, dynamic_state, view_projection, 0..self.len());
+    }
+
+    /// Records the cubes into secondary command buffers for `subpass`, in parallel on the
+    /// threads of `pool`. Executing the returned buffers in order draws the same as `draw`.
+    pub fn record_secondary(
+        &self,
+        pool: &ThreadPool,
+        queue: &Arc<Queue>,
+        subpass: &Subpass,
+        dynamic_state: &DynamicState,
+        view_projection: Matrix4<f32>,
+    ) -> Vec<SecondaryAutoCommandBuffer> {
+        // a few batches per thread, so threads which finish early can take over the remaining
+        // ones
+        let batch_count = (pool.current_num_threads() * 4).min(self.len()).max(1);
+        let batch_size = (self.len() + batch_count - 1) / batch_count;
+        let batches: Vec<Range<usize>> = (0..self.len())
+            .step_by(batch_size.max(1))
+            .map(|start| start..(start + batch_size).min(self.len()))
+            .collect();
+
+        secondary::record_batches(pool, queue, subpass, &batches, |builder, batch| {
+            self.draw_range(builder, dynamic_state, view_projection, batch.clone());
+        })
+    }
+}
@@ -0,0 +1,435 @@
+use vulkano::buffer::{BufferAccess, BufferUsage, TypedBufferAccess};
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, DrawIndirectCommand, PrimaryCommandBuffer};
+use vulkano::command_buffer::SubpassContents;
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::sync::GpuFuture;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3, Vector4};
+use image::{Rgba, ImageBuffer};
+
+use crate::camera::Camera;
+use crate::cli::Options;
+use crate::cube_sample::{self, CubeVertex};
+use crate::depth;
+use crate::offscreen::OffscreenTarget;
+use crate::pipeline_cache;
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: "
+#version 450
+
+layout(local_size_x = 64, local_size_y = 1, local_size_z = 1) in;
+
+struct Instance {
+    // center and radius of the bounding sphere
+    vec4 sphere;
+    vec4 color;
+};
+
+struct DrawCommand {
+    uint vertex_count;
+    uint instance_count;
+    uint first_vertex;
+    uint first_instance;
+};
+
+layout(set = 0, binding = 0) readonly buffer Instances {
+    Instance data[];
+} instances;
+
+layout(set = 0, binding = 1) writeonly buffer Commands {
+    DrawCommand data[];
+} commands;
+
+layout(set = 0, binding = 2) buffer Counter {
+    uint count;
+} counter;
+
+layout(push_constant) uniform PushConstants {
+    // normalized frustum planes, a point p is inside if dot(plane.xyz, p) + plane.w >= 0
+    vec4 planes[6];
+    uint instance_count;
+    uint vertex_count;
+} push_constants;
+
+void main() {
+    uint idx = gl_GlobalInvocationID.x;
+    if (idx >= push_constants.instance_count) {
+        return;
+    }
+
+    vec4 sphere = instances.data[idx].sphere;
+    for (int i = 0; i < 6; i++) {
+        vec4 plane = push_constants.planes[i];
+        if (dot(plane.xyz, sphere.xyz) + plane.w < -sphere.w) {
+            return;
+        }
+    }
+
+    // visible instances are packed at the start of the buffer, the rest stays zeroed and
+    // draws nothing
+    uint slot = atomicAdd(counter.count, 1);
+    commands.data[slot] = DrawCommand(push_constants.vertex_count, 1, 0, idx);
+}"
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec3 position;
+layout(location=1) in vec3 color;
+
+layout(location=0) out vec3 v_color;
+
+struct Instance {
+    vec4 sphere;
+    vec4 color;
+};
+
+layout(set = 0, binding = 0) readonly buffer Instances {
+    Instance data[];
+} instances;
+
+layout(push_constant) uniform PushConstants {
+    mat4 view_projection;
+} push_constants;
+
+void main() {
+    // the instance to draw is passed as first instance of the indirect draw command
+    Instance instance = instances.data[gl_InstanceIndex];
+
+    // scale the unit cube, so its corners touch the bounding sphere
+    vec3 world = instance.sphere.xyz + position * instance.sphere.w * 1.1547;
+    v_color = color * instance.color.rgb;
+    gl_Position = push_constants.view_projection * vec4(world, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec3 v_color;
+layout(location=0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(v_color, 1.0);
+}
+"
+    }
+}
+
+/// Bounding sphere and color of a cube, as stored in the instance buffer.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct CullInstance {
+    pub sphere: [f32; 4],
+    pub color: [f32; 4],
+}
+
+/// `count` cubes on a regular grid around the origin.
+pub fn grid_instances(count: u32) -> Vec<CullInstance> {
+    let side = (count as f32).cbrt().ceil().max(1.0) as u32;
+    let spacing = 2.0;
+    let extent = (side - 1) as f32 * spacing * 0.5;
+
+    (0..count)
+        .map(|i| {
+            let [x, y, z] = [i % side, (i / side) % side, i / (side * side)];
+            let position = [x, y, z].map(|c| c as f32 * spacing - extent);
+            let color = [x, y, z].map(|c| 0.3 + 0.7 * c as f32 / side as f32);
+            CullInstance {
+                sphere: [position[0], position[1], position[2], 0.5],
+                color: [color[0], color[1], color[2], 1.0],
+            }
+        })
+        .collect()
+}
+
+/// The camera sits in the middle of the grid and looks along -z, so most instances are outside
+/// of its view.
+pub fn culling_camera() -> Camera {
+    Camera {
+        eye: Point3::new(0.0, 0.0, 0.0),
+        target: Point3::new(0.0, 0.0, -1.0),
+        ..Camera::default()
+    }
+}
+
+/// The six planes bounding the view volume of `view_projection`, pointing inwards and
+/// normalized, so the signed distance of a point p to a plane is `dot(plane.xyz, p) + plane.w`.
+pub fn frustum_planes(view_projection: Matrix4<f32>) -> [[f32; 4]; 6] {
+    // cgmath matrices are stored by column
+    let row = |i: usize| {
+        Vector4::new(
+            view_projection.x[i],
+            view_projection.y[i],
+            view_projection.z[i],
+            view_projection.w[i],
+        )
+    };
+    let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+    // vulkan clip space: -w <= x, y <= w and 0 <= z <= w
+    let planes = [w + x, w - x, w + y, w - y, z, w - z];
+
+    let mut normalized = [[0.0; 4]; 6];
+    for (normalized, plane) in normalized.iter_mut().zip(planes.iter()) {
+        let length = plane.truncate().magnitude();
+        *normalized = (plane / length).into();
+    }
+    normalized
+}
+
+/// Signed distance of the sphere's surface to the closest plane, negative if the sphere lies
+/// completely outside of at least one of them.
+pub fn sphere_distance(planes: &[[f32; 4]; 6], sphere: [f32; 4]) -> f32 {
+    let center = Vector3::new(sphere[0], sphere[1], sphere[2]);
+    planes
+        .iter()
+        .map(|plane| Vector3::new(plane[0], plane[1], plane[2]).dot(center) + plane[3] + sphere[3])
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// The result of a culled render: the image and the instances the compute shader emitted a
+/// draw command for, in the order they were written.
+pub struct CullingResult {
+    pub image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    pub drawn: Vec<u32>,
+}
+
+/// Culls `instances` against the view of `camera` in a compute shader and draws the visible
+/// ones with indirect draw commands into `target`. The device needs the
+/// `draw_indirect_first_instance` feature.
+pub fn render(
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    target: &OffscreenTarget,
+    instances: &[CullInstance],
+    camera: &Camera,
+) -> CullingResult {
+    // the visible instances are drawn by index, passed as the first instance of their command
+    assert!(
+        device.enabled_features().draw_indirect_first_instance,
+        "culling needs the draw_indirect_first_instance feature, which the device doesn't support"
+    );
+
+    let cull_shader = cs::Shader::load(device.clone()).expect("failed to create compute shader");
+    let cull_pipeline = Arc::new(
+        ComputePipeline::new(
+            device.clone(),
+            &cull_shader.main_entry_point(),
+            &(),
+            Some(pipeline_cache::get(&device)),
+        )
+            .expect("failed to create compute pipeline"),
+    );
+
+    let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+    let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+    let builder = GraphicsPipeline::start()
+        .vertex_input_single_buffer::<CubeVertex>()
+        .vertex_shader(vertex_shader.main_entry_point(), ())
+        .triangle_list()
+        .viewports_dynamic_scissors_irrelevant(1)
+        .fragment_shader(fragment_shader.main_entry_point(), ());
+    let builder = if target.subpass().has_depth() {
+        builder.depth_stencil_simple_depth()
+    } else {
+        builder
+    };
+    let draw_pipeline = Arc::new(
+        builder
+            .render_pass(target.subpass())
+            .build_with_cache(pipeline_cache::get(&device))
+            .build(device.clone())
+            .unwrap(),
+    );
+
+    // indirect draws can't use an index buffer, so the strips of the cube are turned into a
+    // plain triangle list
+    let corners = cube_sample::cube_vertices();
+    let vertices: Vec<CubeVertex> = (0..6)
+        .flat_map(|face| [0, 1, 2, 0, 2, 3].iter().map(move |&i| face * 4 + i))
+        .map(|i| corners[i])
+        .collect();
+    let vertex_count = vertices.len() as u32;
+    let vertex_buffer =
+        CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::vertex_buffer(), false, vertices.into_iter())
+            .unwrap();
+
+    let instance_buffer = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage {
+            storage_buffer: true,
+            ..BufferUsage::none()
+        },
+        false,
+        instances.iter().cloned(),
+    )
+    .unwrap();
+
+    // one command per instance, in case all of them are visible
+    let command_buffer = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage {
+            indirect_buffer: true,
+            storage_buffer: true,
+            transfer_destination: true,
+            ..BufferUsage::none()
+        },
+        false,
+        instances.iter().map(|_| DrawIndirectCommand {
+            vertex_count: 0,
+            instance_count: 0,
+            first_vertex: 0,
+            first_instance: 0,
+        }),
+    )
+    .unwrap();
+
+    let counter_buffer = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage {
+            storage_buffer: true,
+            transfer_destination: true,
+            ..BufferUsage::none()
+        },
+        false,
+        std::iter::once(0u32),
+    )
+    .unwrap();
+
+    let cull_layout = cull_pipeline.layout().descriptor_set_layouts().get(0).unwrap();
+    let cull_set = Arc::new(
+        PersistentDescriptorSet::start(cull_layout.clone())
+            .add_buffer(instance_buffer.clone())
+            .unwrap()
+            .add_buffer(command_buffer.clone())
+            .unwrap()
+            .add_buffer(counter_buffer.clone())
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+
+    let draw_layout = draw_pipeline.layout().descriptor_set_layouts().get(0).unwrap();
+    let draw_set = Arc::new(
+        PersistentDescriptorSet::start(draw_layout.clone())
+            .add_buffer(instance_buffer.clone())
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+
+    let [width, height] = target.dimensions();
+    let view_projection = camera.view_projection(width as f32 / height as f32);
+    let instance_count = instances.len() as u32;
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        device.clone(),
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+
+    // the commands and the counter are reset on the gpu, the barriers between the fill, the
+    // compute shader writing the commands and the draw reading them are added by the builder
+    builder
+        .fill_buffer(command_buffer.clone(), 0)
+        .unwrap()
+        .fill_buffer(counter_buffer.clone(), 0)
+        .unwrap()
+        .dispatch(
+            [(instance_count + 63) / 64, 1, 1],
+            cull_pipeline.clone(),
+            cull_set,
+            cs::ty::PushConstants {
+                planes: frustum_planes(view_projection),
+                instance_count,
+                vertex_count,
+            },
+        )
+        .unwrap()
+        .begin_render_pass(
+            target.framebuffer(),
+            SubpassContents::Inline,
+            target.clear_values([0.0, 0.0, 0.0, 1.0]),
+        )
+        .unwrap();
+
+    // without the `multi_draw_indirect` feature only a single command can be drawn per call
+    let limit = if device.enabled_features().multi_draw_indirect {
+        device.physical_device().properties().max_draw_indirect_count.max(1) as u64
+    } else {
+        1
+    };
+    let dynamic_state = target.dynamic_state();
+    let mut start = 0;
+    while start < command_buffer.len() {
+        let end = (start + limit).min(command_buffer.len());
+        builder
+            .draw_indirect(
+                draw_pipeline.clone(),
+                &dynamic_state,
+                vertex_buffer.clone(),
+                command_buffer.clone().into_buffer_slice().slice(start..end).unwrap(),
+                draw_set.clone(),
+                vs::ty::PushConstants {
+                    view_projection: view_projection.into(),
+                },
+            )
+            .unwrap();
+        start = end;
+    }
+
+    builder.end_render_pass().unwrap();
+    target.record_readback(&mut builder);
+
+    let command = builder.build().unwrap();
+    let finished = command.execute(queue).unwrap();
+    finished.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+    let count = counter_buffer.read().unwrap()[0] as usize;
+    let commands = command_buffer.read().unwrap();
+    CullingResult {
+        image: target.read(),
+        drawn: commands[..count].iter().map(|command| command.first_instance).collect(),
+    }
+}
+
+/// `culling-sample` culls `--instances N` cubes on the gpu and draws the visible ones with
+/// indirect draws into `culling.png`.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let target = OffscreenTarget::start(device.clone(), options.size)
+        .samples(samples)
+        .depth_format(depth::choose_depth_format(device.physical_device()))
+        .build();
+
+    let instances = grid_instances(options.instances);
+    let result = render(queue, device, &target, &instances, &culling_camera());
+
+    println!("drew {} of {} instances", result.drawn.len(), instances.len());
+    result
+        .image
+        .save(options.output.as_deref().unwrap_or("culling.png"))
+        .unwrap();
+}
@@ -0,0 +1,54 @@
+use vulkano::device::Device;
+use vulkano::device::Queue;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::collections::HashSet;
+
+use crate::culling_sample;
+use crate::depth;
+use crate::offscreen::OffscreenTarget;
+
+// spheres closer than this to a plane may end up on either side, depending on rounding
+const TOLERANCE: f32 = 1e-3;
+
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>) {
+    println!("performing culling test");
+
+    let target = OffscreenTarget::start(device.clone(), [256, 256])
+        .depth_format(depth::choose_depth_format(device.physical_device()))
+        .build();
+    let camera = culling_sample::culling_camera();
+    let instances = culling_sample::grid_instances(4096);
+
+    let result = culling_sample::render(queue, device, &target, &instances, &camera);
+
+    // every instance is drawn at most once
+    let drawn: HashSet<u32> = result.drawn.iter().cloned().collect();
+    assert_eq!(drawn.len(), result.drawn.len(), "instances were drawn more than once");
+
+    // compare with the visible set calculated on the cpu, from the same planes (the target is
+    // square, so the aspect ratio is 1)
+    let planes = culling_sample::frustum_planes(camera.view_projection(1.0));
+    let mut visible = 0;
+    for (i, instance) in instances.iter().enumerate() {
+        let distance = culling_sample::sphere_distance(&planes, instance.sphere);
+        let is_drawn = drawn.contains(&(i as u32));
+        if distance > TOLERANCE {
+            assert!(is_drawn, "visible instance {} was culled", i);
+            visible += 1;
+        } else if distance < -TOLERANCE {
+            assert!(!is_drawn, "invisible instance {} was drawn", i);
+        } else if is_drawn {
+            visible += 1;
+        }
+    }
+    assert_eq!(result.drawn.len(), visible);
+
+    // the camera looks into the grid, so some but far from all cubes are visible
+    assert!(visible > 0 && visible < instances.len() / 2);
+    println!("{} of {} instances visible", visible, instances.len());
+
+    println!("Culling test successful!");
+}
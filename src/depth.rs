@@ -0,0 +1,24 @@
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::format::Format;
+
+// depth formats in order of preference, only `D16Unorm` is guaranteed to be supported
+const CANDIDATES: [Format; 4] = [
+    Format::D32Sfloat,
+    Format::D32Sfloat_S8Uint,
+    Format::D24Unorm_S8Uint,
+    Format::D16Unorm,
+];
+
+/// Picks the most precise depth format the device supports as a depth attachment.
+pub fn choose_depth_format(physical: PhysicalDevice) -> Format {
+    CANDIDATES
+        .iter()
+        .cloned()
+        .find(|format| {
+            format
+                .properties(physical)
+                .optimal_tiling_features
+                .depth_stencil_attachment
+        })
+        .unwrap_or(Format::D16Unorm)
+}
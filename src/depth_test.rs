@@ -0,0 +1,46 @@
+use vulkano::device::Device;
+use vulkano::device::Queue;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use cgmath::Rad;
+use image::Rgba;
+
+use crate::cube_sample;
+use crate::depth;
+use crate::offscreen::OffscreenTarget;
+
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>) {
+    println!("performing depth test");
+
+    let depth_format = depth::choose_depth_format(device.physical_device());
+    println!("using depth format {:?}", depth_format);
+
+    // the back face of the cube is drawn after the front face, so without a depth buffer it
+    // covers the front face
+    let target = OffscreenTarget::start(device.clone(), [256, 256]).build();
+    let image = cube_sample::render(queue.clone(), device.clone(), &target, Rad(0.0));
+    assert_eq!(*image.get_pixel(128, 128), to_rgba(cube_sample::BACK));
+
+    // with the depth test, only the front face closest to the camera is visible
+    let target = OffscreenTarget::start(device.clone(), [256, 256])
+        .depth_format(depth_format)
+        .build();
+    let image = cube_sample::render(queue.clone(), device.clone(), &target, Rad(0.0));
+    assert_eq!(*image.get_pixel(128, 128), to_rgba(cube_sample::FRONT));
+
+    // the background around the cube stays cleared
+    assert_eq!(*image.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+
+    println!("Depth test successful!");
+}
+
+fn to_rgba(color: [f32; 3]) -> Rgba<u8> {
+    Rgba([
+        (color[0] * 255.0) as u8,
+        (color[1] * 255.0) as u8,
+        (color[2] * 255.0) as u8,
+        255,
+    ])
+}
@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// number of frames the summaries are calculated over
+const WINDOW: usize = 1000;
+
+/// What the cpu spent on one frame.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameTimes {
+    // from the start of the frame until it has been submitted
+    pub cpu: Duration,
+    // waiting for a swapchain image
+    pub acquire: Duration,
+    // submitting the command buffer and queueing the present
+    pub submit: Duration,
+}
+
+/// Collects the times of the frames drawn into a window, keeps the most recent ones to summarize
+/// them and optionally writes every frame to a CSV file.
+#[derive(Default)]
+pub struct FrameStats {
+    // the times of the most recent frames, with the time since the previous present
+    frames: VecDeque<(FrameTimes, Duration)>,
+    count: u64,
+    last_present: Option<Instant>,
+    csv: Option<BufWriter<File>>,
+}
+
+impl FrameStats {
+    /// Writes the times of every frame recorded from now on to `path`, in milliseconds.
+    pub fn write_csv(&mut self, path: &Path) -> io::Result<()> {
+        let mut csv = BufWriter::new(File::create(path)?);
+        writeln!(csv, "frame,cpu_ms,acquire_ms,submit_ms,present_interval_ms")?;
+        self.csv = Some(csv);
+        Ok(())
+    }
+
+    /// Adds the times of a frame which has just been presented.
+    pub fn record(&mut self, times: FrameTimes) {
+        let now = Instant::now();
+        // the first frame has no previous one to measure the interval from
+        let interval = self.last_present.map_or(Duration::default(), |last| now - last);
+        self.last_present = Some(now);
+
+        if let Some(csv) = &mut self.csv {
+            let result = writeln!(
+                csv,
+                "{},{:.3},{:.3},{:.3},{:.3}",
+                self.count,
+                milliseconds(times.cpu),
+                milliseconds(times.acquire),
+                milliseconds(times.submit),
+                milliseconds(interval),
+            );
+            if let Err(err) = result {
+                eprintln!("failed to write frame times, stopping: {}", err);
+                self.csv = None;
+            }
+        }
+
+        if self.frames.len() == WINDOW {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((times, interval));
+        self.count += 1;
+    }
+
+    /// Writes the buffered frames to the CSV file. The event loop exits the process without
+    /// dropping anything, so this has to be called before.
+    pub fn flush(&mut self) {
+        if let Some(csv) = &mut self.csv {
+            if let Err(err) = csv.flush() {
+                eprintln!("failed to write frame times: {}", err);
+            }
+        }
+    }
+
+    /// Frames per second over the most recent frames.
+    pub fn fps(&self) -> f32 {
+        let intervals = self.frames.iter().skip(1).map(|&(_, interval)| interval);
+        let total: Duration = intervals.sum();
+        if total.is_zero() {
+            0.0
+        } else {
+            (self.frames.len() - 1) as f32 / total.as_secs_f32()
+        }
+    }
+
+    /// Minimum, average and 99th percentile of every time over the most recent frames.
+    pub fn summary(&self) -> Summary {
+        let times: Vec<FrameTimes> = self.frames.iter().map(|&(times, _)| times).collect();
+        Summary {
+            frames: self.frames.len(),
+            fps: self.fps(),
+            cpu: Distribution::of(times.iter().map(|times| times.cpu)),
+            acquire: Distribution::of(times.iter().map(|times| times.acquire)),
+            submit: Distribution::of(times.iter().map(|times| times.submit)),
+            present_interval: Distribution::of(self.frames.iter().skip(1).map(|&(_, interval)| interval)),
+        }
+    }
+}
+
+pub struct Summary {
+    frames: usize,
+    fps: f32,
+    cpu: Distribution,
+    acquire: Distribution,
+    submit: Distribution,
+    present_interval: Distribution,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:.1} fps over the last {} frames (min/avg/p99):", self.fps, self.frames)?;
+        writeln!(f, "  cpu              {}", self.cpu)?;
+        writeln!(f, "  acquire          {}", self.acquire)?;
+        writeln!(f, "  submit           {}", self.submit)?;
+        write!(f, "  present interval {}", self.present_interval)
+    }
+}
+
+/// Minimum, average and 99th percentile of a set of durations.
+struct Distribution {
+    min: Duration,
+    average: Duration,
+    p99: Duration,
+}
+
+impl Distribution {
+    fn of<I: Iterator<Item = Duration>>(durations: I) -> Distribution {
+        let mut sorted: Vec<Duration> = durations.collect();
+        if sorted.is_empty() {
+            return Distribution {
+                min: Duration::default(),
+                average: Duration::default(),
+                p99: Duration::default(),
+            };
+        }
+        sorted.sort();
+
+        let total: Duration = sorted.iter().sum();
+        Distribution {
+            min: sorted[0],
+            average: total / sorted.len() as u32,
+            // the smallest time which at least 99% of the frames didn't exceed
+            p99: sorted[(sorted.len() * 99 + 99) / 100 - 1],
+        }
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:.2} / {:.2} / {:.2} ms",
+            milliseconds(self.min),
+            milliseconds(self.average),
+            milliseconds(self.p99),
+        )
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,163 @@
+use vulkano::device::Device;
+use vulkano::sync::{self, FenceSignalFuture, GpuFuture};
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The fence signaled when the gpu is done with a submitted frame.
+pub type FrameFence = FenceSignalFuture<Box<dyn GpuFuture>>;
+
+/// Keeps up to a fixed number of frames in flight, each with its own resources.
+///
+/// Every frame slot has a fence and the resources `R` only used by the frames recorded in that
+/// slot, e.g. the uniform buffers. Before a slot is used again, the cpu waits for the fence of the
+/// frame which used it last, so the resources can be written without waiting for more recent
+/// frames. With one frame in flight the cpu waits for every frame before recording the next one
+/// (lowest latency), with more frames the cpu can record ahead while the gpu is still busy
+/// (highest throughput).
+///
+/// The command buffers of a frame, its secondary ones included, are kept alive by its fence.
+/// They go back to the standard command pool of the device, to be reused by a later frame, when
+/// the slot replaces the fence after waiting for it. vulkano's builders can't allocate from any
+/// other pool, so the slots don't have one of their own.
+pub struct FramesInFlight<R> {
+    device: Arc<Device>,
+    frames: Vec<Frame<R>>,
+    // the slot the next frame is recorded in
+    current: usize,
+    // the fence of the last frame which was submitted successfully, the next frame starts after
+    // it
+    last: Option<Arc<FrameFence>>,
+    stats: WaitStats,
+}
+
+struct Frame<R> {
+    // unset before the first submission of the slot, and after a failed one
+    fence: Option<Arc<FrameFence>>,
+    resources: R,
+}
+
+impl<R> FramesInFlight<R> {
+    /// `count` frame slots (at least one), with the resources created by `resources` for each
+    /// slot index.
+    pub fn new<F>(device: Arc<Device>, count: usize, mut resources: F) -> FramesInFlight<R>
+    where
+        F: FnMut(usize) -> R,
+    {
+        let frames = (0..count.max(1))
+            .map(|index| Frame {
+                fence: None,
+                resources: resources(index),
+            })
+            .collect();
+
+        FramesInFlight {
+            device,
+            frames,
+            current: 0,
+            last: None,
+            stats: WaitStats::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Blocks until the gpu is done with the last frame recorded in the current slot, and returns
+    /// the resources of the slot. The time spent waiting is added to the stats.
+    ///
+    /// Can be called again if the frame isn't submitted after all (e.g. when the swapchain is
+    /// out of date), it doesn't wait a second time.
+    pub fn wait(&mut self) -> &mut R {
+        let start = Instant::now();
+        let frame = &mut self.frames[self.current];
+        if let Some(fence) = &frame.fence {
+            fence.wait(None).expect("failed to wait for frame");
+        }
+        self.stats.add(start.elapsed());
+
+        &mut frame.resources
+    }
+
+    /// The future the submission of the current frame has to start from: the last submission
+    /// which succeeded, so the frames execute in order and use the images they share, e.g. the
+    /// depth buffer, one after another.
+    ///
+    /// A failed submission isn't waited for. vulkano blocks until the queue is idle when it drops
+    /// the future of the failed submission, so the frames before it are done as well.
+    pub fn previous_future(&self) -> Box<dyn GpuFuture> {
+        match &self.last {
+            Some(fence) => Box::new(fence.clone()),
+            None => sync::now(self.device.clone()).boxed(),
+        }
+    }
+
+    /// Keeps the fence of the submitted frame, or `None` if the submission failed, in the current
+    /// slot and moves on to the next slot.
+    pub fn submitted(&mut self, fence: Option<FrameFence>) {
+        let fence = fence.map(Arc::new);
+        if fence.is_some() {
+            self.last = fence.clone();
+        }
+        self.frames[self.current].fence = fence;
+        self.current = (self.current + 1) % self.frames.len();
+    }
+
+    /// Blocks until the gpu is done with all frames, e.g. to read what they rendered.
+    pub fn wait_all(&mut self) {
+        for frame in &self.frames {
+            if let Some(fence) = &frame.fence {
+                fence.wait(None).expect("failed to wait for frame");
+            }
+        }
+    }
+
+    /// Frees the resources of the frames the gpu is done with, without blocking.
+    pub fn cleanup_finished(&mut self) {
+        for frame in &mut self.frames {
+            if let Some(fence) = &mut frame.fence {
+                fence.cleanup_finished();
+            }
+        }
+    }
+
+    pub fn stats(&self) -> &WaitStats {
+        &self.stats
+    }
+}
+
+/// How long the cpu waited for the frame slots to become available.
+#[derive(Default)]
+pub struct WaitStats {
+    frames: u32,
+    total: Duration,
+    max: Duration,
+}
+
+impl WaitStats {
+    fn add(&mut self, wait: Duration) {
+        self.frames += 1;
+        self.total += wait;
+        self.max = self.max.max(wait);
+    }
+
+    pub fn average(&self) -> Duration {
+        self.total / self.frames.max(1)
+    }
+}
+
+impl fmt::Display for WaitStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "waited {:.2?} per frame on average, at most {:.2?}, over {} frames",
+            self.average(),
+            self.max,
+            self.frames,
+        )
+    }
+}
@@ -0,0 +1,579 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::{ImageDimensions, ImmutableImage, MipmapsCount};
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::blend::{AttachmentBlend, BlendFactor, BlendOp};
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::pipeline::viewport::Scissor;
+use vulkano::render_pass::Subpass;
+use vulkano::sampler::{Filter, SamplerAddressMode};
+use vulkano::swapchain::PresentMode;
+use vulkano::sync::GpuFuture;
+
+use winit::event::{ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::time::Instant;
+
+use egui::{ClippedMesh, Color32, CtxRef, Event, Key, Modifiers, PointerButton, Pos2, RawInput, Rect, Rgba, Slider, TextureId, Vec2};
+
+use crate::mandelbrot_sample::MandelbrotParameters;
+use crate::pipeline_cache;
+use crate::texture::{self, SamplerOptions};
+
+// points a mouse wheel scrolls for one line
+const POINTS_PER_LINE: f32 = 50.0;
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec2 position;
+layout(location=1) in vec2 uv;
+layout(location=2) in vec4 color;
+
+layout(location=0) out vec2 v_uv;
+layout(location=1) out vec4 v_color;
+
+// the positions are in points from the top left corner of the screen
+layout(push_constant) uniform PushConstants {
+    vec2 screen_size;
+} push_constants;
+
+void main() {
+    v_uv = uv;
+    v_color = color;
+    gl_Position = vec4(position / push_constants.screen_size * 2.0 - 1.0, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec4 v_color;
+layout(location=0) out vec4 f_color;
+
+// the coverage of the glyphs and shapes of egui, white with the alpha in the red channel
+layout(set=0, binding=0) uniform sampler2D font;
+
+void main() {
+    // the colors are premultiplied by their alpha
+    f_color = v_color * texture(font, v_uv).r;
+}
+"
+    }
+}
+
+/// The values which can be changed in the gui while the program runs, and the window with the
+/// widgets to change them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameters {
+    pub clear_color: [f32; 3],
+    // the vertices of the triangle, if it is drawn
+    pub triangle: Option<[[f32; 2]; 3]>,
+    // the view of the Mandelbrot set, if it is drawn
+    pub mandelbrot: Option<MandelbrotParameters>,
+    pub present_mode: PresentMode,
+}
+
+impl Parameters {
+    /// Shows the window with a widget for every parameter. Only the present modes in
+    /// `present_modes` can be chosen.
+    pub fn ui(&mut self, ctx: &CtxRef, present_modes: &[PresentMode]) {
+        egui::Window::new("parameters")
+            .default_pos([8.0, 120.0])
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("clear color");
+                for (channel, name) in self.clear_color.iter_mut().zip(["r", "g", "b"]) {
+                    ui.add(Slider::new(channel, 0.0..=1.0).text(name));
+                }
+
+                if let Some(triangle) = &mut self.triangle {
+                    ui.separator();
+                    ui.label("triangle");
+                    for (i, vertex) in triangle.iter_mut().enumerate() {
+                        ui.add(Slider::new(&mut vertex[0], -1.0..=1.0).text(format!("vertex {} x", i)));
+                        ui.add(Slider::new(&mut vertex[1], -1.0..=1.0).text(format!("vertex {} y", i)));
+                    }
+                }
+
+                if let Some(mandelbrot) = &mut self.mandelbrot {
+                    ui.separator();
+                    ui.label("mandelbrot");
+                    ui.add(Slider::new(&mut mandelbrot.center[0], -2.0..=1.0).text("center re"));
+                    ui.add(Slider::new(&mut mandelbrot.center[1], -1.5..=1.5).text("center im"));
+                    ui.add(Slider::new(&mut mandelbrot.zoom, 0.5..=10_000.0).logarithmic(true).text("zoom"));
+                    ui.add(Slider::new(&mut mandelbrot.iterations, 1..=2000).text("iterations"));
+                }
+
+                ui.separator();
+                ui.label("present mode");
+                ui.horizontal(|ui| {
+                    for &mode in present_modes {
+                        ui.selectable_value(&mut self.present_mode, mode, format!("{:?}", mode));
+                    }
+                });
+            });
+    }
+}
+
+/// The input of a window, translated from the events of winit into the `RawInput` of egui.
+///
+/// The events are collected as they arrive, `take` hands them to the next frame of the gui.
+/// Positions are converted from the physical pixels of winit into the points of egui.
+#[derive(Debug, Clone)]
+pub struct GuiInput {
+    pixels_per_point: f32,
+    // in points from the top left corner, `None` while outside of the window
+    pointer: Option<Pos2>,
+    modifiers: Modifiers,
+    // the events and the scrolling since the last `take`
+    events: Vec<Event>,
+    scroll: Vec2,
+}
+
+impl GuiInput {
+    /// `pixels_per_point` is the scale factor of the window.
+    pub fn new(pixels_per_point: f32) -> GuiInput {
+        GuiInput {
+            pixels_per_point,
+            pointer: None,
+            modifiers: Modifiers::default(),
+            events: Vec::new(),
+            scroll: Vec2::ZERO,
+        }
+    }
+
+    pub fn pixels_per_point(&self) -> f32 {
+        self.pixels_per_point
+    }
+
+    /// Collects an event of the window. Events egui doesn't use are ignored.
+    pub fn window_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let pointer = (Vec2::new(position.x as f32, position.y as f32) / self.pixels_per_point).to_pos2();
+                self.pointer = Some(pointer);
+                self.events.push(Event::PointerMoved(pointer));
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.pointer = None;
+                self.events.push(Event::PointerGone);
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                // a button is pressed wherever the pointer was moved to last
+                if let (Some(pos), Some(button)) = (self.pointer, pointer_button(button)) {
+                    self.events.push(Event::PointerButton {
+                        pos,
+                        button,
+                        pressed: state == ElementState::Pressed,
+                        modifiers: self.modifiers,
+                    });
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(x, y) * POINTS_PER_LINE,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        Vec2::new(position.x as f32, position.y as f32) / self.pixels_per_point
+                    }
+                };
+            }
+            // the control characters (like backspace) are sent as keys, and some platforms send
+            // keys like the arrows as characters of the private use area as well
+            WindowEvent::ReceivedCharacter(c) if !c.is_control() && !('\u{e000}'..='\u{f8ff}').contains(&c) => {
+                self.events.push(Event::Text(c.to_string()));
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    virtual_keycode: Some(keycode),
+                    state,
+                    ..
+                },
+                ..
+            } => {
+                if let Some(key) = key(keycode) {
+                    self.events.push(Event::Key {
+                        key,
+                        pressed: state == ElementState::Pressed,
+                        modifiers: self.modifiers,
+                    });
+                }
+            }
+            WindowEvent::ModifiersChanged(state) => self.modifiers = modifiers(state),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => self.pixels_per_point = scale_factor as f32,
+            _ => (),
+        }
+    }
+
+    /// The input of the next frame of the gui, for a window of `dimensions` pixels, `time`
+    /// seconds after the gui was started. The collected events are passed on and cleared.
+    pub fn take(&mut self, dimensions: [u32; 2], time: f64) -> RawInput {
+        let size = Vec2::new(dimensions[0] as f32, dimensions[1] as f32) / self.pixels_per_point;
+        RawInput {
+            scroll_delta: std::mem::take(&mut self.scroll),
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, size)),
+            pixels_per_point: Some(self.pixels_per_point),
+            time: Some(time),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            ..RawInput::default()
+        }
+    }
+}
+
+/// An immediate mode gui (egui) drawn on top of a window.
+///
+/// The events of the window are passed to `window_event` as they arrive, `run` builds the
+/// widgets of a frame once per frame and tessellates them into meshes, which a `GuiPainter`
+/// draws.
+pub struct Gui {
+    ctx: CtxRef,
+    input: GuiInput,
+    start: Instant,
+    // the meshes of the last frame
+    meshes: Vec<ClippedMesh>,
+}
+
+impl Gui {
+    pub fn new(pixels_per_point: f32) -> Gui {
+        Gui {
+            ctx: CtxRef::default(),
+            input: GuiInput::new(pixels_per_point),
+            start: Instant::now(),
+            meshes: Vec::new(),
+        }
+    }
+
+    pub fn ctx(&self) -> &CtxRef {
+        &self.ctx
+    }
+
+    pub fn pixels_per_point(&self) -> f32 {
+        self.input.pixels_per_point()
+    }
+
+    pub fn meshes(&self) -> &[ClippedMesh] {
+        &self.meshes
+    }
+
+    /// Collects an event of the window, returns whether the gui used it. Used events shouldn't
+    /// move the camera as well, e.g. dragging a slider.
+    ///
+    /// Presses and scrolling are used while the pointer is over the gui (or dragging in it), key
+    /// presses and characters while a widget has the keyboard focus. Releases are never used, so
+    /// a drag which started outside of the gui ends wherever the button is released.
+    pub fn window_event(&mut self, event: &WindowEvent) -> bool {
+        self.input.window_event(event);
+        match *event {
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                ..
+            }
+            | WindowEvent::MouseWheel { .. } => self.ctx.wants_pointer_input(),
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    ..
+                },
+                ..
+            }
+            | WindowEvent::ReceivedCharacter(_) => self.ctx.wants_keyboard_input(),
+            _ => false,
+        }
+    }
+
+    /// Builds the widgets of a frame with `ui`, for a window of `dimensions` pixels, with the
+    /// events collected since the last frame.
+    pub fn run(&mut self, dimensions: [u32; 2], ui: impl FnOnce(&CtxRef)) {
+        let input = self.input.take(dimensions, self.start.elapsed().as_secs_f64());
+        self.ctx.begin_frame(input);
+        ui(&self.ctx);
+        // the output (like the cursor icon the widgets ask for) is ignored
+        let (_, shapes) = self.ctx.end_frame();
+        self.meshes = self.ctx.tessellate(shapes);
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct GuiVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+vulkano::impl_vertex!(GuiVertex, position, uv, color);
+
+/// Draws the meshes of a `Gui` on top of what has been drawn before.
+///
+/// Like the `TextRenderer`, the pipeline has no depth test, it is built for an overlay subpass.
+/// Every mesh is clipped to its rectangle with a dynamic scissor. The font texture of egui is
+/// uploaded again whenever it changes, textures of the user aren't supported.
+pub struct GuiPainter {
+    queue: Arc<Queue>,
+    pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    // whether the colors are written to an sRGB attachment, which expects them to be linear
+    srgb: bool,
+    // the version of the uploaded font texture and the descriptor set with it
+    font: Option<(u64, Arc<dyn DescriptorSet + Send + Sync>)>,
+}
+
+impl GuiPainter {
+    pub fn new(queue: Arc<Queue>, subpass: Subpass) -> GuiPainter {
+        let device = queue.device().clone();
+
+        // load shader for device
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        // the colors of egui are premultiplied by their alpha
+        let blend = AttachmentBlend {
+            enabled: true,
+            color_op: BlendOp::Add,
+            color_source: BlendFactor::One,
+            color_destination: BlendFactor::OneMinusSrcAlpha,
+            alpha_op: BlendOp::Add,
+            alpha_source: BlendFactor::OneMinusDstAlpha,
+            alpha_destination: BlendFactor::One,
+            mask_red: true,
+            mask_green: true,
+            mask_blue: true,
+            mask_alpha: true,
+        };
+
+        let render_pass = subpass.render_pass().desc();
+        let color_attachment = render_pass.subpasses()[subpass.index() as usize].color_attachments[0].0;
+        let srgb = texture::is_srgb(render_pass.attachments()[color_attachment].format);
+
+        let pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input_single_buffer::<GuiVertex>()
+                .vertex_shader(vertex_shader.main_entry_point(), ())
+                .triangle_list()
+                // the scissors clip the meshes to their rectangles
+                .viewports_scissors_dynamic(1)
+                .cull_mode_disabled()
+                .fragment_shader(fragment_shader.main_entry_point(), ())
+                .blend_collective(blend)
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        GuiPainter {
+            queue,
+            pipeline,
+            srgb,
+            font: None,
+        }
+    }
+
+    /// Draws the meshes of the last frame of `gui` into the viewport of `dynamic_state`.
+    pub fn draw(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+        gui: &Gui,
+    ) {
+        if gui.meshes().is_empty() {
+            return;
+        }
+
+        let set = self.font_set(&gui.ctx().texture());
+        let viewport = dynamic_state
+            .viewports
+            .as_ref()
+            .and_then(|viewports| viewports.first())
+            .expect("the gui is drawn into a dynamic viewport")
+            .dimensions;
+        let pixels_per_point = gui.pixels_per_point();
+        let screen_size = [viewport[0] / pixels_per_point, viewport[1] / pixels_per_point];
+        let device = self.queue.device();
+        let srgb = self.srgb;
+
+        for ClippedMesh(clip, mesh) in gui.meshes() {
+            if mesh.texture_id != TextureId::Egui || mesh.indices.is_empty() {
+                continue;
+            }
+            let scissor = match scissor(*clip, pixels_per_point, viewport) {
+                Some(scissor) => scissor,
+                None => continue,
+            };
+
+            // the meshes change every frame, like the quads of the text
+            let vertex_buffer = CpuAccessibleBuffer::from_iter(
+                device.clone(),
+                BufferUsage::vertex_buffer(),
+                false,
+                mesh.vertices.iter().map(|vertex| GuiVertex {
+                    position: [vertex.pos.x, vertex.pos.y],
+                    uv: [vertex.uv.x, vertex.uv.y],
+                    color: color(vertex.color, srgb),
+                }),
+            )
+            .unwrap();
+            let index_buffer = CpuAccessibleBuffer::from_iter(
+                device.clone(),
+                BufferUsage::index_buffer(),
+                false,
+                mesh.indices.iter().cloned(),
+            )
+            .unwrap();
+
+            let dynamic_state = DynamicState {
+                scissors: Some(vec![scissor]),
+                ..dynamic_state.clone()
+            };
+            builder
+                .draw_indexed(
+                    self.pipeline.clone(),
+                    &dynamic_state,
+                    vertex_buffer,
+                    index_buffer,
+                    set.clone(),
+                    vs::ty::PushConstants { screen_size },
+                )
+                .unwrap();
+        }
+    }
+
+    // the descriptor set with the font texture, uploaded again if it changed since the last
+    // frame, waiting until the upload has finished
+    fn font_set(&mut self, font: &egui::Texture) -> Arc<dyn DescriptorSet + Send + Sync> {
+        if let Some((version, set)) = &self.font {
+            if *version == font.version {
+                return set.clone();
+            }
+        }
+
+        let (texture, upload) = ImmutableImage::from_iter(
+            font.pixels.iter().cloned(),
+            ImageDimensions::Dim2d {
+                width: font.width as u32,
+                height: font.height as u32,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8Unorm,
+            self.queue.clone(),
+        )
+        .expect("failed to create gui font texture");
+
+        upload
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let sampler = SamplerOptions {
+            filter: Filter::Linear,
+            address_mode: SamplerAddressMode::ClampToEdge,
+            anisotropy: 1.0,
+        }
+        .build(&self.queue);
+
+        let layout = self.pipeline.layout().descriptor_set_layouts().first().unwrap();
+        let set: Arc<dyn DescriptorSet + Send + Sync> = Arc::new(
+            PersistentDescriptorSet::start(layout.clone())
+                .add_sampled_image(ImageView::new(texture).unwrap(), sampler)
+                .unwrap()
+                .build()
+                .unwrap(),
+        );
+        self.font = Some((font.version, set.clone()));
+        set
+    }
+}
+
+// the premultiplied sRGB color of egui, converted to linear for sRGB attachments
+fn color(color: Color32, srgb: bool) -> [f32; 4] {
+    if srgb {
+        Rgba::from(color).to_array()
+    } else {
+        let [r, g, b, a] = color.to_array();
+        [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0]
+    }
+}
+
+// the part of the viewport `clip` (in points) covers, `None` if it doesn't cover any pixels
+fn scissor(clip: Rect, pixels_per_point: f32, viewport: [f32; 2]) -> Option<Scissor> {
+    let min = (clip.min.to_vec2() * pixels_per_point).round();
+    let max = (clip.max.to_vec2() * pixels_per_point).round();
+    let min = [min.x.clamp(0.0, viewport[0]), min.y.clamp(0.0, viewport[1])];
+    let max = [max.x.clamp(0.0, viewport[0]), max.y.clamp(0.0, viewport[1])];
+    if max[0] <= min[0] || max[1] <= min[1] {
+        return None;
+    }
+
+    Some(Scissor {
+        origin: [min[0] as i32, min[1] as i32],
+        dimensions: [(max[0] - min[0]) as u32, (max[1] - min[1]) as u32],
+    })
+}
+
+fn pointer_button(button: MouseButton) -> Option<PointerButton> {
+    match button {
+        MouseButton::Left => Some(PointerButton::Primary),
+        MouseButton::Right => Some(PointerButton::Secondary),
+        MouseButton::Middle => Some(PointerButton::Middle),
+        MouseButton::Other(_) => None,
+    }
+}
+
+fn modifiers(state: ModifiersState) -> Modifiers {
+    // shortcuts use the command key on macOS, and control everywhere else
+    let mac = cfg!(target_os = "macos");
+    Modifiers {
+        alt: state.alt(),
+        ctrl: state.ctrl(),
+        shift: state.shift(),
+        mac_cmd: mac && state.logo(),
+        command: if mac { state.logo() } else { state.ctrl() },
+    }
+}
+
+// the keys used to edit text and to move between widgets, and the ones of the shortcuts of
+// the text fields
+fn key(keycode: VirtualKeyCode) -> Option<Key> {
+    Some(match keycode {
+        VirtualKeyCode::Down => Key::ArrowDown,
+        VirtualKeyCode::Left => Key::ArrowLeft,
+        VirtualKeyCode::Right => Key::ArrowRight,
+        VirtualKeyCode::Up => Key::ArrowUp,
+        VirtualKeyCode::Escape => Key::Escape,
+        VirtualKeyCode::Tab => Key::Tab,
+        VirtualKeyCode::Back => Key::Backspace,
+        VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Key::Enter,
+        VirtualKeyCode::Space => Key::Space,
+        VirtualKeyCode::Insert => Key::Insert,
+        VirtualKeyCode::Delete => Key::Delete,
+        VirtualKeyCode::Home => Key::Home,
+        VirtualKeyCode::End => Key::End,
+        VirtualKeyCode::PageUp => Key::PageUp,
+        VirtualKeyCode::PageDown => Key::PageDown,
+        VirtualKeyCode::A => Key::A,
+        VirtualKeyCode::K => Key::K,
+        VirtualKeyCode::U => Key::U,
+        VirtualKeyCode::W => Key::W,
+        VirtualKeyCode::Z => Key::Z,
+        _ => return None,
+    })
+}
@@ -0,0 +1,102 @@
+use vulkano::swapchain::PresentMode;
+
+use winit::event::{ElementState, MouseButton, VirtualKeyCode, WindowEvent};
+
+use egui::{Event, Key, PointerButton, Pos2, TextureId, Vec2};
+
+use crate::gui::{Gui, GuiInput, Parameters};
+use crate::input_test::{button, cursor, device_id, key, scroll};
+use crate::mandelbrot_sample::MandelbrotParameters;
+
+/// Feeds made up events into the gui and checks what it makes of them. Like the input test, this
+/// doesn't need the gpu: the widgets are built and tessellated, but not drawn.
+pub fn execute() {
+    println!("performing gui test");
+
+    // positions are converted from pixels to points
+    let mut input = GuiInput::new(2.0);
+    input.window_event(&cursor(100.0, 40.0));
+    input.window_event(&button(MouseButton::Left, ElementState::Pressed));
+    input.window_event(&scroll(1.0));
+    input.window_event(&WindowEvent::ReceivedCharacter('a'));
+    // control characters are sent as keys
+    input.window_event(&WindowEvent::ReceivedCharacter('\u{8}'));
+    input.window_event(&key(VirtualKeyCode::Back, ElementState::Pressed));
+    // keys egui doesn't use aren't passed on
+    input.window_event(&key(VirtualKeyCode::F1, ElementState::Pressed));
+    input.window_event(&WindowEvent::CursorLeft { device_id: device_id() });
+
+    let raw = input.take([800, 600], 1.5);
+    assert_eq!(raw.screen_rect.unwrap().size(), Vec2::new(400.0, 300.0));
+    assert_eq!((raw.pixels_per_point, raw.time), (Some(2.0), Some(1.5)));
+    assert!(raw.scroll_delta.y > 0.0 && raw.scroll_delta.x == 0.0);
+    let pointer = Pos2::new(50.0, 20.0);
+    let expected = vec![
+        Event::PointerMoved(pointer),
+        // the button is pressed where the pointer was moved to
+        Event::PointerButton {
+            pos: pointer,
+            button: PointerButton::Primary,
+            pressed: true,
+            modifiers: Default::default(),
+        },
+        Event::Text(String::from("a")),
+        Event::Key {
+            key: Key::Backspace,
+            pressed: true,
+            modifiers: Default::default(),
+        },
+        Event::PointerGone,
+    ];
+    assert_eq!(raw.events, expected);
+
+    // the events are only passed to one frame
+    let raw = input.take([800, 600], 2.0);
+    assert!(raw.events.is_empty() && raw.scroll_delta == Vec2::ZERO);
+    // without a pointer, the buttons have no position to be pressed at
+    input.window_event(&button(MouseButton::Left, ElementState::Pressed));
+    assert!(input.take([800, 600], 2.0).events.is_empty());
+
+    let present_modes = [PresentMode::Fifo, PresentMode::Mailbox];
+    let defaults = Parameters {
+        clear_color: [0.0, 0.0, 1.0],
+        triangle: Some([[-0.5, -0.25], [0.0, 0.5], [0.25, -0.1]]),
+        mandelbrot: Some(MandelbrotParameters::default()),
+        present_mode: PresentMode::Fifo,
+    };
+    let mut parameters = defaults.clone();
+    let mut gui = Gui::new(1.0);
+    let frame = |gui: &mut Gui, parameters: &mut Parameters| {
+        gui.run([800, 600], |ctx| parameters.ui(ctx, &present_modes));
+    };
+
+    // the window of the parameters is tessellated with the font texture of egui
+    frame(&mut gui, &mut parameters);
+    assert!(!gui.meshes().is_empty());
+    assert!(gui.meshes().iter().all(|mesh| mesh.1.texture_id == TextureId::Egui));
+    assert!(gui.ctx().texture().width > 0);
+
+    // the press over the window is used by the gui, so it doesn't move the camera
+    gui.window_event(&cursor(20.0, 130.0));
+    frame(&mut gui, &mut parameters);
+    assert!(gui.window_event(&button(MouseButton::Left, ElementState::Pressed)));
+    assert!(gui.window_event(&scroll(1.0)));
+    // releases are never used, so the input sees them in any case
+    assert!(!gui.window_event(&button(MouseButton::Left, ElementState::Released)));
+    frame(&mut gui, &mut parameters);
+
+    // outside of the window everything is left to the input
+    gui.window_event(&cursor(700.0, 550.0));
+    frame(&mut gui, &mut parameters);
+    assert!(!gui.window_event(&button(MouseButton::Left, ElementState::Pressed)));
+    assert!(!gui.window_event(&scroll(1.0)));
+    assert!(!gui.window_event(&button(MouseButton::Left, ElementState::Released)));
+    // no widget has the keyboard focus
+    assert!(!gui.window_event(&key(VirtualKeyCode::W, ElementState::Pressed)));
+    frame(&mut gui, &mut parameters);
+
+    // clicking the title of the window doesn't change any of the parameters
+    assert_eq!(parameters, defaults);
+
+    println!("gui test successful!");
+}
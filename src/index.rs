@@ -0,0 +1,137 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::command_buffer::DynamicState;
+use vulkano::descriptor_set::DescriptorSetsCollection;
+use vulkano::device::Device;
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::pipeline::vertex::VertexSource;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// The index which ends the current strip (with primitive restart enabled) and starts a new
+/// one. It is converted to the restart index of 16 bit buffers by `IndexBuffer::new`.
+pub const RESTART: u32 = u32::MAX;
+
+/// An index buffer with 16 bit indices if all vertices can be addressed by them, 32 bit ones
+/// otherwise.
+#[derive(Clone)]
+pub enum IndexBuffer {
+    U16(Arc<CpuAccessibleBuffer<[u16]>>),
+    U32(Arc<CpuAccessibleBuffer<[u32]>>),
+}
+
+impl IndexBuffer {
+    pub fn new(device: Arc<Device>, indices: &[u32]) -> IndexBuffer {
+        // 0xffff is reserved for primitive restart, so it can't address a vertex
+        let fits_u16 = indices
+            .iter()
+            .all(|&index| index == RESTART || index < u16::MAX as u32);
+
+        if fits_u16 {
+            let indices = indices
+                .iter()
+                .map(|&index| if index == RESTART { u16::MAX } else { index as u16 });
+            IndexBuffer::U16(
+                CpuAccessibleBuffer::from_iter(device, BufferUsage::index_buffer(), false, indices)
+                    .expect("failed to create index buffer"),
+            )
+        } else {
+            IndexBuffer::U32(
+                CpuAccessibleBuffer::from_iter(device, BufferUsage::index_buffer(), false, indices.iter().cloned())
+                    .expect("failed to create index buffer"),
+            )
+        }
+    }
+
+    /// Records an indexed draw of all indices, with whichever index type the buffer has, into a
+    /// primary or secondary command buffer.
+    pub fn draw<L, V, Gp, S, Pc>(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<L>,
+        pipeline: Gp,
+        dynamic_state: &DynamicState,
+        vertex_buffers: V,
+        descriptor_sets: S,
+        push_constants: Pc,
+    ) where
+        Gp: GraphicsPipelineAbstract + VertexSource<V> + Send + Sync + 'static + Clone,
+        S: DescriptorSetsCollection,
+    {
+        match self {
+            IndexBuffer::U16(buffer) => builder.draw_indexed(
+                pipeline,
+                dynamic_state,
+                vertex_buffers,
+                buffer.clone(),
+                descriptor_sets,
+                push_constants,
+            ),
+            IndexBuffer::U32(buffer) => builder.draw_indexed(
+                pipeline,
+                dynamic_state,
+                vertex_buffers,
+                buffer.clone(),
+                descriptor_sets,
+                push_constants,
+            ),
+        }
+        .unwrap();
+    }
+}
+
+/// Merges equal vertices, returning the unique vertices and an index into them for every
+/// vertex of `vertices`, in order.
+///
+/// Vertices are compared by `key`, as floats can't be hashed (see `float_bits`).
+pub fn deduplicate<V, K, F>(vertices: &[V], key: F) -> (Vec<V>, Vec<u32>)
+where
+    V: Clone,
+    K: Hash + Eq,
+    F: Fn(&V) -> K,
+{
+    let mut unique = Vec::new();
+    let mut lookup = HashMap::new();
+
+    let indices = vertices
+        .iter()
+        .map(|vertex| {
+            *lookup.entry(key(vertex)).or_insert_with(|| {
+                unique.push(vertex.clone());
+                unique.len() as u32 - 1
+            })
+        })
+        .collect();
+
+    (unique, indices)
+}
+
+/// Concatenates strips into a single list of indices, separated by `RESTART`.
+pub fn join_strips<I>(strips: I) -> Vec<u32>
+where
+    I: IntoIterator,
+    I::Item: IntoIterator<Item = u32>,
+{
+    let mut indices = Vec::new();
+    for strip in strips {
+        if !indices.is_empty() {
+            indices.push(RESTART);
+        }
+        indices.extend(strip);
+    }
+    indices
+}
+
+/// The bit patterns of `values`, to use floats as a hash key. Only bit-identical values are
+/// considered equal (so `0.0` and `-0.0` are not).
+pub fn float_bits<const N: usize>(values: [f32; N]) -> [u32; N] {
+    let mut bits = [0; N];
+    for (bits, value) in bits.iter_mut().zip(values.iter()) {
+        *bits = value.to_bits();
+    }
+    bits
+}
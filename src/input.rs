@@ -0,0 +1,359 @@
+use winit::dpi::PhysicalPosition;
+use winit::event::{DeviceEvent, ElementState, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+use std::collections::{HashMap, HashSet};
+
+// pixels a touchpad has to scroll for one line of a mouse wheel
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// What the keys and mouse buttons are used for. Which ones trigger an action is configured by
+/// `Bindings`, so they can be changed on the command line with `--bind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Up,
+    Down,
+    // moves the fly camera faster while held
+    Fast,
+    // dragging the mouse orbits around the target
+    Rotate,
+    // dragging the mouse moves the target of the orbit camera
+    Pan,
+    // dragging the mouse turns the fly camera
+    Look,
+    // switches between the orbit and the fly camera
+    SwitchCamera,
+    // switches the focused window between fullscreen and windowed
+    ToggleFullscreen,
+    // saves the next frame of the focused window
+    Screenshot,
+    // shows or hides the text overlay of the windows
+    ToggleOverlay,
+    // shows or hides the gui with the parameters of the sample
+    ToggleGui,
+}
+
+/// A key or mouse button an action can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+/// The keys and mouse buttons bound to each action. An action is triggered by any of them.
+#[derive(Debug, Clone)]
+pub struct Bindings {
+    actions: HashMap<Action, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        use Binding::{Key, Mouse};
+
+        let mut bindings = Bindings { actions: HashMap::new() };
+        bindings.bind(Action::Forward, Key(VirtualKeyCode::W));
+        bindings.bind(Action::Back, Key(VirtualKeyCode::S));
+        bindings.bind(Action::Left, Key(VirtualKeyCode::A));
+        bindings.bind(Action::Right, Key(VirtualKeyCode::D));
+        bindings.bind(Action::Up, Key(VirtualKeyCode::E));
+        bindings.bind(Action::Down, Key(VirtualKeyCode::Q));
+        bindings.bind(Action::Fast, Key(VirtualKeyCode::LShift));
+        bindings.bind(Action::Rotate, Mouse(MouseButton::Left));
+        bindings.bind(Action::Pan, Mouse(MouseButton::Middle));
+        bindings.bind(Action::Look, Mouse(MouseButton::Right));
+        bindings.bind(Action::SwitchCamera, Key(VirtualKeyCode::Tab));
+        bindings.bind(Action::ToggleFullscreen, Key(VirtualKeyCode::F11));
+        bindings.bind(Action::Screenshot, Key(VirtualKeyCode::F12));
+        bindings.bind(Action::ToggleOverlay, Key(VirtualKeyCode::F3));
+        bindings.bind(Action::ToggleGui, Key(VirtualKeyCode::F1));
+        bindings
+    }
+}
+
+impl Bindings {
+    /// Lets `binding` trigger `action`, in addition to what already does.
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.actions.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes every binding of `action`, so it can't be triggered until it is bound again.
+    pub fn unbind(&mut self, action: Action) {
+        self.actions.remove(&action);
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The state of the keyboard and the mouse, collected from the events of a window.
+///
+/// The events of a frame are passed to `window_event` and `device_event` as they arrive, the
+/// state is queried afterwards and `end_frame` is called once the frame is done with it. What
+/// happened during the frame, like a key which was just pressed or how far the mouse moved, is
+/// reset by `end_frame`, while what is held down is kept.
+#[derive(Debug, Clone)]
+pub struct Input {
+    bindings: Bindings,
+    keys: HashSet<VirtualKeyCode>,
+    buttons: HashSet<MouseButton>,
+    // pressed and released since the last `end_frame`
+    keys_pressed: HashSet<VirtualKeyCode>,
+    keys_released: HashSet<VirtualKeyCode>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    modifiers: ModifiersState,
+    // in physical pixels from the top left corner, `None` while outside of the window
+    cursor: Option<PhysicalPosition<f64>>,
+    // accumulated since the last `end_frame`
+    mouse_delta: (f64, f64),
+    scroll: f32,
+}
+
+impl Input {
+    pub fn new(bindings: Bindings) -> Input {
+        Input {
+            bindings,
+            keys: HashSet::new(),
+            buttons: HashSet::new(),
+            keys_pressed: HashSet::new(),
+            keys_released: HashSet::new(),
+            buttons_pressed: HashSet::new(),
+            buttons_released: HashSet::new(),
+            modifiers: ModifiersState::empty(),
+            cursor: None,
+            mouse_delta: (0.0, 0.0),
+            scroll: 0.0,
+        }
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        &mut self.bindings
+    }
+
+    /// Updates the state from an event of the window. Events which aren't about input are
+    /// ignored.
+    pub fn window_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                // keys without a virtual key code can't be bound
+                if let Some(key) = input.virtual_keycode {
+                    match input.state {
+                        // held keys repeat their press events, only the first one counts
+                        ElementState::Pressed => {
+                            if self.keys.insert(key) {
+                                self.keys_pressed.insert(key);
+                            }
+                        }
+                        ElementState::Released => {
+                            if self.keys.remove(&key) {
+                                self.keys_released.insert(key);
+                            }
+                        }
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    if self.buttons.insert(button) {
+                        self.buttons_pressed.insert(button);
+                    }
+                }
+                ElementState::Released => {
+                    if self.buttons.remove(&button) {
+                        self.buttons_released.insert(button);
+                    }
+                }
+            },
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            WindowEvent::CursorMoved { position, .. } => self.cursor = Some(position),
+            WindowEvent::CursorLeft { .. } => self.cursor = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => (position.y / PIXELS_PER_LINE) as f32,
+                };
+            }
+            // The releases of what is held while the window loses the focus go to another
+            // window, everything is released now so nothing stays held down.
+            WindowEvent::Focused(false) => {
+                self.keys_released.extend(self.keys.drain());
+                self.buttons_released.extend(self.buttons.drain());
+                self.modifiers = ModifiersState::empty();
+            }
+            _ => (),
+        }
+    }
+
+    /// Updates the state from an event of a device. Unlike the cursor, the raw motion of the
+    /// mouse isn't limited by the borders of the window or the screen.
+    pub fn device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta: (x, y) } = *event {
+            self.mouse_delta.0 += x;
+            self.mouse_delta.1 += y;
+        }
+    }
+
+    /// Forgets what happened during the frame, to collect the events of the next one.
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll = 0.0;
+    }
+
+    /// Whether `key` is held down.
+    pub fn key(&self, key: VirtualKeyCode) -> bool {
+        self.keys.contains(&key)
+    }
+
+    /// Whether `key` was pressed during this frame.
+    pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    /// Whether `key` was released during this frame.
+    pub fn key_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    /// Whether `button` is held down.
+    pub fn button(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// Whether `button` was pressed during this frame.
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    /// Whether `button` was released during this frame.
+    pub fn button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// Whether any binding of `action` is held down.
+    pub fn action(&self, action: Action) -> bool {
+        self.bindings.get(action).iter().any(|&binding| match binding {
+            Binding::Key(key) => self.key(key),
+            Binding::Mouse(button) => self.button(button),
+        })
+    }
+
+    /// Whether a binding of `action` was pressed during this frame.
+    pub fn action_pressed(&self, action: Action) -> bool {
+        self.bindings.get(action).iter().any(|&binding| match binding {
+            Binding::Key(key) => self.key_pressed(key),
+            Binding::Mouse(button) => self.button_pressed(button),
+        })
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Where the cursor is in the window, in physical pixels, or `None` if it is outside.
+    pub fn cursor(&self) -> Option<[f32; 2]> {
+        self.cursor.map(|position| [position.x as f32, position.y as f32])
+    }
+
+    /// How far the mouse moved during this frame, in the units of the device (usually pixels).
+    pub fn mouse_delta(&self) -> [f32; 2] {
+        [self.mouse_delta.0 as f32, self.mouse_delta.1 as f32]
+    }
+
+    /// How many lines the wheel was scrolled during this frame, positive away from the user.
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+}
+
+/// The action named `name` in a binding on the command line.
+pub fn parse_action(name: &str) -> Option<Action> {
+    match name {
+        "forward" => Some(Action::Forward),
+        "back" => Some(Action::Back),
+        "left" => Some(Action::Left),
+        "right" => Some(Action::Right),
+        "up" => Some(Action::Up),
+        "down" => Some(Action::Down),
+        "fast" => Some(Action::Fast),
+        "rotate" => Some(Action::Rotate),
+        "pan" => Some(Action::Pan),
+        "look" => Some(Action::Look),
+        "switch-camera" => Some(Action::SwitchCamera),
+        "toggle-fullscreen" => Some(Action::ToggleFullscreen),
+        "screenshot" => Some(Action::Screenshot),
+        "toggle-overlay" => Some(Action::ToggleOverlay),
+        "toggle-gui" => Some(Action::ToggleGui),
+        _ => None,
+    }
+}
+
+/// The key or mouse button named `name` on the command line: a letter, a digit, a function key
+/// from `f1` to `f12`, one of the keys below or `mouse-left`, `mouse-right` and `mouse-middle`.
+pub fn parse_input(name: &str) -> Option<Binding> {
+    use VirtualKeyCode::*;
+
+    // letters and digits are named by themselves
+    const LETTERS: [VirtualKeyCode; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    const FUNCTION_KEYS: [VirtualKeyCode; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        let c = c.to_ascii_lowercase();
+        if c.is_ascii_lowercase() {
+            return Some(Binding::Key(LETTERS[(c as u8 - b'a') as usize]));
+        }
+        if c.is_ascii_digit() {
+            return Some(Binding::Key(DIGITS[(c as u8 - b'0') as usize]));
+        }
+    }
+
+    if let Some(number) = name.strip_prefix('f').and_then(|number| number.parse::<usize>().ok()) {
+        return FUNCTION_KEYS.get(number.wrapping_sub(1)).map(|&key| Binding::Key(key));
+    }
+
+    let key = match name {
+        "space" => Space,
+        "tab" => Tab,
+        "enter" => Return,
+        "escape" => Escape,
+        "backspace" => Back,
+        "shift" => LShift,
+        "right-shift" => RShift,
+        "ctrl" => LControl,
+        "right-ctrl" => RControl,
+        "alt" => LAlt,
+        "right-alt" => RAlt,
+        "up" => Up,
+        "down" => Down,
+        "left" => Left,
+        "right" => Right,
+        "page-up" => PageUp,
+        "page-down" => PageDown,
+        "home" => Home,
+        "end" => End,
+        "mouse-left" => return Some(Binding::Mouse(MouseButton::Left)),
+        "mouse-right" => return Some(Binding::Mouse(MouseButton::Right)),
+        "mouse-middle" => return Some(Binding::Mouse(MouseButton::Middle)),
+        _ => return None,
+    };
+    Some(Binding::Key(key))
+}
+
+/// A binding on the command line, like `forward=up`.
+pub fn parse_binding(text: &str) -> Option<(Action, Binding)> {
+    let (action, input) = text.split_once('=')?;
+    Some((parse_action(action.trim())?, parse_input(input.trim())?))
+}
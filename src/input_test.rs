@@ -0,0 +1,164 @@
+use winit::dpi::PhysicalPosition;
+use winit::event::{
+    DeviceEvent, DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase,
+    VirtualKeyCode, WindowEvent,
+};
+
+use cgmath::{InnerSpace, Point3};
+
+use crate::camera::Camera;
+use crate::camera_controller::{CameraController, CameraMode, FlyController, OrbitController};
+use crate::input::{self, Action, Binding, Bindings, Input};
+
+/// Feeds made up events into `Input` and checks what it and the camera controllers make of them.
+/// Unlike the other tests, this doesn't need the gpu.
+pub fn execute() {
+    println!("performing input test");
+
+    let mut input = Input::new(Bindings::default());
+
+    // a key counts as pressed only in the frame it went down, repeats don't press it again
+    input.window_event(&key(VirtualKeyCode::W, ElementState::Pressed));
+    assert!(input.key(VirtualKeyCode::W) && input.key_pressed(VirtualKeyCode::W));
+    assert!(input.action(Action::Forward) && input.action_pressed(Action::Forward));
+    input.end_frame();
+    input.window_event(&key(VirtualKeyCode::W, ElementState::Pressed));
+    assert!(input.key(VirtualKeyCode::W) && !input.key_pressed(VirtualKeyCode::W));
+    input.end_frame();
+    input.window_event(&key(VirtualKeyCode::W, ElementState::Released));
+    assert!(!input.key(VirtualKeyCode::W) && input.key_released(VirtualKeyCode::W));
+    input.end_frame();
+    assert!(!input.key_released(VirtualKeyCode::W));
+
+    // the mouse moves and scrolls by the sum of its events during a frame
+    input.window_event(&button(MouseButton::Left, ElementState::Pressed));
+    input.device_event(&DeviceEvent::MouseMotion { delta: (3.0, -1.0) });
+    input.device_event(&DeviceEvent::MouseMotion { delta: (2.0, -1.0) });
+    input.window_event(&scroll(1.5));
+    input.window_event(&scroll(-0.5));
+    input.window_event(&cursor(10.0, 20.0));
+    assert!(input.button_pressed(MouseButton::Left) && input.action(Action::Rotate));
+    assert_eq!(input.mouse_delta(), [5.0, -2.0]);
+    assert_eq!(input.scroll(), 1.0);
+    assert_eq!(input.cursor(), Some([10.0, 20.0]));
+    input.end_frame();
+    assert_eq!(input.mouse_delta(), [0.0, 0.0]);
+    assert_eq!(input.scroll(), 0.0);
+    assert!(input.button(MouseButton::Left) && !input.button_pressed(MouseButton::Left));
+
+    // losing the focus releases everything, the releases go to another window
+    input.window_event(&key(VirtualKeyCode::LShift, ElementState::Pressed));
+    input.window_event(&WindowEvent::ModifiersChanged(ModifiersState::SHIFT));
+    input.window_event(&WindowEvent::Focused(false));
+    assert!(!input.button(MouseButton::Left) && input.button_released(MouseButton::Left));
+    assert!(!input.key(VirtualKeyCode::LShift));
+    assert!(input.key_released(VirtualKeyCode::LShift) && input.modifiers().is_empty());
+    input.end_frame();
+
+    // a binding on the command line replaces what the action was bound to
+    let (action, binding) = input::parse_binding("forward=up").unwrap();
+    assert_eq!((action, binding), (Action::Forward, Binding::Key(VirtualKeyCode::Up)));
+    assert_eq!(input::parse_binding("look=mouse-left"), Some((Action::Look, Binding::Mouse(MouseButton::Left))));
+    assert_eq!(input::parse_binding("forward=nothing"), None);
+    input.bindings_mut().unbind(action);
+    input.bindings_mut().bind(action, binding);
+    input.window_event(&key(VirtualKeyCode::W, ElementState::Pressed));
+    assert!(!input.action(Action::Forward));
+    input.window_event(&key(VirtualKeyCode::Up, ElementState::Pressed));
+    assert!(input.action(Action::Forward));
+
+    // both controllers start from the view of the camera they are created from
+    let camera = Camera {
+        eye: Point3::new(1.0, 2.0, 3.0),
+        target: Point3::new(0.0, 0.5, -1.0),
+        ..Camera::default()
+    };
+    for &mode in &[CameraMode::Orbit, CameraMode::Fly] {
+        let mut moved = Camera::default();
+        CameraController::new(mode, &camera).apply(&mut moved);
+        assert_close(moved.eye, camera.eye, "eye", mode);
+        assert_close(moved.target, camera.target, "target", mode);
+    }
+
+    // flying forward moves towards the target, without turning
+    let mut fly = FlyController::from_camera(&camera);
+    let direction = fly.forward();
+    fly.update(&input, 0.5);
+    let moved = fly.position - camera.eye;
+    assert!((moved.magnitude() - fly.speed * 0.5).abs() < 1e-4);
+    assert!(moved.normalize().dot(direction) > 0.9999);
+
+    // orbiting keeps the distance to the target
+    let mut orbit = OrbitController::from_camera(&camera);
+    let mut rotating = Input::new(Bindings::default());
+    rotating.window_event(&button(MouseButton::Left, ElementState::Pressed));
+    rotating.device_event(&DeviceEvent::MouseMotion { delta: (100.0, 40.0) });
+    orbit.update(&rotating, 0.0);
+    let distance = (camera.eye - camera.target).magnitude();
+    assert!(((orbit.eye() - orbit.target).magnitude() - distance).abs() < 1e-4);
+    assert!((orbit.eye() - camera.eye).magnitude() > 0.1, "the orbit camera didn't move");
+
+    println!("input test successful!");
+}
+
+fn assert_close(actual: Point3<f32>, expected: Point3<f32>, what: &str, mode: CameraMode) {
+    assert!(
+        (actual - expected).magnitude() < 1e-4,
+        "{:?} camera moved the {} from {:?} to {:?}",
+        mode,
+        what,
+        expected,
+        actual,
+    );
+}
+
+pub fn device_id() -> DeviceId {
+    // the input doesn't look at which device an event came from
+    unsafe { DeviceId::dummy() }
+}
+
+// Made up window events, the gui test feeds them into the gui as well.
+//
+// `modifiers` is deprecated in favor of `ModifiersChanged`, but still has to be given
+#[allow(deprecated)]
+pub fn key(key: VirtualKeyCode, state: ElementState) -> WindowEvent<'static> {
+    WindowEvent::KeyboardInput {
+        device_id: device_id(),
+        input: KeyboardInput {
+            scancode: 0,
+            state,
+            virtual_keycode: Some(key),
+            modifiers: ModifiersState::empty(),
+        },
+        is_synthetic: false,
+    }
+}
+
+#[allow(deprecated)]
+pub fn button(button: MouseButton, state: ElementState) -> WindowEvent<'static> {
+    WindowEvent::MouseInput {
+        device_id: device_id(),
+        state,
+        button,
+        modifiers: ModifiersState::empty(),
+    }
+}
+
+#[allow(deprecated)]
+pub fn scroll(lines: f32) -> WindowEvent<'static> {
+    WindowEvent::MouseWheel {
+        device_id: device_id(),
+        delta: MouseScrollDelta::LineDelta(0.0, lines),
+        phase: TouchPhase::Moved,
+        modifiers: ModifiersState::empty(),
+    }
+}
+
+#[allow(deprecated)]
+pub fn cursor(x: f64, y: f64) -> WindowEvent<'static> {
+    WindowEvent::CursorMoved {
+        device_id: device_id(),
+        position: PhysicalPosition::new(x, y),
+        modifiers: ModifiersState::empty(),
+    }
+}
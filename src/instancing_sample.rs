@@ -0,0 +1,230 @@
+use vulkano::buffer::{BufferAccess, BufferUsage};
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::render_pass::Subpass;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use image::{Rgba, ImageBuffer};
+
+use crate::cli::Options;
+use crate::offscreen::OffscreenTarget;
+use crate::pipeline_cache;
+use crate::vertex::{self, Attributes, Instance, Position};
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+// first buffer
+layout(location=0) in vec3 position;
+// second buffer
+layout(location=1) in vec3 color;
+layout(location=2) in vec3 normal;
+layout(location=3) in vec2 uv;
+// third buffer, one element per instance
+layout(location=4) in vec2 offset;
+layout(location=5) in float scale;
+layout(location=6) in float rotation;
+layout(location=7) in vec3 tint;
+
+layout(location=0) out vec3 v_color;
+layout(location=1) out vec2 v_uv;
+
+layout(push_constant) uniform PushConstants {
+    // additional rotation of all instances, in radians
+    float angle;
+    // width / height of the viewport
+    float aspect;
+} push_constants;
+
+void main() {
+    float a = rotation + push_constants.angle;
+    mat2 rotate = mat2(cos(a), sin(a), -sin(a), cos(a));
+    vec2 p = rotate * position.xy * scale + offset;
+
+    // the triangles face the viewer, light coming from the front
+    float light = 0.5 + 0.5 * max(normal.z, 0.0);
+    v_color = color * tint * light;
+    v_uv = uv;
+    gl_Position = vec4(p.x / max(push_constants.aspect, 1.0), p.y * min(push_constants.aspect, 1.0), position.z, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec3 v_color;
+layout(location=1) in vec2 v_uv;
+layout(location=0) out vec4 f_color;
+
+void main() {
+    // darken the edges of the triangle, using the uvs as barycentric coordinates
+    float edge = min(min(v_uv.x, v_uv.y), 1.0 - v_uv.x - v_uv.y);
+    f_color = vec4(v_color * smoothstep(0.0, 0.08, edge), 1.0);
+}
+"
+    }
+}
+
+/// A single triangle, stored in two buffers: the positions and the remaining attributes.
+fn triangle() -> (Vec<Position>, Vec<Attributes>) {
+    let normal = [0.0, 0.0, 1.0];
+    let positions = vec![
+        Position { position: [-0.5, 0.43, 0.0] },
+        Position { position: [0.5, 0.43, 0.0] },
+        Position { position: [0.0, -0.43, 0.0] },
+    ];
+    let attributes = vec![
+        Attributes { color: [1.0, 0.6, 0.6], normal, uv: [1.0, 0.0] },
+        Attributes { color: [0.6, 1.0, 0.6], normal, uv: [0.0, 1.0] },
+        Attributes { color: [0.6, 0.6, 1.0], normal, uv: [0.0, 0.0] },
+    ];
+    (positions, attributes)
+}
+
+/// `count` instances on a square grid covering clip space, each with its own rotation and
+/// color.
+pub fn grid_instances(count: u32) -> Vec<Instance> {
+    let columns = (count as f32).sqrt().ceil().max(1.0) as u32;
+    let cell = 2.0 / columns as f32;
+
+    (0..count)
+        .map(|i| {
+            let (column, row) = (i % columns, i / columns);
+            // walk around the hue circle, so neighbouring instances have different colors
+            let hue = (i as f32 * 0.618_034).fract();
+            Instance {
+                offset: [
+                    -1.0 + (column as f32 + 0.5) * cell,
+                    -1.0 + (row as f32 + 0.5) * cell,
+                ],
+                scale: cell * 0.8,
+                rotation: i as f32 * 0.1,
+                tint: hue_to_rgb(hue),
+            }
+        })
+        .collect()
+}
+
+fn hue_to_rgb(hue: f32) -> [f32; 3] {
+    let channel = |offset: f32| {
+        let h = (hue + offset).fract() * 6.0;
+        (2.0 - (h - 3.0).abs()).max(0.0).min(1.0)
+    };
+    [channel(0.0), channel(2.0 / 3.0), channel(1.0 / 3.0)]
+}
+
+/// Pipeline and the three vertex buffers to draw many instances of a triangle with one draw
+/// call.
+pub struct InstancedTriangles {
+    pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    positions: Arc<CpuAccessibleBuffer<[Position]>>,
+    attributes: Arc<CpuAccessibleBuffer<[Attributes]>>,
+    instances: Arc<CpuAccessibleBuffer<[Instance]>>,
+}
+
+impl InstancedTriangles {
+    pub fn new(device: Arc<Device>, subpass: Subpass, instances: &[Instance]) -> InstancedTriangles {
+        // load shader for device
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        let builder = GraphicsPipeline::start()
+            // positions, attributes and instances come from three different buffers
+            .vertex_input(vertex::instanced_definition())
+            .vertex_shader(vertex_shader.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fragment_shader.main_entry_point(), ());
+
+        let builder = if subpass.has_depth() {
+            builder.depth_stencil_simple_depth()
+        } else {
+            builder
+        };
+
+        let pipeline = Arc::new(
+            builder
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        let (positions, attributes) = triangle();
+        let usage = BufferUsage::vertex_buffer();
+
+        InstancedTriangles {
+            pipeline,
+            positions: CpuAccessibleBuffer::from_iter(device.clone(), usage, false, positions.into_iter()).unwrap(),
+            attributes: CpuAccessibleBuffer::from_iter(device.clone(), usage, false, attributes.into_iter()).unwrap(),
+            instances: CpuAccessibleBuffer::from_iter(device, usage, false, instances.iter().cloned()).unwrap(),
+        }
+    }
+
+    /// Draws all instances, rotated by an additional `angle` (in radians).
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+        aspect: f32,
+        angle: f32,
+    ) {
+        // the vertex count is taken from the per-vertex buffers, the instance count from the
+        // per-instance buffer
+        let buffers: Vec<Arc<dyn BufferAccess + Send + Sync>> = vec![
+            self.positions.clone(),
+            self.attributes.clone(),
+            self.instances.clone(),
+        ];
+
+        builder
+            .draw(
+                self.pipeline.clone(),
+                dynamic_state,
+                buffers,
+                (),
+                vs::ty::PushConstants { angle, aspect },
+            )
+            .unwrap();
+    }
+}
+
+/// Renders `count` instances headlessly into `target`.
+pub fn render(queue: Arc<Queue>, device: Arc<Device>, target: &OffscreenTarget, count: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let triangles = InstancedTriangles::new(device, target.subpass(), &grid_instances(count));
+    let dynamic_state = target.dynamic_state();
+    let [width, height] = target.dimensions();
+
+    target.render(queue, [0.0, 0.0, 0.0, 1.0], |builder| {
+        triangles.draw(builder, &dynamic_state, width as f32 / height as f32, 0.0);
+    })
+}
+
+/// `instancing-sample` draws `--instances N` triangles with a single draw call into
+/// `instancing.png`.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let target = OffscreenTarget::start(device.clone(), options.size)
+        .samples(samples)
+        .build();
+
+    let image_buffer = render(queue, device, &target, options.instances);
+
+    image_buffer
+        .save(options.output.as_deref().unwrap_or("instancing.png"))
+        .unwrap();
+}
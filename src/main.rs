@@ -16,32 +16,86 @@
 // and that you want to learn Vulkan. This means that for example it won't go into details about
 // what a vertex or a shader is.
 
+mod camera;
+mod camera_controller;
+mod cli;
+mod command_buffer_test;
+mod compute_test;
+mod cube_sample;
+mod cube_scene;
+mod culling_sample;
+mod culling_test;
+mod depth;
+mod depth_test;
+mod export_mandelbrot;
+mod frame_stats;
+mod frames;
+mod gui;
+mod gui_test;
+mod image_test;
+mod index;
+mod input;
+mod input_test;
+mod instancing_sample;
+mod mandelbrot_sample;
+mod mesh;
+mod msaa;
+mod msaa_test;
+mod offscreen;
+mod particle_sample;
+mod particle_test;
+mod pipeline_cache;
+mod present;
+mod recording_benchmark;
+mod render_graph;
+mod render_graph_sample;
+mod render_graph_test;
+mod render_mesh;
+mod render_pass_sample;
+mod renderer;
+mod scene_file;
+mod scene_sample;
+mod scene_test;
+mod screenshot;
+mod secondary;
+mod shader_interface;
+mod shader_interface_test;
+mod shader_reload;
+mod swapchain_config;
+mod text;
+mod text_test;
+mod texture;
+mod texture_sample;
+mod texture_test;
+mod vertex;
+mod virtual_swapchain;
+mod virtual_swapchain_test;
+mod window_test;
+mod windows;
+
 use vulkano::swapchain::SwapchainBuilder;
 use vulkano::buffer::BufferUsage;
 use vulkano::buffer::CpuAccessibleBuffer;
-use vulkano::command_buffer::DynamicState;
+use vulkano::descriptor_set::PersistentDescriptorSet;
 use vulkano::command_buffer::PrimaryAutoCommandBuffer;
 use vulkano::command_buffer::SubpassContents;
 use vulkano::command_buffer::pool::CommandPoolBuilderAlloc;
-use vulkano::image::SwapchainImage;
 use vulkano::instance::Instance;
 use vulkano::Version;
 use vulkano::device::physical::PhysicalDevice;
 use vulkano::pipeline::GraphicsPipeline;
-use vulkano::pipeline::viewport::Viewport;
-use vulkano::render_pass::FramebufferAbstract;
-use vulkano::render_pass::Framebuffer;
+use vulkano::pipeline::GraphicsPipelineAbstract;
+use vulkano::pipeline::shader::GraphicsEntryPoint;
 use vulkano::render_pass::Subpass;
-use vulkano::render_pass::RenderPass;
 use vulkano::command_buffer::pool::CommandPool;
-use vulkano::image::view;
 
 
 use vulkano::device::Device;
 use vulkano::device::DeviceExtensions;
 use vulkano::device::Features;
+use vulkano::device::Queue;
+use vulkano::instance::InstanceExtensions;
 
-use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBuffer};
 
 // windowing
 use vulkano_win::VkSurfaceBuild;
@@ -49,30 +103,97 @@ use winit::event_loop::EventLoop;
 use winit::event::WindowEvent;
 use winit::event::Event;
 
-use winit::window::Window;
 use winit::window::WindowBuilder;
 use winit::event_loop::ControlFlow;
 use winit::dpi::PhysicalSize;
 
-use vulkano::swapchain::{AcquireError, SwapchainCreationError, Swapchain, SurfaceTransform, PresentMode, ColorSpace, FullscreenExclusive};
-use vulkano::image::ImageUsage;
 
 use vulkano::swapchain::Capabilities;
-use vulkano::sync;
-use vulkano::sync::{GpuFuture, FlushError};
 
+use std::path::Path;
 use std::sync::Arc;
-use vulkano::format::Format;
+use std::time::{Duration, Instant};
+
+use cgmath::{Matrix4, Point3, Rad, SquareMatrix};
+
+use camera::Camera;
+use input::Action;
+use present::Overlay;
+use windows::View;
 
 fn main() {
+    let options = cli::Options::from_args();
+
+    // Samples which render without a window are selected by the first argument. The windowed
+    // samples draw the triangle or the scene given with `--scene` (default), the rotating cube,
+    // a textured quad, many instanced triangles, particles moved by a compute shader, many cubes
+    // recorded on several threads (`scene`) or the Mandelbrot set.
+    match options.command.as_deref() {
+        None | Some("triangle") | Some("cube") | Some("texture") | Some("instancing") | Some("particles")
+        | Some("scene") | Some("mandelbrot") => (),
+        // the tests which don't need a device
+        Some("input-test") => return input_test::execute(),
+        Some("gui-test") => return gui_test::execute(),
+        Some("scene-test") => return scene_test::execute(),
+        Some("render-graph-test") => return render_graph_test::execute(),
+        Some("shader-interface-test") => return shader_interface_test::execute(),
+        // an empty window, closed with its close button
+        Some("window-test") => {
+            let instance = Instance::new(None, Version::V1_2, &vulkano_win::required_extensions(), None).unwrap();
+            return window_test::execute(instance);
+        }
+        Some(command) => {
+            let (device, queue) = headless_device();
+            let samples = msaa::clamp_samples(device.physical_device(), options.samples);
+
+            let start = Instant::now();
+            pipeline_cache::load(&device, Path::new(&options.cache_dir));
+            match command {
+                "command-buffer-test" => command_buffer_test::execute(queue, device),
+                "compute-test" => compute_test::execute(queue, device),
+                "image-test" => image_test::execute(queue, device),
+                "export-mandelbrot" => export_mandelbrot::execute(queue, device),
+                "render-pass-sample" => render_pass_sample::execute(queue, device, samples, &options),
+                "cube-sample" => cube_sample::execute(queue, device, samples),
+                "msaa-test" => msaa_test::execute(queue, device),
+                "depth-test" => depth_test::execute(queue, device),
+                "render-mesh" => render_mesh::execute(queue, device, samples, &options),
+                "render-scene" => scene_sample::execute(queue, device, samples, &options),
+                "render-graph" => render_graph_sample::execute(queue, device, samples, &options),
+                "texture-sample" => texture_sample::execute(queue, device, samples, &options),
+                "texture-test" => texture_test::execute(queue, device),
+                "text-test" => text_test::execute(queue, device),
+                "instancing-sample" => instancing_sample::execute(queue, device, samples, &options),
+                "culling-sample" => culling_sample::execute(queue, device, samples, &options),
+                "culling-test" => culling_test::execute(queue, device),
+                "particle-sample" => particle_sample::execute(queue, device, samples, &options),
+                "particle-test" => particle_test::execute(queue, device),
+                "recording-benchmark" => recording_benchmark::execute(queue, device, samples, &options),
+                "virtual-swapchain" => virtual_swapchain::execute(queue, device, samples, &options),
+                "virtual-swapchain-test" => virtual_swapchain_test::execute(queue, device),
+                _ => panic!("unknown command: {}", command),
+            }
+            // most of this is spent creating pipelines, run twice to see the effect of the cache
+            println!("{} took {:.2?}", command, start.elapsed());
+            pipeline_cache::save();
+            return;
+        }
+    }
+
     // The first step of any Vulkan program is to create an instance.
     //
     // When we create an instance, we have to pass a list of extensions that we want to enable.
     //
     // All the window-drawing functionalities are part of non-core extensions that we need
     // to enable manually. To do so, we ask the `vulkano_win` crate for the list of extensions
-    // required to draw to a window.
-    let required_extensions = vulkano_win::required_extensions();
+    // required to draw to a window. Exclusive fullscreen (see `Renderer::toggle_fullscreen`)
+    // needs two more extensions, they are enabled if they are available.
+    let supported_extensions = InstanceExtensions::supported_by_core().unwrap();
+    let required_extensions = InstanceExtensions {
+        khr_get_physical_device_properties2: supported_extensions.khr_get_physical_device_properties2,
+        khr_get_surface_capabilities2: supported_extensions.khr_get_surface_capabilities2,
+        ..vulkano_win::required_extensions()
+    };
 
     // Now creating the instance.
     let instance = Instance::new(None, Version::V1_2, &required_extensions, None).unwrap();
@@ -110,10 +231,25 @@ fn main() {
     //
     // This returns a `vulkano::swapchain::Surface` object that contains both a cross-platform winit
     // window and a cross-platform Vulkan surface that represents the surface of the window.
+    //
+    // The window starts out in the resolution of the scene file given with `--scene`, or of the
+    // default scene, the red triangle on blue. A scene of a single triangle is drawn by the
+    // triangle sample, others are drawn instead of it.
+    let scene_file = scene_file::SceneFile::load_or_default(options.scene.as_deref())
+        .unwrap_or_else(|err| panic!("{}: {}", options.scene.as_deref().unwrap_or("default scene"), err));
     let event_loop = EventLoop::new();
     let surface = WindowBuilder::new()
+        .with_inner_size(PhysicalSize::new(scene_file.resolution[0], scene_file.resolution[1]))
         .build_vk_surface(&event_loop, instance.clone())
         .unwrap();
+    // With `--debug-window` a second window shows the sample from a fixed camera. Every window
+    // has its own surface, which are all drawn into with the same device.
+    let debug_surface = options.debug_window.then(|| {
+        WindowBuilder::new()
+            .with_title("rust-vulkan - debug")
+            .build_vk_surface(&event_loop, instance.clone())
+            .unwrap()
+    });
 
     // The next step is to choose which GPU queue will execute our draw commands.
     //
@@ -128,8 +264,10 @@ fn main() {
     let queue_family = physical
         .queue_families()
         .find(|&q| {
-            // We take the first queue that supports drawing to our window.
-            q.supports_graphics() && surface.is_supported(q).unwrap_or(false)
+            // We take the first queue that supports drawing to our windows.
+            q.supports_graphics()
+                && surface.is_supported(q).unwrap_or(false)
+                && debug_surface.iter().all(|surface| surface.is_supported(q).unwrap_or(false))
         })
         .unwrap();
 
@@ -154,6 +292,9 @@ fn main() {
     // The list of created queues is returned by the function alongside with the device.
     let device_ext = DeviceExtensions {
         khr_swapchain: true,
+        ext_full_screen_exclusive: required_extensions.khr_get_physical_device_properties2
+            && required_extensions.khr_get_surface_capabilities2
+            && physical.supported_extensions().ext_full_screen_exclusive,
         ..DeviceExtensions::none()
     };
     let (device, mut queues) = Device::new(
@@ -169,78 +310,39 @@ fn main() {
     // iterator and throw it away.
     let queue = queues.next().unwrap();
 
-    // Before we can draw on the surface, we have to create what is called a swapchain. Creating
-    // a swapchain allocates the color buffers that will contain the image that will ultimately
-    // be visible on the screen. These images are returned alongside with the swapchain.
-    let (mut swapchain, images) = {
-        // Querying the capabilities of the surface. When we create the swapchain we can only
-        // pass values that are allowed by the capabilities.
-        let caps = surface.capabilities(physical).unwrap();
-
-        // The alpha mode indicates how the alpha value of the final image will behave. For example
-        // you can choose whether the window will be opaque or transparent.
-        let alpha = caps.supported_composite_alpha.iter().next().unwrap();
-
-        // Choosing the internal format that the images will have.
-        let format = caps.supported_formats[0].0;
-
-        // The dimensions of the window, only used to initially setup the swapchain.
-        // NOTE:
-        // On some drivers the swapchain dimensions are specified by `caps.current_extent` and the
-        // swapchain size must use these dimensions.
-        // These dimensions are always the same as the window dimensions
-        //
-        // However other drivers dont specify a value i.e. `caps.current_extent` is `None`
-        // These drivers will allow anything but the only sensible value is the window dimensions.
-        //
-        // Because for both of these cases, the swapchain needs to be the window dimensions, we just use that.
-        let dimensions: [u32; 2] = surface.window().inner_size().into();
-
-        let swapchain_builder = Swapchain::start(device.clone(), surface.clone());
-        swapchain_builder
-            .num_images(caps.min_image_count)
-            .format(format)
-            .dimensions(dimensions)
-            .usage(ImageUsage::color_attachment())
-            .transform(SurfaceTransform::Identity)
-            .composite_alpha(alpha)
-            .present_mode(PresentMode::Fifo)
-            .fullscreen_exclusive(FullscreenExclusive::Default)
-            .clipped(true)
-            .color_space(ColorSpace::SrgbNonLinear)
-            .layers(1)
-            .build()
-            .expect("Swapchain creation failed")
-    };
+    // Building pipelines means compiling shaders in the driver, which takes most of the startup
+    // time. The pipelines built by earlier runs are kept in a cache on disk (see
+    // `pipeline_cache`), every pipeline below is created with it.
+    let startup = Instant::now();
+    pipeline_cache::load(&device, Path::new(&options.cache_dir));
 
     #[derive(Default, Debug, Clone)]
     struct Vertex {
         position: [f32; 2],
     }
     vulkano::impl_vertex!(Vertex, position);
-    // We now create a buffer that will store the shape of our triangle.
-    let vertex_buffer = {
-
+    // We now create a buffer that will store the shape of our triangle, the one of the scene. The
+    // vertices can be moved in the gui, the buffer is created again whenever they change. The
+    // color of the triangle is passed to the fragment shader as a push constant.
+    let (triangle, triangle_color) = match scene_file.triangle() {
+        Some((vertices, color)) => (Some(vertices), color),
+        None => (None, [0.0; 4]),
+    };
+    let triangle_device = device.clone();
+    let triangle_buffer = move |triangle: [[f32; 2]; 3]| {
         CpuAccessibleBuffer::from_iter(
-            device.clone(),
+            triangle_device.clone(),
             BufferUsage::all(),
             false,
-            [
-                Vertex {
-                    position: [-0.5, -0.25],
-                },
-                Vertex {
-                    position: [0.0, 0.5],
-                },
-                Vertex {
-                    position: [0.25, -0.1],
-                },
-            ]
-            .iter()
-            .cloned(),
+            triangle.iter().map(|&position| Vertex { position }),
         )
         .unwrap()
     };
+    let mut vertex_buffer = triangle.map(&triangle_buffer);
+
+    // The order in which the vertices are assembled into triangles. With an index buffer,
+    // vertices shared by several triangles only have to be stored once.
+    let index_buffer = index::IndexBuffer::new(device.clone(), &[0, 1, 2]);
 
     // The next step is to create the shaders.
     //
@@ -250,29 +352,20 @@ fn main() {
     // `vulkano-shaders` crate docs. You can view them at https://docs.rs/vulkano-shaders/
     //
     // TODO: explain this in details
+    //
+    // The shaders of the triangle are kept in files, so they can be edited while the program
+    // runs with `--shader-dir` (see `shader_reload`).
     mod vs {
         vulkano_shaders::shader! {
             ty: "vertex",
-            src: "
-				#version 450
-				layout(location = 0) in vec2 position;
-				void main() {
-					gl_Position = vec4(position, 0.0, 1.0);
-				}
-			"
+            path: "shaders/triangle.vert"
         }
     }
 
     mod fs {
         vulkano_shaders::shader! {
             ty: "fragment",
-            src: "
-				#version 450
-				layout(location = 0) out vec4 f_color;
-				void main() {
-					f_color = vec4(1.0, 0.0, 0.0, 1.0);
-				}
-			"
+            path: "shaders/triangle.frag"
         }
     }
 
@@ -283,43 +376,34 @@ fn main() {
     // implicitly does a lot of computation whenever you draw. In Vulkan, you have to do all this
     // manually.
 
-    // The next step is to create a *render pass*, which is an object that describes where the
-    // output of the graphics pipeline will go. It describes the layout of the images
-    // where the colors, depth and/or stencil information will be written.
-    let render_pass = Arc::new(
-        vulkano::single_pass_renderpass!(
-            device.clone(),
-            attachments: {
-                // `color` is a custom name we give to the first and only attachment.
-                color: {
-                    // `load: Clear` means that we ask the GPU to clear the content of this
-                    // attachment at the start of the drawing.
-                    load: Clear,
-                    // `store: Store` means that we ask the GPU to store the output of the draw
-                    // in the actual image. We could also ask it to discard the result.
-                    store: Store,
-                    // `format: <ty>` indicates the type of the format of the image. This has to
-                    // be one of the types of the `vulkano::format` module (or alternatively one
-                    // of your structs that implements the `FormatDesc` trait). Here we use the
-                    // same format as the swapchain.
-                    format: swapchain.format(),
-                    // TODO:
-                    samples: 1,
-                }
-            },
-            pass: {
-                // We use the attachment named `color` as the one and only color attachment.
-                color: [color],
-                // No depth-stencil attachment is indicated with empty brackets.
-                depth_stencil: {}
-            }
-        )
-        .unwrap(),
-    );
+    // The number of samples per pixel for multisample anti-aliasing, limited to what the device
+    // supports.
+    let samples = msaa::clamp_samples(physical, options.samples);
+
+    // The depth buffer keeps track of the distance of every drawn fragment to the camera, so
+    // that closer geometry occludes the geometry behind it regardless of the drawing order.
+    let depth_format = depth::choose_depth_format(physical);
+
+    // Before we can draw on the surface, we have to create what is called a swapchain, which
+    // holds the images that will ultimately be visible on the screen, and a *render pass*, which
+    // describes where the output of the graphics pipelines will go. The renderer creates both,
+    // along with a framebuffer for every swapchain image, and recreates them whenever they no
+    // longer match the window (see `renderer`).
+    let renderer = renderer::Renderer::new(queue.clone(), surface.clone(), &options.swapchain, samples, depth_format);
+    let render_pass = renderer.render_pass();
+    // The debug window draws with the same render pass, so the pipelines below can draw into
+    // both windows.
+    let debug_renderer =
+        debug_surface.map(|surface| renderer::Renderer::sharing(surface, &options.swapchain, &renderer));
 
     // Before we draw we have to create what is called a pipeline. This is similar to an OpenGL
     // program, but much more specific.
-    let pipeline = Arc::new(
+    //
+    // The pipeline is built by a closure, so it can be built again from shaders which were
+    // reloaded while the program runs.
+    let pipeline_device = device.clone();
+    let pipeline_render_pass = render_pass.clone();
+    let build_pipeline = move |vertex_shader: GraphicsEntryPoint, fragment_shader: GraphicsEntryPoint| {
         GraphicsPipeline::start()
             // We need to indicate the layout of the vertices.
             // The type `SingleBufferDefinition` actually contains a template parameter corresponding
@@ -328,275 +412,566 @@ fn main() {
             // A Vulkan shader can in theory contain multiple entry points, so we have to specify
             // which one. The `main` word of `main_entry_point` actually corresponds to the name of
             // the entry point.
-            .vertex_shader(vs.main_entry_point(), ())
+            .vertex_shader(vertex_shader, ())
             // The content of the vertex buffer describes a list of triangles.
             .triangle_list()
             // Use a resizable viewport set to draw over the entire window
             .viewports_dynamic_scissors_irrelevant(1)
             // See `vertex_shader`.
-            .fragment_shader(fs.main_entry_point(), ())
+            .fragment_shader(fragment_shader, ())
+            // Test fragments against the depth buffer and keep the closest one.
+            .depth_stencil_simple_depth()
             // We have to indicate which subpass of which render pass this pipeline is going to be used
             // in. The pipeline will only be usable from this particular subpass.
-            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .render_pass(Subpass::from(pipeline_render_pass.clone(), 0).unwrap())
+            // Pipelines which were built by an earlier run are taken from the cache.
+            .build_with_cache(pipeline_cache::get(&pipeline_device))
             // Now that our builder is filled, we call `build()` to obtain an actual pipeline.
-            .build(device.clone())
-            .unwrap(),
+            .build(pipeline_device.clone())
+            .map(Arc::new)
+    };
+    let mut pipeline = build_pipeline(vs.main_entry_point(), fs.main_entry_point()).unwrap();
+
+    // The `cube` command draws a rotating cube instead of the triangle, which needs the depth
+    // buffer to hide its back faces.
+    let cube = match options.command.as_deref() {
+        Some("cube") => Some(cube_sample::Cube::new(
+            device.clone(),
+            Subpass::from(render_pass.clone(), 0).unwrap(),
+        )),
+        _ => None,
+    };
+    // The `texture` command draws the texture given as argument (or the default one) instead.
+    let textured_quad = match options.command.as_deref() {
+        Some("texture") => {
+            let bytes = match options.args.first() {
+                Some(path) => std::fs::read(path).unwrap(),
+                None => texture_sample::DEFAULT_TEXTURE.to_vec(),
+            };
+            Some(texture_sample::TexturedQuad::new(
+                queue.clone(),
+                device.clone(),
+                Subpass::from(render_pass.clone(), 0).unwrap(),
+                &bytes,
+                &options.sampler,
+            ))
+        }
+        _ => None,
+    };
+    // The `instancing` command draws `--instances` triangles with a single draw call.
+    let instanced_triangles = match options.command.as_deref() {
+        Some("instancing") => Some(instancing_sample::InstancedTriangles::new(
+            device.clone(),
+            Subpass::from(render_pass.clone(), 0).unwrap(),
+            &instancing_sample::grid_instances(options.instances),
+        )),
+        _ => None,
+    };
+    let mut particles = match options.command.as_deref() {
+        Some("particles") => Some(particle_sample::ParticleSystem::new(
+            device.clone(),
+            Subpass::from(render_pass.clone(), 0).unwrap(),
+            &particle_sample::spawn_particles(options.particles),
+        )),
+        _ => None,
+    };
+    // The `scene` command draws `--instances` cubes with one draw call each. The draw calls are
+    // recorded into secondary command buffers by a pool of `--threads` threads.
+    let scene = match options.command.as_deref() {
+        Some("scene") => {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(options.threads.unwrap_or(0))
+                .build()
+                .unwrap();
+            let scene = cube_scene::CubeScene::new(
+                device.clone(),
+                Subpass::from(render_pass.clone(), 0).unwrap(),
+                options.instances,
+            );
+            Some((pool, scene))
+        }
+        _ => None,
+    };
+    // The shapes of a scene file which isn't a single triangle are drawn with the camera of the
+    // scene, if it has one, or directly in clip space otherwise.
+    let scene_renderer = triangle.is_none().then(|| {
+        scene_sample::SceneRenderer::new(device.clone(), Subpass::from(render_pass.clone(), 0).unwrap(), &scene_file)
+    });
+    let scene_has_camera = scene_file.camera.is_some();
+    // The `mandelbrot` command draws the Mandelbrot set, computed for every pixel of the window.
+    let mandelbrot = match options.command.as_deref() {
+        Some("mandelbrot") => Some(mandelbrot_sample::Mandelbrot::new(
+            device.clone(),
+            Subpass::from(render_pass.clone(), 0).unwrap(),
+        )),
+        _ => None,
+    };
+    // With `--overlay` (or after `Action::ToggleOverlay`) the frame rate, the device and the
+    // parameters of the sample are drawn on top of the windows, in the overlay subpass of the
+    // render pass.
+    let atlas = text::GlyphAtlas::new(&text::load_font(options.font.as_deref()), text::DEFAULT_SIZE)
+        .expect("failed to load font");
+    let mut text = text::TextRenderer::new(queue.clone(), renderer.overlay_subpass(), atlas);
+    let mut show_overlay = options.overlay;
+    let device_name = physical.properties().device_name.clone();
+    let sample_info = match options.command.as_deref() {
+        None if scene_renderer.is_some() => {
+            let path = options.scene.as_deref().unwrap_or("default scene");
+            format!("{}, {} shapes", path, scene_file.shapes.len())
+        }
+        Some("instancing") => format!("instancing, {} triangles", options.instances),
+        Some("particles") => format!("particles, {} particles", options.particles),
+        Some("scene") => {
+            let threads = scene.as_ref().map_or(0, |(pool, _)| pool.current_num_threads());
+            format!("scene, {} cubes recorded by {} threads", options.instances, threads)
+        }
+        Some(command) => command.to_string(),
+        None => String::from("triangle"),
+    };
+    let sample_info = format!(
+        "{}\n{}x msaa, {} frames in flight",
+        sample_info, samples, options.frames_in_flight
     );
 
-    // Dynamic viewports allow us to recreate just the viewport when the window is resized
-    // Otherwise we would have to recreate the whole pipeline.
-    let mut dynamic_state = DynamicState {
-        line_width: None,
-        viewports: None,
-        scissors: None,
-        compare_mask: None,
-        write_mask: None,
-        reference: None,
+    // With `--gui` (or after `Action::ToggleGui`) a window with the parameters of the sample is
+    // drawn on top of the main window, in the overlay subpass as well. The changed parameters
+    // are applied before the next frame is drawn.
+    let mut gui = gui::Gui::new(renderer.window().scale_factor() as f32);
+    let mut gui_painter = gui::GuiPainter::new(queue.clone(), renderer.overlay_subpass());
+    let mut show_gui = options.gui;
+    let present_modes = renderer.present_modes();
+    let mut parameters = gui::Parameters {
+        clear_color: [scene_file.clear_color[0], scene_file.clear_color[1], scene_file.clear_color[2]],
+        triangle: triangle.filter(|_| matches!(options.command.as_deref(), None | Some("triangle"))),
+        mandelbrot: mandelbrot.as_ref().map(|_| mandelbrot_sample::MandelbrotParameters::default()),
+        present_mode: renderer.present_mode(),
     };
 
-    // The render pass we created above only describes the layout of our framebuffers. Before we
-    // can draw we also need to create the actual framebuffers.
-    //
-    // Since we need to draw to multiple images, we are going to create a different framebuffer for
-    // each image.
-    let mut framebuffers =
-        window_size_dependent_setup(&images, render_pass.clone(), &mut dynamic_state);
+    // The camera of the cube, the scene and the triangle is moved with the mouse and the keys,
+    // orbiting around the origin or flying freely with `--camera fly`. It starts out as the
+    // camera of the scene file, if there is one.
+    let mut camera = scene_file.camera.clone().unwrap_or_default();
+    let mut camera_controller = camera_controller::CameraController::new(options.camera_mode, &camera);
+    let mut input = input::Input::new(options.bindings.clone());
+    // the debug window looks at the origin from further away, above and to the side
+    let debug_camera = Camera {
+        eye: Point3::new(4.0, 4.0, 6.0),
+        ..Camera::default()
+    };
+    let start = Instant::now();
+    let mut last_frame = Instant::now();
+
+    // With `--shader-dir` the shaders of the triangle and the particles are compiled from the
+    // files in that directory whenever they change, so they can be edited without a restart.
+    let shader_dir = options.shader_dir.as_ref().map(Path::new);
+    let mut triangle_shaders = shader_dir.map(|dir| {
+        [
+            shader_reload::WatchedShader::new(dir.join("triangle.vert")),
+            shader_reload::WatchedShader::new(dir.join("triangle.frag")),
+        ]
+    });
+    let mut particle_shader = shader_dir
+        .filter(|_| particles.is_some())
+        .map(|dir| shader_reload::WatchedShader::new(dir.join("particles.comp")));
+    if let Some(dir) = shader_dir {
+        println!("watching shaders in {}", dir.display());
+    }
 
-    // Initialization is finally finished!
+    // The matrices change every frame, while the previous frames may still be using theirs on
+    // the GPU. Every frame in flight has its own uniform buffer, which is only written once the
+    // GPU is done with the frame that used it before. Every window has frames in flight of its
+    // own, as they are presented independently.
+    let uniform_buffer = |_| {
+        let data = vs::ty::Data {
+            model: Matrix4::from_angle_y(Rad(0.0)).into(),
+            view: camera.view().into(),
+            proj: camera.projection(1.0).into(),
+            time: 0.0,
+        };
+        CpuAccessibleBuffer::from_data(device.clone(), BufferUsage::uniform_buffer(), false, data).unwrap()
+    };
+    let mut windows = windows::Windows::new(windows::AppWindow {
+        view: View::Scene,
+        renderer,
+        frames: frames::FramesInFlight::new(device.clone(), options.frames_in_flight, uniform_buffer),
+    });
+    if let Some(renderer) = debug_renderer {
+        windows.add(windows::AppWindow {
+            view: View::Debug,
+            renderer,
+            frames: frames::FramesInFlight::new(device.clone(), options.frames_in_flight, uniform_buffer),
+        });
+    }
 
-    // In some situations, the swapchain will become invalid by itself. This includes for example
-    // when the window is resized (as the images of the swapchain will no longer match the
-    // window's) or, on Android, when the application went to the background and goes back to the
-    // foreground.
-    //
-    // In this situation, acquiring a swapchain image or presenting it will return an error.
-    // Rendering to an image of that swapchain will not produce any error, but may or may not work.
-    // To continue rendering, we need to recreate the swapchain by creating a new swapchain.
-    // Here, we remember that we need to do this for the next loop iteration.
-    let mut recreate_swapchain = false;
+    // Initialization is finally finished!
 
     // In the loop below we are going to submit commands to the GPU. Submitting a command produces
     // an object that implements the `GpuFuture` trait, which holds the resources for as long as
     // they are in use by the GPU.
     //
     // Destroying the `GpuFuture` blocks until the GPU is finished executing it. In order to avoid
-    // that, we store the submission of the previous frame here.
-    let mut previous_frame_end = Some(sync::now(device.clone()).boxed());
+    // that, the submissions of the frames in flight are kept by the `frames` of each window, and
+    // the CPU only waits once it wants to reuse the resources of a frame.
+
+    // compare with a second run, which finds the pipelines in the cache
+    println!("pipelines created in {:.2?}", startup.elapsed());
+    println!("{} frames in flight", windows.main().frames.len());
+
+    // The times of every frame are collected, to print summaries of them with `--stats`, write
+    // them to a file with `--stats-csv` or show the frame rate in the title with `--show-fps`.
+    let mut frame_stats = frame_stats::FrameStats::default();
+    if let Some(path) = &options.stats_csv {
+        frame_stats.write_csv(Path::new(path)).unwrap();
+    }
+    let stats_interval = options.stats_interval.map(Duration::from_secs_f32);
+    let show_fps = options.show_fps;
+    let mut last_summary = Instant::now();
+    let mut last_title = Instant::now();
 
     event_loop.run(move |event, _, control_flow| {
         match event {
+            // Closing the main window ends the program, other windows are just closed.
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
-                ..
+                window_id,
             } => {
-                *control_flow = ControlFlow::Exit;
+                if windows.is_main(window_id) {
+                    *control_flow = ControlFlow::Exit;
+                } else {
+                    windows.close(window_id);
+                }
+            }
+            Event::LoopDestroyed => {
+                // the pipelines created while running (e.g. by reloading shaders) are saved too
+                pipeline_cache::save();
+                println!("{}", windows.main().frames.stats());
+                // screenshots still being copied or saved are finished before exiting
+                for window in windows.iter_mut() {
+                    window.frames.wait_all();
+                    window.renderer.finish_screenshots();
+                }
+                println!("{}", frame_stats.summary());
+                frame_stats.flush();
             }
             Event::WindowEvent {
                 event: WindowEvent::Resized(_),
-                ..
+                window_id,
             } => {
-                recreate_swapchain = true;
+                if let Some(window) = windows.get_mut(window_id) {
+                    window.renderer.resized();
+                }
+            }
+            // The input of all windows moves the same camera, actions which affect a single
+            // window (like toggling fullscreen) affect the focused one.
+            Event::WindowEvent { event, window_id } => {
+                if let WindowEvent::Focused(focused) = event {
+                    windows.focused(window_id, focused);
+                }
+                // the gui is only drawn into the main window, the events it used don't move the
+                // camera
+                let used = show_gui && windows.is_main(window_id) && gui.window_event(&event);
+                if !used {
+                    input.window_event(&event);
+                }
             }
+            Event::DeviceEvent { event, .. } => input.device_event(&event),
             Event::RedrawEventsCleared => {
                 // It is important to call this function from time to time, otherwise resources will keep
                 // accumulating and you will eventually reach an out of memory error.
                 // Calling this function polls various fences in order to determine what the GPU has
                 // already processed, and frees the resources that are no longer needed.
-                previous_frame_end.as_mut().unwrap().cleanup_finished();
-
-                // Whenever the window resizes we need to recreate everything dependent on the window size.
-                // In this example that includes the swapchain, the framebuffers and the dynamic state viewport.
-                if recreate_swapchain {
-                    // Get the new dimensions of the window.
-                    let dimensions: [u32; 2] = surface.window().inner_size().into();
-
-                    let (new_swapchain, new_images) =
-                    {
-                        let swapchain_rebuilder = swapchain.recreate();
-                        let rebuilder = swapchain_rebuilder.dimensions(dimensions);
-                        match rebuilder.build() {
-                            Ok(r) => r,
-                            // This error tends to happen when the user is manually resizing the window.
-                            // Simply restarting the loop is the easiest way to fix this issue.
-                            Err(SwapchainCreationError::UnsupportedDimensions) => return,
-                            Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
-                        }
-                    };
-
+                for window in windows.iter_mut() {
+                    window.frames.cleanup_finished();
+                }
 
-                     //   match swapchain.recreate_with_dimensions(dimensions) {
-                     //       Ok(r) => r,
-                     //       // This error tends to happen when the user is manually resizing the window.
-                     //       // Simply restarting the loop is the easiest way to fix this issue.
-                     //       Err(SwapchainCreationError::UnsupportedDimensions) => return,
-                     //       Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
-                     //   };
-
-                    swapchain = new_swapchain;
-                    // Because framebuffers contains an Arc on the old swapchain, we need to
-                    // recreate framebuffers as well.
-                    framebuffers = window_size_dependent_setup(
-                        &new_images,
-                        render_pass.clone(),
-                        &mut dynamic_state,
-                    );
-                    recreate_swapchain = false;
+                // Pipelines are rebuilt between frames from the shaders that changed on disk. If
+                // a shader fails to compile, the error is reported and the previous pipeline is
+                // kept, so a typo doesn't end the program.
+                if let Some([vertex, fragment]) = &mut triangle_shaders {
+                    // both are polled, so a change of one doesn't hide a change of the other
+                    if vertex.poll() | fragment.poll() {
+                        let result = shader_reload::graphics_pipeline(
+                            device.clone(),
+                            vertex,
+                            fragment,
+                            &vs.main_entry_point(),
+                            &fs.main_entry_point(),
+                            &build_pipeline,
+                        )
+                        .map(|reloaded| pipeline = reloaded);
+                        shader_reload::report(windows.main().renderer.window(), &[vertex, fragment], &result);
+                    }
+                }
+                if let (Some(shader), Some(particles)) = (&mut particle_shader, &mut particles) {
+                    if shader.poll() {
+                        let result = particles.reload(device.clone(), shader);
+                        shader_reload::report(windows.main().renderer.window(), &[shader], &result);
+                    }
                 }
 
-                // Before we can draw on the output, we have to *acquire* an image from the swapchain. If
-                // no image is available (which happens if you submit draw commands too quickly), then the
-                // function will block.
-                // This operation returns the index of the image that we are allowed to draw upon.
-                //
-                // This function can block if no image is available. The parameter is an optional timeout
-                let (image_num, suboptimal, acquire_future) =
-                    match vulkano::swapchain::acquire_next_image(swapchain.clone(), None) {
-                    Ok(result) => result,
-                    Err(AcquireError::OutOfDate) => { // swapchain was made unusable by some change and needs to be recreated
-                        recreate_swapchain = true;
-                        return;
-                    },
-                    Err(e) => panic!("Failed to acquire next image: {:?}", e),
-                };
-                //// after which the function call will return an error.
-                //let (image_num, suboptimal, acquire_future) =
-                //    match swapchain::acquire_next_image(swapchain.clone(), None) {
-                //        Ok(r) => r,
-                //        Err(AcquireError::OutOfDate) => {
-                //            recreate_swapchain = true;
-                //            return;
-                //        }
-                //        Err(e) => panic!("Failed to acquire next image: {:?}", e),
-                //    };
-
-                // acquire_next_image can be successful, but suboptimal. This means that the swapchain image
-                // will still work, but it may not display correctly. With some drivers this can be when
-                // the window resizes, but it may not cause the swapchain to become out of date.
-                if suboptimal {
-                    recreate_swapchain = true;
+                // the simulation and the camera are advanced by the time which passed since the
+                // last frame, but by no more than a few frames at 60 Hz, so a stall doesn't make
+                // them jump
+                let now = Instant::now();
+                let dt = now.duration_since(last_frame).as_secs_f32().min(0.05);
+                last_frame = now;
+
+                // the input of the frame has been collected, the events from here on belong to
+                // the next one
+                camera_controller.update(&input, dt);
+                camera_controller.apply(&mut camera);
+                if input.action_pressed(Action::ToggleFullscreen) {
+                    windows.focused_mut().renderer.toggle_fullscreen();
+                }
+                // the screenshot is saved in the background, the next frames aren't held up
+                if input.action_pressed(Action::Screenshot) {
+                    let path = screenshot::timestamped_path(Path::new(&options.screenshot_dir));
+                    windows.focused_mut().renderer.request_screenshot(path);
+                }
+                if input.action_pressed(Action::ToggleOverlay) {
+                    show_overlay = !show_overlay;
+                }
+                if input.action_pressed(Action::ToggleGui) {
+                    show_gui = !show_gui;
+                }
+                input.end_frame();
+
+                // A minimized window has nothing to draw into. Once all of them are minimized,
+                // instead of trying again right away, the loop waits for the next event, e.g. the
+                // resize when one of them is restored.
+                if windows.all_minimized() {
+                    if *control_flow != ControlFlow::Exit {
+                        *control_flow = ControlFlow::Wait;
+                    }
+                    return;
+                }
+                if *control_flow != ControlFlow::Exit {
+                    *control_flow = ControlFlow::Poll;
                 }
 
-                // Specify the color to clear the framebuffer with i.e. blue
-                let clear_values = vec![[0.0, 0.0, 1.0, 1.0].into()];
-
-                // In order to draw, we have to build a *command buffer*. The command buffer object holds
-                // the list of commands that are going to be executed.
-                //
-                // Building a command buffer is an expensive operation (usually a few hundred
-                // microseconds), but it is known to be a hot path in the driver and is expected to be
-                // optimized.
-                //
-                // Note that we have to pass a queue family when we create the command buffer. The command
-                // buffer will only be executable on that given queue family.
-                let mut builder = AutoCommandBufferBuilder::primary(
-                    device.clone(),
-                    queue.family(),
-                    CommandBufferUsage::OneTimeSubmit
-                )
-                .unwrap();
+                // the widgets of the gui are built with the events of the last frame, what they
+                // changed is drawn in this one
+                if show_gui {
+                    let previous = parameters.clone();
+                    let dimensions = windows.main().renderer.dimensions();
+                    gui.run(dimensions, |ctx| parameters.ui(ctx, &present_modes));
+                    if parameters.triangle != previous.triangle {
+                        if let Some(triangle) = parameters.triangle {
+                            vertex_buffer = Some(triangle_buffer(triangle));
+                        }
+                    }
+                    if parameters.present_mode != previous.present_mode {
+                        windows.main_mut().renderer.set_present_mode(parameters.present_mode);
+                    }
+                }
 
-                builder
-                    // Before we can draw, we have to *enter a render pass*. There are two methods to do
-                    // this: `draw_inline` and `draw_secondary`. The latter is a bit more advanced and is
-                    // not covered here.
-                    //
-                    // The third parameter builds the list of values to clear the attachments with. The API
-                    // is similar to the list of attachments when building the framebuffers, except that
-                    // only the attachments that use `load: Clear` appear in the list.
-                    .begin_render_pass(
-                        framebuffers[image_num].clone(),
-                        SubpassContents::Inline,
-                        clear_values,
-                    )
-                    .unwrap()
-                    // We are now inside the first subpass of the render pass. We add a draw command.
-                    //
-                    // The last two parameters contain the list of resources to pass to the shaders.
-                    // Since we used an `EmptyPipeline` object, the objects have to be `()`.
-                    .draw(
-                        pipeline.clone(),
-                        &dynamic_state,
-                        vertex_buffer.clone(),
-                        (),
-                        (),
-                    )
-                    .unwrap()
-                    // We leave the render pass by calling `draw_end`. Note that if we had multiple
-                    // subpasses we could have called `next_inline` (or `next_secondary`) to jump to the
-                    // next subpass.
-                    .end_render_pass()
-                    .unwrap();
-
-                // Finish building the command buffer by calling `build`.
-                let command_buffer = builder.build().unwrap();
-
-                let future = previous_frame_end
-                    .take()
-                    .unwrap()
-                    .join(acquire_future)
-                    .then_execute(queue.clone(), command_buffer)
-                    .unwrap()
-                    // The color output is now expected to contain our triangle. But in order to show it on
-                    // the screen, we have to *present* the image by calling `present`.
-                    //
-                    // This function does not actually present the image immediately. Instead it submits a
-                    // present command at the end of the queue. This means that it will only be presented once
-                    // the GPU has finished executing the command buffer that draws the triangle.
-                    .then_swapchain_present(queue.clone(), swapchain.clone(), image_num)
-                    .then_signal_fence_and_flush();
-
-                match future {
-                    Ok(future) => {
-                        previous_frame_end = Some(future.boxed());
+                // The color output is expected to contain our triangle once the command buffer
+                // has been executed. But in order to show it on the screen, we have to *present*
+                // the image, which `draw_frame` does after `record` has added the commands. The
+                // renderer recreates the swapchain before acquiring an image if the window
+                // changed, and the frame is skipped if that isn't possible right now.
+                // Every window draws a frame of its own, the times of the main window are the
+                // ones collected.
+                let mut main_times = None;
+                // the frame rate is the one of the frames presented so far
+                let overlay = show_overlay.then(|| {
+                    let eye = camera.eye;
+                    let overlay = format!(
+                        "{:.0} fps\n{}\n{}\n{:?} camera at ({:.1}, {:.1}, {:.1})",
+                        frame_stats.fps(),
+                        device_name,
+                        sample_info,
+                        camera_controller.mode(),
+                        eye.x,
+                        eye.y,
+                        eye.z,
+                    );
+                    match parameters.mandelbrot {
+                        Some(mandelbrot) => format!("{}\nzoom {:.1}x", overlay, mandelbrot.zoom),
+                        None => overlay,
                     }
-                    Err(FlushError::OutOfDate) => {
-                        recreate_swapchain = true;
-                        previous_frame_end = Some(sync::now(device.clone()).boxed());
+                });
+                for window in windows.iter_mut() {
+                    let view = window.view;
+                    let camera = match view {
+                        View::Scene => &camera,
+                        View::Debug => &debug_camera,
+                    };
+                    let times = present::draw_frame(&queue, &mut window.renderer, &mut window.frames, |builder, renderer, image, uniform_buffer| {
+                        // Specify the color to clear the framebuffer with i.e. blue (unless
+                        // changed in the gui), the debug window is cleared to dark grey
+                        let clear_color = match view {
+                            View::Scene => {
+                                let [r, g, b] = parameters.clear_color;
+                                [r, g, b, 1.0]
+                            }
+                            View::Debug => [0.1, 0.1, 0.1, 1.0],
+                        };
+
+                        // compute work has to be recorded outside of the render pass, the
+                        // particles are moved once per frame, by the scene window
+                        if let (Some(particles), View::Scene) = (&particles, view) {
+                            particles.update(builder, dt);
+                        }
+
+                        // a subpass either contains commands or executes secondary command buffers
+                        let contents = if scene.is_some() {
+                            SubpassContents::SecondaryCommandBuffers
+                        } else {
+                            SubpassContents::Inline
+                        };
+
+                        // the overlay is drawn on top of the scene, and the gui over the overlay
+                        let on_top = Overlay {
+                            text: overlay.as_ref().map(|overlay| {
+                                let label = match view {
+                                    View::Scene => overlay.as_str(),
+                                    View::Debug => "debug camera",
+                                };
+                                text.panel(label, [8.0, 8.0], 4.0, [1.0, 1.0, 1.0, 1.0], [0.0, 0.0, 0.0, 0.6]);
+                                &mut text
+                            }),
+                            gui: (show_gui && view == View::Scene).then_some((&mut gui_painter, &gui)),
+                        };
+
+                        // The scene is drawn in the first subpass of the render pass.
+                        let aspect = renderer.aspect();
+                        present::record_frame(builder, renderer, image, clear_color, contents, |builder, dynamic_state| {
+                            match (&cube, &textured_quad, &instanced_triangles, &particles, &scene, &mandelbrot, &scene_renderer) {
+                                (Some(cube), _, _, _, _, _, _) => {
+                                    let angle = Rad(start.elapsed().as_secs_f32());
+                                    cube.draw(builder, dynamic_state, camera, aspect, angle);
+                                }
+                                (None, Some(quad), _, _, _, _, _) => {
+                                    let scale = texture_sample::square_scale(renderer.dimensions());
+                                    quad.draw(builder, dynamic_state, scale);
+                                }
+                                (None, None, Some(triangles), _, _, _, _) => {
+                                    let angle = start.elapsed().as_secs_f32();
+                                    triangles.draw(builder, dynamic_state, aspect, angle);
+                                }
+                                (None, None, None, Some(particles), _, _, _) => {
+                                    particles.draw(builder, dynamic_state, aspect);
+                                }
+                                (None, None, None, None, Some((pool, scene)), _, _) => {
+                                    let angle = Rad(start.elapsed().as_secs_f32() * 0.2);
+                                    let view_projection = camera.view_projection(aspect) * Matrix4::from_angle_y(angle);
+                                    let secondaries = scene.record_secondary(
+                                        pool,
+                                        &queue,
+                                        &renderer.subpass(),
+                                        dynamic_state,
+                                        view_projection,
+                                    );
+                                    builder.execute_commands_from_vec(secondaries).unwrap();
+                                }
+                                (None, None, None, None, None, Some(mandelbrot), _) => {
+                                    let parameters = parameters.mandelbrot.unwrap_or_default();
+                                    mandelbrot.draw(builder, dynamic_state, aspect, &parameters);
+                                }
+                                (None, None, None, None, None, None, Some(scene_renderer)) => {
+                                    let view_projection = if scene_has_camera {
+                                        camera.view_projection(aspect)
+                                    } else {
+                                        Matrix4::identity()
+                                    };
+                                    scene_renderer.draw(builder, dynamic_state, view_projection);
+                                }
+                                (None, None, None, None, None, None, None) => {
+                                    // The triangle spins around the y axis. The projection is calculated from
+                                    // the current swapchain dimensions, so it keeps its shape after a resize.
+                                    let time = start.elapsed().as_secs_f32();
+                                    *uniform_buffer.write().unwrap() = vs::ty::Data {
+                                        model: Matrix4::from_angle_y(Rad(time)).into(),
+                                        view: camera.view().into(),
+                                        proj: camera.projection(aspect).into(),
+                                        time,
+                                    };
+
+                                    // The descriptor set binds the buffer to `set = 0, binding = 0` of the
+                                    // vertex shader. It is cheap to create, so a new one is built every frame.
+                                    let layout = pipeline.layout().descriptor_set_layouts().get(0).unwrap();
+                                    let set = Arc::new(
+                                        PersistentDescriptorSet::start(layout.clone())
+                                            .add_buffer(uniform_buffer)
+                                            .unwrap()
+                                            .build()
+                                            .unwrap(),
+                                    );
+
+                                    // Without the scene renderer, the scene is a single triangle.
+                                    let vertex_buffer = vertex_buffer.clone().expect("the scene has no triangle");
+
+                                    // The last two parameters contain the descriptor sets and the push
+                                    // constants passed to the shaders.
+                                    index_buffer.draw(
+                                        builder,
+                                        pipeline.clone(),
+                                        dynamic_state,
+                                        vertex_buffer,
+                                        set,
+                                        fs::ty::PushConstants { color: triangle_color },
+                                    );
+                                }
+                            }
+                        }, on_top);
+                    });
+                    if view == View::Scene {
+                        main_times = times;
                     }
-                    Err(e) => {
-                        println!("Failed to flush future: {:?}", e);
-                        previous_frame_end = Some(sync::now(device.clone()).boxed());
+                }
+                let times = match main_times {
+                    Some(times) => times,
+                    None => return,
+                };
+
+                frame_stats.record(times);
+                if let Some(interval) = stats_interval {
+                    if last_summary.elapsed() >= interval {
+                        println!("{}", frame_stats.summary());
+                        last_summary = Instant::now();
                     }
                 }
+                // Shader errors shown in the title are replaced by the frame rate after a
+                // second, they are still printed to the console.
+                if show_fps && last_title.elapsed() >= Duration::from_secs(1) {
+                    windows.main().renderer.window().set_title(&format!("rust-vulkan - {:.0} fps", frame_stats.fps()));
+                    last_title = Instant::now();
+                }
             }
             _ => (),
         }
     });
 }
 
-/// This method is called once during initialization, then again whenever the window is resized
-fn window_size_dependent_setup(
-    images: &[Arc<SwapchainImage<Window>>],
-    render_pass: Arc<RenderPass>,
-    dynamic_state: &mut DynamicState,
-) -> Vec<Arc<dyn FramebufferAbstract + Send + Sync>> { // this notation specifies bounds on the implemented traits of the template parameter, i guess
-    let dimensions = images[0].dimensions();
-
-    // setup viewport data
-    let viewport = Viewport {
-        origin: [0.0, 0.0],
-        dimensions: [dimensions[0] as f32, dimensions[1] as f32],
-        depth_range: 0.0..1.0,
+/// Creates a device and a graphics queue without any window-drawing extensions, for the samples
+/// rendering into offscreen images.
+fn headless_device() -> (Arc<Device>, Arc<Queue>) {
+    let instance = Instance::new(None, Version::V1_2, &InstanceExtensions::none(), None).unwrap();
+    let physical = PhysicalDevice::enumerate(&instance).next().unwrap();
+    let queue_family = physical
+        .queue_families()
+        .find(|&q| q.supports_graphics())
+        .expect("couldn't find a graphical queue family");
+
+    // only the optional features some of the samples make use of, if they are supported
+    let supported = physical.supported_features();
+    let features = Features {
+        multi_draw_indirect: supported.multi_draw_indirect,
+        draw_indirect_first_instance: supported.draw_indirect_first_instance,
+        sampler_anisotropy: supported.sampler_anisotropy,
+        ..Features::none()
     };
-    // create a dynamic state with the viewport specification
-    dynamic_state.viewports = Some(vec![viewport]);
-
-    // create a framebuffer for each image
-    images
-        .iter()
-        .map(|image| {
-            // map allows for a function to capture the element of
-            // the iterator and perform some calculation/tranformation on it
-            let image_view = view::ImageView::new(image.clone()).unwrap();
-            Arc::new(
-                Framebuffer::start(render_pass.clone())
-                    .add(image_view.clone())
-                    .unwrap()
-                    .build()
-                    .unwrap(),
-            ) as Arc<dyn FramebufferAbstract + Send + Sync>
-        })
-        .collect::<Vec<_>>()
+
+    let (device, mut queues) = Device::new(
+        physical,
+        &features,
+        &DeviceExtensions::none(),
+        [(queue_family, 0.5)].iter().cloned(),
+    )
+    .expect("failed to create device");
+
+    (device, queues.next().unwrap())
 }
 
 ///// This method is called once during initialization, then again whenever the window is resized
@@ -0,0 +1,168 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::device::Device;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::render_pass::Subpass;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use crate::pipeline_cache;
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec2 position;
+
+layout(location=0) out vec2 v_position;
+
+void main() {
+    v_position = position;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec2 v_position;
+layout(location=0) out vec4 f_color;
+
+// the point of the complex plane in the center of the viewport, and how far from it the edges
+// of the viewport are
+layout(push_constant) uniform PushConstants {
+    vec2 center;
+    vec2 extent;
+    uint iterations;
+} push_constants;
+
+void main() {
+    // the imaginary axis points up, the viewport y axis down
+    vec2 c = push_constants.center + vec2(v_position.x, -v_position.y) * push_constants.extent;
+
+    // the same check as the compute shader of export_mandelbrot, with a configurable number of
+    // iterations
+    vec2 z = vec2(0.0, 0.0);
+    uint i;
+    for (i = 0; i < push_constants.iterations; i++) {
+        z = vec2(
+            z.x*z.x - z.y*z.y + c.x,
+            z.x*z.y + z.x*z.y + c.y
+        );
+
+        if (length(z) > 4.0) {
+            break;
+        }
+    }
+
+    // greyscale by how many iterations it took to escape, white if it didn't
+    f_color = vec4(vec3(float(i) / float(push_constants.iterations)), 1.0);
+}
+"
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct MandelbrotVertex {
+    position: [f32; 2],
+}
+
+vulkano::impl_vertex!(MandelbrotVertex, position);
+
+/// Which part of the Mandelbrot set is drawn, and how exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MandelbrotParameters {
+    // the point of the complex plane in the center of the window
+    pub center: [f32; 2],
+    // 1 shows the imaginary axis from -1 to 1
+    pub zoom: f32,
+    // the maximum number of iterations per pixel, more show more detail at high zoom
+    pub iterations: u32,
+}
+
+impl Default for MandelbrotParameters {
+    /// The whole set, as exported by `export_mandelbrot`.
+    fn default() -> MandelbrotParameters {
+        MandelbrotParameters {
+            center: [-0.5, 0.0],
+            zoom: 1.0,
+            iterations: 200,
+        }
+    }
+}
+
+/// Pipeline and vertex buffer to draw the Mandelbrot set over the whole viewport, computed per
+/// pixel in the fragment shader.
+pub struct Mandelbrot {
+    pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[MandelbrotVertex]>>,
+}
+
+impl Mandelbrot {
+    pub fn new(device: Arc<Device>, subpass: Subpass) -> Mandelbrot {
+        // load shader for device
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        let pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input_single_buffer::<MandelbrotVertex>()
+                .vertex_shader(vertex_shader.main_entry_point(), ())
+                .triangle_strip()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fragment_shader.main_entry_point(), ())
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::vertex_buffer(),
+            false,
+            [[-1.0, -1.0], [-1.0, 1.0], [1.0, -1.0], [1.0, 1.0]]
+                .iter()
+                .map(|&position| MandelbrotVertex { position }),
+        )
+        .unwrap();
+
+        Mandelbrot { pipeline, vertex_buffer }
+    }
+
+    /// Draws the part of the set given by `parameters`, stretched horizontally to `aspect`, so
+    /// the set keeps its shape.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+        aspect: f32,
+        parameters: &MandelbrotParameters,
+    ) {
+        let extent = 1.0 / parameters.zoom;
+        builder
+            .draw(
+                self.pipeline.clone(),
+                dynamic_state,
+                self.vertex_buffer.clone(),
+                (),
+                fs::ty::PushConstants {
+                    center: parameters.center,
+                    extent: [extent * aspect, extent],
+                    iterations: parameters.iterations.max(1),
+                },
+            )
+            .unwrap();
+    }
+}
@@ -0,0 +1,275 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::device::Device;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::fmt;
+use std::path::Path;
+
+use crate::index::{self, IndexBuffer};
+
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector3, Vector4};
+
+#[derive(Default, Copy, Clone)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+vulkano::impl_vertex!(MeshVertex, position, normal, uv);
+
+#[derive(Debug)]
+pub enum MeshError {
+    UnknownFormat(String),
+    Obj(tobj::LoadError),
+    Gltf(gltf::Error),
+    Empty,
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::UnknownFormat(path) => write!(f, "unknown mesh format: {}", path),
+            MeshError::Obj(err) => write!(f, "failed to load OBJ: {}", err),
+            MeshError::Gltf(err) => write!(f, "failed to load glTF: {}", err),
+            MeshError::Empty => write!(f, "mesh contains no triangles"),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+/// Indexed triangle list, loaded from an OBJ or glTF 2.0 file.
+pub struct Mesh {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    /// Loads all meshes in the file at `path` into a single mesh; the format is chosen by the
+    /// file extension (`.obj`, `.gltf` or `.glb`).
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Mesh, MeshError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        let mut mesh = match extension.as_deref() {
+            Some("obj") => Mesh::load_obj(path)?,
+            Some("gltf") | Some("glb") => Mesh::load_gltf(path)?,
+            _ => return Err(MeshError::UnknownFormat(path.display().to_string())),
+        };
+
+        if mesh.indices.is_empty() {
+            return Err(MeshError::Empty);
+        }
+
+        // OBJ files repeat vertices shared by several objects, and glTF primitives may not be
+        // indexed at all
+        mesh.deduplicate();
+
+        // files without normals get flat-ish normals averaged from the adjacent faces
+        if mesh.vertices.iter().all(|vertex| vertex.normal == [0.0; 3]) {
+            mesh.compute_normals();
+        }
+
+        Ok(mesh)
+    }
+
+    fn load_obj(path: &Path) -> Result<Mesh, MeshError> {
+        let options = tobj::LoadOptions {
+            triangulate: true,
+            // positions, normals and uvs share one index, like the vertex buffer will
+            single_index: true,
+            ..Default::default()
+        };
+        let (models, _materials) = tobj::load_obj(path, &options).map_err(MeshError::Obj)?;
+
+        let mut mesh = Mesh {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        };
+
+        for model in models {
+            let obj = model.mesh;
+            let offset = mesh.vertices.len() as u32;
+
+            for i in 0..obj.positions.len() / 3 {
+                let mut vertex = MeshVertex::default();
+                vertex.position.copy_from_slice(&obj.positions[3 * i..3 * i + 3]);
+                if obj.normals.len() >= 3 * i + 3 {
+                    vertex.normal.copy_from_slice(&obj.normals[3 * i..3 * i + 3]);
+                }
+                if obj.texcoords.len() >= 2 * i + 2 {
+                    // OBJ has its origin in the bottom left corner, vulkan in the top left
+                    vertex.uv = [obj.texcoords[2 * i], 1.0 - obj.texcoords[2 * i + 1]];
+                }
+                mesh.vertices.push(vertex);
+            }
+
+            mesh.indices.extend(obj.indices.iter().map(|index| index + offset));
+        }
+
+        Ok(mesh)
+    }
+
+    fn load_gltf(path: &Path) -> Result<Mesh, MeshError> {
+        let (document, buffers, _images) = gltf::import(path).map_err(MeshError::Gltf)?;
+
+        let mut mesh = Mesh {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        };
+
+        // without a scene all nodes are drawn with their own transform
+        match document.default_scene().or_else(|| document.scenes().next()) {
+            Some(scene) => {
+                for node in scene.nodes() {
+                    mesh.add_gltf_node(&node, Matrix4::identity(), &buffers);
+                }
+            }
+            None => {
+                for node in document.nodes() {
+                    let transform = Matrix4::from(node.transform().matrix());
+                    mesh.add_gltf_mesh(&node, transform, &buffers);
+                }
+            }
+        }
+
+        Ok(mesh)
+    }
+
+    fn add_gltf_node(&mut self, node: &gltf::Node, parent: Matrix4<f32>, buffers: &[gltf::buffer::Data]) {
+        let transform = parent * Matrix4::from(node.transform().matrix());
+
+        self.add_gltf_mesh(node, transform, buffers);
+        for child in node.children() {
+            self.add_gltf_node(&child, transform, buffers);
+        }
+    }
+
+    fn add_gltf_mesh(&mut self, node: &gltf::Node, transform: Matrix4<f32>, buffers: &[gltf::buffer::Data]) {
+        let gltf_mesh = match node.mesh() {
+            Some(mesh) => mesh,
+            None => return,
+        };
+
+        // normals are transformed by the inverse transpose, so non-uniform scaling keeps them
+        // perpendicular to the surface
+        let upper = Matrix3::from_cols(transform.x.truncate(), transform.y.truncate(), transform.z.truncate());
+        let normal_matrix = upper.invert().unwrap_or(upper).transpose();
+
+        for primitive in gltf_mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions = match reader.read_positions() {
+                Some(positions) => positions,
+                None => continue,
+            };
+
+            let offset = self.vertices.len() as u32;
+            for position in positions {
+                let position = transform * Vector4::new(position[0], position[1], position[2], 1.0);
+                self.vertices.push(MeshVertex {
+                    position: position.truncate().into(),
+                    ..Default::default()
+                });
+            }
+            let vertices = &mut self.vertices[offset as usize..];
+
+            if let Some(normals) = reader.read_normals() {
+                for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                    let normal = normal_matrix * Vector3::from(normal);
+                    vertex.normal = normal.normalize().into();
+                }
+            }
+            if let Some(uvs) = reader.read_tex_coords(0) {
+                for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                    vertex.uv = uv;
+                }
+            }
+
+            match reader.read_indices() {
+                Some(indices) => self.indices.extend(indices.into_u32().map(|index| index + offset)),
+                // non-indexed primitives use every vertex exactly once
+                None => self.indices.extend(offset..self.vertices.len() as u32),
+            }
+        }
+    }
+
+    /// Merges vertices with equal attributes and updates the indices to point to the merged ones.
+    pub fn deduplicate(&mut self) {
+        let (vertices, remap) = index::deduplicate(&self.vertices, |vertex| {
+            let [x, y, z] = vertex.position;
+            let [nx, ny, nz] = vertex.normal;
+            let [u, v] = vertex.uv;
+            index::float_bits([x, y, z, nx, ny, nz, u, v])
+        });
+
+        self.vertices = vertices;
+        for index in &mut self.indices {
+            *index = remap[*index as usize];
+        }
+    }
+
+    /// Sets the normal of every vertex to the average of the normals of the triangles using it.
+    pub fn compute_normals(&mut self) {
+        let mut normals = vec![Vector3::new(0.0f32, 0.0, 0.0); self.vertices.len()];
+
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+            let pa = Vector3::from(self.vertices[a].position);
+            let pb = Vector3::from(self.vertices[b].position);
+            let pc = Vector3::from(self.vertices[c].position);
+
+            // not normalized, so larger triangles have more weight
+            let normal = (pb - pa).cross(pc - pa);
+            normals[a] += normal;
+            normals[b] += normal;
+            normals[c] += normal;
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            if normal.magnitude2() > 0.0 {
+                vertex.normal = normal.normalize().into();
+            }
+        }
+    }
+
+    /// Axis aligned bounding box of all vertices, as `(min, max)`.
+    pub fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+
+        for vertex in &self.vertices {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex.position[axis]);
+                max[axis] = max[axis].max(vertex.position[axis]);
+            }
+        }
+
+        (min, max)
+    }
+
+    pub fn vertex_buffer(&self, device: Arc<Device>) -> Arc<CpuAccessibleBuffer<[MeshVertex]>> {
+        CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::vertex_buffer(),
+            false,
+            self.vertices.iter().cloned(),
+        )
+        .expect("failed to create vertex buffer")
+    }
+
+    /// 16 bit indices for meshes with less than 65535 vertices, 32 bit ones otherwise.
+    pub fn index_buffer(&self, device: Arc<Device>) -> IndexBuffer {
+        IndexBuffer::new(device, &self.indices)
+    }
+}
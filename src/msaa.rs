@@ -0,0 +1,28 @@
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::image::SampleCounts;
+
+/// Clamps the requested number of samples per pixel to the highest of 1, 2, 4 or 8 which the
+/// device supports for both color and depth attachments.
+pub fn clamp_samples(physical: PhysicalDevice, requested: u32) -> u32 {
+    let properties = physical.properties();
+    let color = &properties.framebuffer_color_sample_counts;
+    let depth = &properties.framebuffer_depth_sample_counts;
+
+    [8, 4, 2]
+        .iter()
+        .cloned()
+        .find(|&samples| {
+            samples <= requested && supports(color, samples) && supports(depth, samples)
+        })
+        .unwrap_or(1)
+}
+
+fn supports(counts: &SampleCounts, samples: u32) -> bool {
+    match samples {
+        1 => counts.sample1,
+        2 => counts.sample2,
+        4 => counts.sample4,
+        8 => counts.sample8,
+        _ => false,
+    }
+}
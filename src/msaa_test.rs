@@ -0,0 +1,55 @@
+use vulkano::device::Device;
+use vulkano::device::Queue;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use image::{Rgba, ImageBuffer};
+
+use crate::msaa;
+use crate::offscreen::OffscreenTarget;
+use crate::render_pass_sample;
+use crate::scene_file::SceneFile;
+
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>) {
+    println!("performing msaa test");
+
+    let samples = msaa::clamp_samples(device.physical_device(), 4);
+    assert!(samples > 1, "device doesn't support multisampling");
+
+    // render the red triangle on blue of the default scene once without and once with
+    // multisampling
+    let scene = SceneFile::load_or_default(None).unwrap();
+    let aliased_target = OffscreenTarget::start(device.clone(), [256, 256]).build();
+    let aliased = render_pass_sample::render(queue.clone(), device.clone(), &aliased_target, &scene);
+
+    let multisampled_target = OffscreenTarget::start(device.clone(), [256, 256])
+        .samples(samples)
+        .build();
+    let multisampled = render_pass_sample::render(queue.clone(), device.clone(), &multisampled_target, &scene);
+
+    // with a single sample, every pixel is either covered by the triangle or not
+    assert_eq!(count_blended(&aliased), 0, "aliased render contains blended pixels");
+
+    // with multiple samples, the pixels along the edges of the triangle are partially covered
+    // and get a mix of the triangle and the clear color
+    let blended = count_blended(&multisampled);
+    assert!(blended > 0, "no blended edge pixels with {} samples", samples);
+
+    // the interior of the triangle and the background stay untouched
+    assert_eq!(*multisampled.get_pixel(128, 128), RED);
+    assert_eq!(*multisampled.get_pixel(0, 0), BLUE);
+
+    println!("{} blended edge pixels with {} samples", blended, samples);
+    println!("MSAA test successful!");
+}
+
+fn count_blended(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> usize {
+    image
+        .pixels()
+        .filter(|&&pixel| pixel != RED && pixel != BLUE)
+        .count()
+}
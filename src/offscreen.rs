@@ -0,0 +1,491 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, PrimaryCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::command_buffer::SubpassContents;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::format::ClearValue;
+use vulkano::format::Format;
+use vulkano::image::AttachmentImage;
+use vulkano::image::ImageLayout;
+use vulkano::image::ImageUsage;
+use vulkano::image::SampleCount;
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::render_pass::{AttachmentDesc, LoadOp, RenderPassDesc, StoreOp, SubpassDependencyDesc, SubpassDesc};
+use vulkano::render_pass::Framebuffer;
+use vulkano::render_pass::FramebufferAbstract;
+use vulkano::render_pass::RenderPass;
+use vulkano::render_pass::Subpass;
+use vulkano::sync::{AccessFlags, GpuFuture, PipelineStages};
+
+use std::convert::TryFrom;
+// atomically reference counted
+use std::sync::Arc;
+
+use image::{Rgba, ImageBuffer};
+
+/// A render target without a window.
+///
+/// Owns the color attachment (plus an optional depth attachment), a render pass matching those
+/// attachments, the framebuffer and a host visible buffer the color attachment is copied to after
+/// rendering. Any pipeline built for `subpass()` can be drawn into it, and with an overlay
+/// subpass any pipeline built for `overlay_subpass()` on top of that.
+pub struct OffscreenTarget {
+    device: Arc<Device>,
+    dimensions: [u32; 2],
+    format: Format,
+    samples: SampleCount,
+    depth_format: Option<Format>,
+
+    // the image the pipeline renders into
+    color: Arc<AttachmentImage>,
+    // single sampled image the multisampled color attachment is resolved into
+    resolve: Option<Arc<AttachmentImage>>,
+    depth: Option<Arc<AttachmentImage>>,
+
+    render_pass: Arc<RenderPass>,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+
+    // the rendered image is copied into this buffer to read it on the cpu side
+    readback: Arc<CpuAccessibleBuffer<[u8]>>,
+}
+
+/// Builder for an `OffscreenTarget`, started with `OffscreenTarget::start`.
+pub struct OffscreenTargetBuilder {
+    device: Arc<Device>,
+    dimensions: [u32; 2],
+    format: Format,
+    samples: u32,
+    depth_format: Option<Format>,
+    overlay: bool,
+    render_pass: Option<Arc<RenderPass>>,
+}
+
+impl OffscreenTargetBuilder {
+    /// Sets the format of the color attachment. Defaults to `R8G8B8A8Unorm`.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the number of samples per pixel of the color (and depth) attachment.
+    /// Defaults to 1; with more than one sample the color attachment is resolved before readback.
+    pub fn samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Adds a depth attachment of the given format.
+    pub fn depth_format(mut self, format: Format) -> Self {
+        self.depth_format = Some(format);
+        self
+    }
+
+    /// Adds a second subpass, which draws on top of the first one into the single sampled image
+    /// without the depth attachment, like the overlay subpass of the windows.
+    pub fn overlay(mut self) -> Self {
+        self.overlay = true;
+        self
+    }
+
+    /// Uses the render pass of another target with the same format, samples, depth format and
+    /// overlay subpass instead of creating a new one, so pipelines built for one target are used with the same
+    /// render pass in all of them.
+    pub fn render_pass(mut self, render_pass: Arc<RenderPass>) -> Self {
+        self.render_pass = Some(render_pass);
+        self
+    }
+
+    pub fn build(self) -> OffscreenTarget {
+        let samples = SampleCount::try_from(self.samples).expect("unsupported sample count");
+        let multisampled = samples != SampleCount::Sample1;
+
+        let render_pass = match &self.render_pass {
+            Some(render_pass) => render_pass.clone(),
+            None => Arc::new(
+                RenderPass::new(
+                    self.device.clone(),
+                    offscreen_render_pass_desc(self.format, samples, self.depth_format, self.overlay),
+                )
+                .expect("failed to create offscreen render pass"),
+            ),
+        };
+
+        // the image which is read back needs to be usable as a transfer source
+        let transfer_source = ImageUsage {
+            transfer_source: true,
+            ..ImageUsage::none()
+        };
+
+        let color = AttachmentImage::multisampled_with_usage(
+            self.device.clone(),
+            self.dimensions,
+            samples,
+            self.format,
+            if multisampled { ImageUsage::none() } else { transfer_source },
+        )
+        .expect("failed to create color attachment");
+
+        let resolve = if multisampled {
+            Some(
+                AttachmentImage::with_usage(
+                    self.device.clone(),
+                    self.dimensions,
+                    self.format,
+                    transfer_source,
+                )
+                .expect("failed to create resolve attachment"),
+            )
+        } else {
+            None
+        };
+
+        let depth = self.depth_format.map(|format| {
+            AttachmentImage::multisampled(self.device.clone(), self.dimensions, samples, format)
+                .expect("failed to create depth attachment")
+        });
+
+        // the attachments need to be added in the same order as in the render pass description:
+        // color, resolve, depth
+        let color_view = ImageView::new(color.clone()).unwrap();
+        let framebuffer = match (&resolve, &depth) {
+            (None, None) => Arc::new(
+                Framebuffer::start(render_pass.clone())
+                    .add(color_view).unwrap()
+                    .build().unwrap(),
+            ) as Arc<dyn FramebufferAbstract + Send + Sync>,
+            (Some(resolve), None) => Arc::new(
+                Framebuffer::start(render_pass.clone())
+                    .add(color_view).unwrap()
+                    .add(ImageView::new(resolve.clone()).unwrap()).unwrap()
+                    .build().unwrap(),
+            ) as Arc<dyn FramebufferAbstract + Send + Sync>,
+            (None, Some(depth)) => Arc::new(
+                Framebuffer::start(render_pass.clone())
+                    .add(color_view).unwrap()
+                    .add(ImageView::new(depth.clone()).unwrap()).unwrap()
+                    .build().unwrap(),
+            ) as Arc<dyn FramebufferAbstract + Send + Sync>,
+            (Some(resolve), Some(depth)) => Arc::new(
+                Framebuffer::start(render_pass.clone())
+                    .add(color_view).unwrap()
+                    .add(ImageView::new(resolve.clone()).unwrap()).unwrap()
+                    .add(ImageView::new(depth.clone()).unwrap()).unwrap()
+                    .build().unwrap(),
+            ) as Arc<dyn FramebufferAbstract + Send + Sync>,
+        };
+
+        let pixel_size = self.format.size().expect("format has no fixed pixel size");
+        let readback = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::all(),
+            false,
+            (0..(self.dimensions[0] * self.dimensions[1]) as usize * pixel_size as usize).map(|_| 0u8),
+        )
+        .expect("Failed to create readback buffer");
+
+        OffscreenTarget {
+            device: self.device,
+            dimensions: self.dimensions,
+            format: self.format,
+            samples,
+            depth_format: self.depth_format,
+            color,
+            resolve,
+            depth,
+            render_pass,
+            framebuffer,
+            readback,
+        }
+    }
+}
+
+impl OffscreenTarget {
+    /// Starts building a target of the given size with a single sampled `R8G8B8A8Unorm` color
+    /// attachment and no depth attachment.
+    pub fn start(device: Arc<Device>, dimensions: [u32; 2]) -> OffscreenTargetBuilder {
+        OffscreenTargetBuilder {
+            device,
+            dimensions,
+            format: Format::R8G8B8A8Unorm,
+            samples: 1,
+            depth_format: None,
+            overlay: false,
+            render_pass: None,
+        }
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn samples(&self) -> SampleCount {
+        self.samples
+    }
+
+    pub fn depth_format(&self) -> Option<Format> {
+        self.depth_format
+    }
+
+    pub fn render_pass(&self) -> Arc<RenderPass> {
+        self.render_pass.clone()
+    }
+
+    /// The first subpass of the render pass, pipelines drawing into this target have to be built
+    /// for it.
+    pub fn subpass(&self) -> Subpass {
+        Subpass::from(self.render_pass.clone(), 0).unwrap()
+    }
+
+    /// The subpass drawing on top of the first one, if the target was built with `overlay`.
+    pub fn overlay_subpass(&self) -> Option<Subpass> {
+        Subpass::from(self.render_pass.clone(), 1)
+    }
+
+    pub fn framebuffer(&self) -> Arc<dyn FramebufferAbstract + Send + Sync> {
+        self.framebuffer.clone()
+    }
+
+    /// The single sampled image holding the final result of a render.
+    pub fn image(&self) -> Arc<AttachmentImage> {
+        self.resolve.as_ref().unwrap_or(&self.color).clone()
+    }
+
+    /// Dynamic state with a viewport covering the whole target.
+    pub fn dynamic_state(&self) -> DynamicState {
+        DynamicState {
+            viewports: Some(vec![Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [self.dimensions[0] as f32, self.dimensions[1] as f32],
+                depth_range: 0.0..1.0,
+            }]),
+            ..DynamicState::none()
+        }
+    }
+
+    /// Clear values for `begin_render_pass`, one per attachment in render pass order.
+    pub fn clear_values(&self, clear_color: [f32; 4]) -> Vec<ClearValue> {
+        let mut clear_values = vec![clear_color.into()];
+        if self.resolve.is_some() {
+            // the resolve attachment is not cleared, it gets overwritten entirely
+            clear_values.push(ClearValue::None);
+        }
+        if self.depth.is_some() {
+            clear_values.push(1.0f32.into());
+        }
+        clear_values
+    }
+
+    /// Records the render pass, lets `draw` add the draw commands to it and copies the result to
+    /// the readback buffer. Blocks until the gpu is done and returns the rendered image.
+    ///
+    /// Nothing is drawn in the overlay subpass, if there is one.
+    pub fn render<F>(&self, queue: Arc<Queue>, clear_color: [f32; 4], draw: F) -> ImageBuffer<Rgba<u8>, Vec<u8>>
+    where
+        F: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>),
+    {
+        self.render_subpasses(queue, clear_color, draw, |_| ())
+    }
+
+    /// Like `render`, with `overlay` adding the draw commands of the overlay subpass after those
+    /// of `draw`. Panics if the target was built without `overlay`.
+    pub fn render_with_overlay<F, G>(
+        &self,
+        queue: Arc<Queue>,
+        clear_color: [f32; 4],
+        draw: F,
+        overlay: G,
+    ) -> ImageBuffer<Rgba<u8>, Vec<u8>>
+    where
+        F: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>),
+        G: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>),
+    {
+        assert!(self.overlay_subpass().is_some(), "the target has no overlay subpass");
+        self.render_subpasses(queue, clear_color, draw, overlay)
+    }
+
+    fn render_subpasses<F, G>(
+        &self,
+        queue: Arc<Queue>,
+        clear_color: [f32; 4],
+        draw: F,
+        overlay: G,
+    ) -> ImageBuffer<Rgba<u8>, Vec<u8>>
+    where
+        F: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>),
+        G: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>),
+    {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        builder
+            .begin_render_pass(
+                self.framebuffer.clone(),
+                SubpassContents::Inline,
+                self.clear_values(clear_color),
+            )
+            .unwrap();
+
+        draw(&mut builder);
+        // the render pass can only be ended in its last subpass
+        if self.overlay_subpass().is_some() {
+            builder.next_subpass(SubpassContents::Inline).unwrap();
+            overlay(&mut builder);
+        }
+
+        builder.end_render_pass().unwrap();
+        self.record_readback(&mut builder);
+
+        let command = builder.build().unwrap();
+        let finished = command.execute(queue).unwrap();
+        finished.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+        self.read()
+    }
+
+    /// Adds the copy of the rendered image into the readback buffer to `builder`.
+    /// Has to be recorded outside of the render pass.
+    pub fn record_readback(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) {
+        builder
+            .copy_image_to_buffer(self.image(), self.readback.clone())
+            .unwrap();
+    }
+
+    /// Converts the content of the readback buffer to an RGBA image. The command buffer with the
+    /// copy recorded by `record_readback` needs to have finished executing.
+    pub fn read(&self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let content = self.readback.read().unwrap();
+        let [width, height] = self.dimensions;
+        ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, to_rgba8(self.format, &content))
+            .unwrap()
+    }
+}
+
+/// Describes a render pass with a subpass drawing into a color attachment of the given format,
+/// resolved into a second color attachment if multisampled, and an optional depth attachment.
+/// With `overlay` a second subpass draws into the single sampled color attachment after it.
+fn offscreen_render_pass_desc(
+    format: Format,
+    samples: SampleCount,
+    depth_format: Option<Format>,
+    overlay: bool,
+) -> RenderPassDesc {
+    let multisampled = samples != SampleCount::Sample1;
+
+    let mut attachments = vec![AttachmentDesc {
+        format,
+        samples,
+        load: LoadOp::Clear,
+        // the multisampled image is only needed until it has been resolved
+        store: if multisampled { StoreOp::DontCare } else { StoreOp::Store },
+        stencil_load: LoadOp::DontCare,
+        stencil_store: StoreOp::DontCare,
+        initial_layout: ImageLayout::ColorAttachmentOptimal,
+        final_layout: ImageLayout::ColorAttachmentOptimal,
+    }];
+
+    let mut resolve_attachments = Vec::new();
+    if multisampled {
+        resolve_attachments.push((attachments.len(), ImageLayout::ColorAttachmentOptimal));
+        attachments.push(AttachmentDesc {
+            format,
+            samples: SampleCount::Sample1,
+            load: LoadOp::DontCare,
+            store: StoreOp::Store,
+            stencil_load: LoadOp::DontCare,
+            stencil_store: StoreOp::DontCare,
+            initial_layout: ImageLayout::ColorAttachmentOptimal,
+            final_layout: ImageLayout::ColorAttachmentOptimal,
+        });
+    }
+
+    let depth_stencil = depth_format.map(|depth_format| {
+        let index = attachments.len();
+        attachments.push(AttachmentDesc {
+            format: depth_format,
+            samples,
+            load: LoadOp::Clear,
+            store: StoreOp::DontCare,
+            stencil_load: LoadOp::Clear,
+            stencil_store: StoreOp::DontCare,
+            initial_layout: ImageLayout::DepthStencilAttachmentOptimal,
+            final_layout: ImageLayout::DepthStencilAttachmentOptimal,
+        });
+        (index, ImageLayout::DepthStencilAttachmentOptimal)
+    });
+
+    // the image which is read back, the resolve attachment if there is one
+    let single_sampled = resolve_attachments.first().map_or(0, |&(index, _)| index);
+
+    let mut subpasses = vec![SubpassDesc {
+        color_attachments: vec![(0, ImageLayout::ColorAttachmentOptimal)],
+        depth_stencil,
+        input_attachments: vec![],
+        resolve_attachments,
+        preserve_attachments: vec![],
+    }];
+    let mut dependencies = vec![];
+
+    if overlay {
+        subpasses.push(SubpassDesc {
+            color_attachments: vec![(single_sampled, ImageLayout::ColorAttachmentOptimal)],
+            depth_stencil: None,
+            input_attachments: vec![],
+            resolve_attachments: vec![],
+            preserve_attachments: vec![],
+        });
+        // the overlay is blended over the color (or resolve) writes of the first subpass
+        let color_output = PipelineStages {
+            color_attachment_output: true,
+            ..PipelineStages::none()
+        };
+        dependencies.push(SubpassDependencyDesc {
+            source_subpass: 0,
+            destination_subpass: 1,
+            source_stages: color_output,
+            destination_stages: color_output,
+            source_access: AccessFlags {
+                color_attachment_write: true,
+                ..AccessFlags::none()
+            },
+            destination_access: AccessFlags {
+                color_attachment_read: true,
+                color_attachment_write: true,
+                ..AccessFlags::none()
+            },
+            by_region: true,
+        });
+    }
+
+    RenderPassDesc::new(attachments, subpasses, dependencies)
+}
+
+/// Whether `to_rgba8` can convert images of `format`.
+pub fn converts_to_rgba8(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb | Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb
+    )
+}
+
+/// Reorders the raw bytes of an image with 8 bit per channel into RGBA order.
+pub fn to_rgba8(format: Format, raw: &[u8]) -> Vec<u8> {
+    match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => raw.to_vec(),
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => raw
+            .chunks_exact(4)
+            .flat_map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+            .collect(),
+        _ => panic!("can't convert {:?} to RGBA8", format),
+    }
+}
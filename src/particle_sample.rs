@@ -0,0 +1,337 @@
+use vulkano::buffer::{BufferUsage, TypedBufferAccess};
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, PrimaryCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::command_buffer::SubpassContents;
+use vulkano::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::render_pass::Subpass;
+use vulkano::sync::GpuFuture;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::path::Path;
+
+use image::{Rgba, ImageBuffer};
+
+use crate::cli::Options;
+use crate::offscreen::OffscreenTarget;
+use crate::pipeline_cache;
+use crate::shader_reload::{self, ShaderError, WatchedShader};
+
+/// Time step of the headless sample and the test, in seconds.
+pub const FRAME_TIME: f32 = 1.0 / 60.0;
+
+// downwards acceleration, in clip space units per second squared
+const GRAVITY: f32 = 1.5;
+// fraction of the velocity kept when bouncing off a border
+const BOUNCE: f32 = 0.8;
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "shaders/particles.comp"
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec2 position;
+layout(location=1) in vec2 velocity;
+
+layout(location=0) out vec3 v_color;
+
+layout(push_constant) uniform PushConstants {
+    // width / height of the viewport
+    float aspect;
+} push_constants;
+
+void main() {
+    // slow particles are blue, fast ones yellow
+    float speed = clamp(length(velocity) * 0.5, 0.0, 1.0);
+    v_color = mix(vec3(0.2, 0.4, 1.0), vec3(1.0, 0.9, 0.5), speed);
+
+    // other sizes need the large_points feature
+    gl_PointSize = 1.0;
+    gl_Position = vec4(position.x / max(push_constants.aspect, 1.0), position.y * min(push_constants.aspect, 1.0), 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec3 v_color;
+layout(location=0) out vec4 f_color;
+
+void main() {
+    f_color = vec4(v_color, 1.0);
+}
+"
+    }
+}
+
+/// A particle as stored in the storage buffer, which is also the vertex buffer of the points.
+///
+/// Two `vec2` have the same layout in a std430 storage buffer as in this struct.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone)]
+pub struct Particle {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+}
+
+vulkano::impl_vertex!(Particle, position, velocity);
+
+/// `count` particles on a disc below the center, shooting upwards and outwards like a fountain.
+pub fn spawn_particles(count: u32) -> Vec<Particle> {
+    (0..count)
+        .map(|i| {
+            // the golden angle spreads the particles evenly over the disc
+            let t = (i as f32 + 0.5) / count as f32;
+            let (sin, cos) = (i as f32 * 2.399_963).sin_cos();
+            let radius = 0.1 * t.sqrt();
+            let speed = 0.2 + 0.6 * t;
+            Particle {
+                position: [cos * radius, 0.5 + sin * radius],
+                velocity: [cos * speed, sin * speed - 1.2],
+            }
+        })
+        .collect()
+}
+
+/// Advances `particles` by `dt` seconds on the cpu, exactly like the compute shader does.
+pub fn step(particles: &mut [Particle], dt: f32) {
+    for particle in particles {
+        particle.velocity[1] += GRAVITY * dt;
+        for axis in 0..2 {
+            particle.position[axis] += particle.velocity[axis] * dt;
+            if particle.position[axis].abs() > 1.0 {
+                particle.position[axis] = particle.position[axis].signum();
+                particle.velocity[axis] *= -BOUNCE;
+            }
+        }
+    }
+}
+
+/// Particles which are moved by a compute shader and drawn as points, without ever leaving
+/// the gpu.
+pub struct ParticleSystem {
+    compute_pipeline: Arc<ComputePipeline>,
+    draw_pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    particles: Arc<CpuAccessibleBuffer<[Particle]>>,
+    set: Arc<dyn DescriptorSet + Send + Sync>,
+}
+
+impl ParticleSystem {
+    pub fn new(device: Arc<Device>, subpass: Subpass, particles: &[Particle]) -> ParticleSystem {
+        // load shaders for device
+        let compute_shader = cs::Shader::load(device.clone()).expect("failed to create compute shader");
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        let compute_pipeline = Arc::new(
+            ComputePipeline::new(
+                device.clone(),
+                &compute_shader.main_entry_point(),
+                &(),
+                Some(pipeline_cache::get(&device)),
+            )
+                .expect("failed to create compute pipeline"),
+        );
+
+        let builder = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<Particle>()
+            .vertex_shader(vertex_shader.main_entry_point(), ())
+            .point_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fragment_shader.main_entry_point(), ());
+
+        let builder = if subpass.has_depth() {
+            builder.depth_stencil_simple_depth()
+        } else {
+            builder
+        };
+
+        let draw_pipeline = Arc::new(
+            builder
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        // written by the compute shader and read as vertices by the draw
+        let particles = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage {
+                storage_buffer: true,
+                vertex_buffer: true,
+                ..BufferUsage::none()
+            },
+            false,
+            particles.iter().cloned(),
+        )
+        .expect("failed to create particle buffer");
+
+        let set = particle_set(&compute_pipeline, particles.clone());
+
+        ParticleSystem {
+            compute_pipeline,
+            draw_pipeline,
+            particles,
+            set,
+        }
+    }
+
+    /// Replaces the compute shader with the current version of `shader`, which has to keep the
+    /// interface of `shaders/particles.comp`. The previous one stays in use if this fails.
+    pub fn reload(&mut self, device: Arc<Device>, shader: &WatchedShader) -> Result<(), ShaderError> {
+        let reference = cs::Shader::load(device.clone()).expect("failed to create compute shader");
+        let compute_pipeline = shader_reload::compute_pipeline(device, shader, &reference.main_entry_point())?;
+
+        self.set = particle_set(&compute_pipeline, self.particles.clone());
+        self.compute_pipeline = compute_pipeline;
+        Ok(())
+    }
+
+    /// Records the dispatch moving all particles by `dt` seconds. Has to be recorded outside of
+    /// the render pass, before `draw`.
+    ///
+    /// The builder keeps track of the accesses to the particle buffer: it adds a barrier between
+    /// the shader writes of the dispatch and the vertex reads of the following draw, as well as
+    /// between the draw of this frame and the dispatch of the next one if both are recorded
+    /// into the same command buffer. Across command buffers the futures of the submissions
+    /// take care of the synchronization.
+    pub fn update(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, dt: f32) {
+        let particle_count = self.particles.len() as u32;
+        builder
+            .dispatch(
+                [(particle_count + 63) / 64, 1, 1],
+                self.compute_pipeline.clone(),
+                self.set.clone(),
+                cs::ty::PushConstants {
+                    dt,
+                    gravity: GRAVITY,
+                    bounce: BOUNCE,
+                    particle_count,
+                },
+            )
+            .unwrap();
+    }
+
+    /// Draws all particles as points.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+        aspect: f32,
+    ) {
+        builder
+            .draw(
+                self.draw_pipeline.clone(),
+                dynamic_state,
+                self.particles.clone(),
+                (),
+                vs::ty::PushConstants { aspect },
+            )
+            .unwrap();
+    }
+
+    /// Copies the current state of the particles. The gpu must not be using the buffer.
+    pub fn read(&self) -> Vec<Particle> {
+        self.particles.read().unwrap().to_vec()
+    }
+}
+
+fn particle_set(
+    pipeline: &Arc<ComputePipeline>,
+    particles: Arc<CpuAccessibleBuffer<[Particle]>>,
+) -> Arc<dyn DescriptorSet + Send + Sync> {
+    let layout = pipeline.layout().descriptor_set_layouts().get(0).unwrap();
+    Arc::new(
+        PersistentDescriptorSet::start(layout.clone())
+            .add_buffer(particles)
+            .unwrap()
+            .build()
+            .unwrap(),
+    )
+}
+
+/// Moves the particles by `dt` seconds and renders them headlessly into `target`.
+pub fn render_frame(
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    target: &OffscreenTarget,
+    particles: &ParticleSystem,
+    dt: f32,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let mut builder = AutoCommandBufferBuilder::primary(
+        device,
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+
+    particles.update(&mut builder, dt);
+
+    builder
+        .begin_render_pass(
+            target.framebuffer(),
+            SubpassContents::Inline,
+            target.clear_values([0.0, 0.0, 0.0, 1.0]),
+        )
+        .unwrap();
+
+    let [width, height] = target.dimensions();
+    particles.draw(&mut builder, &target.dynamic_state(), width as f32 / height as f32);
+
+    builder.end_render_pass().unwrap();
+    target.record_readback(&mut builder);
+
+    let command = builder.build().unwrap();
+    let finished = command.execute(queue).unwrap();
+    finished.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+    target.read()
+}
+
+/// The path of frame `frame`, with its number appended to the file name of `path`, e.g.
+/// `particles_007.png`.
+pub fn frame_path(path: &str, frame: u32) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("frame");
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("png");
+    path.with_file_name(format!("{}_{:03}.{}", stem, frame, extension))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// `particle-sample` simulates `--particles N` particles for `--frames N` frames of 1/60 s and
+/// saves every frame as `particles_NNN.png`.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let target = OffscreenTarget::start(device.clone(), options.size)
+        .samples(samples)
+        .build();
+
+    let particles = ParticleSystem::new(device.clone(), target.subpass(), &spawn_particles(options.particles));
+    let output = options.output.as_deref().unwrap_or("particles.png");
+
+    for frame in 0..options.frames {
+        let image_buffer = render_frame(queue.clone(), device.clone(), &target, &particles, FRAME_TIME);
+        image_buffer.save(frame_path(output, frame)).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+use vulkano::device::Device;
+use vulkano::device::Queue;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use crate::offscreen::OffscreenTarget;
+use crate::particle_sample::{self, ParticleSystem};
+
+const FRAMES: u32 = 30;
+
+// the gpu may fuse multiplications and additions, so the results differ slightly
+const TOLERANCE: f32 = 1e-3;
+
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>) {
+    println!("performing particle test");
+
+    let target = OffscreenTarget::start(device.clone(), [128, 128]).build();
+    let mut expected = particle_sample::spawn_particles(1000);
+    let particles = ParticleSystem::new(device.clone(), target.subpass(), &expected);
+
+    let mut image_buffer = None;
+    for _ in 0..FRAMES {
+        image_buffer = Some(particle_sample::render_frame(
+            queue.clone(),
+            device.clone(),
+            &target,
+            &particles,
+            particle_sample::FRAME_TIME,
+        ));
+        particle_sample::step(&mut expected, particle_sample::FRAME_TIME);
+    }
+
+    // the compute shader moved every particle like the cpu did
+    for (i, (actual, expected)) in particles.read().iter().zip(expected.iter()).enumerate() {
+        for axis in 0..2 {
+            assert!(
+                (actual.position[axis] - expected.position[axis]).abs() < TOLERANCE
+                    && (actual.velocity[axis] - expected.velocity[axis]).abs() < TOLERANCE,
+                "particle {} is at {:?}, expected {:?}",
+                i,
+                actual,
+                expected,
+            );
+        }
+    }
+
+    // and the last frame shows the moved particles, not the initial ones
+    let image_buffer = image_buffer.unwrap();
+    let [width, height] = target.dimensions();
+    // the points are a pixel wide, so each one lights the pixel it is in
+    let lit = |position: [f32; 2]| {
+        let x = ((position[0] * 0.5 + 0.5) * width as f32).min(width as f32 - 1.0) as u32;
+        let y = ((position[1] * 0.5 + 0.5) * height as f32).min(height as f32 - 1.0) as u32;
+        image_buffer.get_pixel(x, y).0[..3].iter().any(|&channel| channel > 0)
+    };
+    let drawn = expected.iter().filter(|particle| lit(particle.position)).count();
+    assert!(drawn > expected.len() * 9 / 10, "only {} of {} particles were drawn", drawn, expected.len());
+
+    println!("Particle test successful!");
+}
@@ -0,0 +1,127 @@
+use vulkano::device::Device;
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::pipeline::cache::PipelineCache;
+
+// atomically reference counted
+use std::sync::{Arc, Weak};
+
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+// Pipelines are created all over the program, usually next to the shaders they use. Instead of
+// passing the cache to every one of them, the cache of each device is kept here, and looked up
+// by `get` when a pipeline is created. A cache holds on to its device, so the entries are
+// removed by `save`, which is the last thing done with a device.
+static CACHES: Mutex<Vec<DeviceCache>> = Mutex::new(Vec::new());
+
+struct DeviceCache {
+    device: Weak<Device>,
+    cache: Arc<PipelineCache>,
+    // the file the cache is saved to
+    path: PathBuf,
+}
+
+// VK_PIPELINE_CACHE_HEADER_VERSION_ONE
+const HEADER_VERSION: u32 = 1;
+const HEADER_SIZE: usize = 32;
+
+/// The file in `dir` holding the cache of `physical`. Drivers can only reuse the pipelines of
+/// their own device and version, so every combination gets its own file.
+pub fn cache_file(dir: &Path, physical: PhysicalDevice) -> PathBuf {
+    let properties = physical.properties();
+    dir.join(format!(
+        "{:04x}_{:04x}_{:08x}.bin",
+        properties.vendor_id, properties.device_id, properties.driver_version,
+    ))
+}
+
+/// Loads the cache of `device` from its file in `dir`, or starts with an empty cache if there
+/// is no usable file. The pipelines created afterwards are added to it, and `save` writes it
+/// back. Has to be called before any pipeline of `device` is created.
+pub fn load(device: &Arc<Device>, dir: &Path) {
+    let path = cache_file(dir, device.physical_device());
+    let start = Instant::now();
+
+    let cache = match std::fs::read(&path) {
+        Ok(data) if header_matches(&data, device.physical_device()) => {
+            // the driver validates the data again, and ignores it if it isn't usable
+            let cache = unsafe { PipelineCache::with_data(device.clone(), &data) }
+                .expect("failed to create pipeline cache");
+            println!(
+                "loaded pipeline cache {} ({} bytes) in {:.2?}",
+                path.display(),
+                data.len(),
+                start.elapsed(),
+            );
+            cache
+        }
+        Ok(_) => {
+            println!("ignoring pipeline cache {}, it was written by another device", path.display());
+            PipelineCache::empty(device.clone()).expect("failed to create pipeline cache")
+        }
+        Err(_) => {
+            println!("no pipeline cache at {}, starting with an empty one", path.display());
+            PipelineCache::empty(device.clone()).expect("failed to create pipeline cache")
+        }
+    };
+
+    let mut caches = CACHES.lock().unwrap();
+    let device = Arc::downgrade(device);
+    caches.retain(|entry| !Weak::ptr_eq(&entry.device, &device));
+    caches.push(DeviceCache { device, cache, path });
+}
+
+/// The cache to create the pipelines of `device` with, loaded by `load`.
+pub fn get(device: &Arc<Device>) -> Arc<PipelineCache> {
+    let device = Arc::downgrade(device);
+    let caches = CACHES.lock().unwrap();
+    match caches.iter().find(|entry| Weak::ptr_eq(&entry.device, &device)) {
+        Some(entry) => entry.cache.clone(),
+        None => panic!("no pipeline cache for the device, it has to be loaded before creating pipelines"),
+    }
+}
+
+/// Writes every cache loaded by `load` back to its file, and releases them and their devices;
+/// no pipelines can be created with the cache afterwards. Failures are only reported, the
+/// cache is just rebuilt on the next start.
+pub fn save() {
+    let mut caches = CACHES.lock().unwrap();
+    for entry in caches.drain(..) {
+        let path = &entry.path;
+        let result = entry
+            .cache
+            .get_data()
+            .map_err(|err| err.to_string())
+            .and_then(|data| {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+                }
+                std::fs::write(path, &data).map_err(|err| err.to_string())?;
+                Ok(data.len())
+            });
+
+        match result {
+            Ok(size) => println!("saved pipeline cache {} ({} bytes)", path.display(), size),
+            Err(err) => eprintln!("failed to save pipeline cache {}: {}", path.display(), err),
+        }
+    }
+}
+
+// Checks the header every pipeline cache starts with, so data of other devices (e.g. a file
+// copied from another machine) is never handed to the driver.
+fn header_matches(data: &[u8], physical: PhysicalDevice) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+
+    let word = |offset: usize| u32::from_ne_bytes(data[offset..offset + 4].try_into().unwrap());
+    let properties = physical.properties();
+
+    word(0) as usize >= HEADER_SIZE
+        && word(4) == HEADER_VERSION
+        && word(8) == properties.vendor_id
+        && word(12) == properties.device_id
+        && data[16..32] == properties.pipeline_cache_uuid
+}
@@ -0,0 +1,164 @@
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, DynamicState, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::SubpassContents;
+use vulkano::device::Queue;
+use vulkano::format::ClearValue;
+use vulkano::render_pass::FramebufferAbstract;
+use vulkano::sync::GpuFuture;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::time::Instant;
+
+use crate::frame_stats::FrameTimes;
+use crate::frames::{FrameFence, FramesInFlight};
+use crate::gui::{Gui, GuiPainter};
+use crate::text::TextRenderer;
+
+/// Where frames are presented to: the swapchain of a window (`Renderer`), or a ring of offscreen
+/// images standing in for one (`VirtualSwapchain`).
+pub trait PresentTarget {
+    type Image: PresentImage;
+
+    /// Dynamic state with a viewport covering the whole image.
+    fn dynamic_state(&self) -> &DynamicState;
+
+    /// Clear values for `begin_render_pass`, one per attachment in render pass order.
+    fn clear_values(&self, clear_color: [f32; 4]) -> Vec<ClearValue>;
+
+    /// The image to draw the next frame into, or `None` if no frame can be drawn right now.
+    fn acquire(&mut self) -> Option<Self::Image>;
+
+    /// Executes `command_buffer` after `previous` once `image` is available, and presents the
+    /// image afterwards. Returns the fence signaled when the frame is done, or `None` if the
+    /// submission failed.
+    fn present(
+        &mut self,
+        previous: Box<dyn GpuFuture>,
+        image: Self::Image,
+        command_buffer: PrimaryAutoCommandBuffer,
+    ) -> Option<FrameFence>;
+}
+
+/// An image acquired from a `PresentTarget`.
+pub trait PresentImage {
+    /// The framebuffer drawing into the image, for `begin_render_pass`.
+    fn framebuffer(&self) -> Arc<dyn FramebufferAbstract + Send + Sync>;
+}
+
+/// Draws one frame into `target`: waits for the next slot of `frames`, acquires an image, lets
+/// `record` add the commands of the frame and presents it.
+///
+/// `record` gets the target and the acquired image to begin the render pass with, and the
+/// resources of the frame slot. Returns what the frame took, or `None` if it was skipped because
+/// no image could be acquired.
+pub fn draw_frame<T, R, F>(
+    queue: &Arc<Queue>,
+    target: &mut T,
+    frames: &mut FramesInFlight<R>,
+    record: F,
+) -> Option<FrameTimes>
+where
+    T: PresentTarget,
+    R: Clone,
+    F: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &T, &T::Image, R),
+{
+    let start = Instant::now();
+
+    // The resources of this frame may still be in use by the frame which was recorded in the
+    // same slot. Waiting here, before acquiring an image, is where the CPU trades latency for
+    // throughput: with more frames in flight, it waits less.
+    let resources = frames.wait().clone();
+
+    // Before we can draw on the output, we have to *acquire* an image from the swapchain.
+    let acquire_start = Instant::now();
+    let image = target.acquire()?;
+    let acquire = acquire_start.elapsed();
+
+    // In order to draw, we have to build a *command buffer*. The command buffer object holds
+    // the list of commands that are going to be executed.
+    //
+    // Building a command buffer is an expensive operation (usually a few hundred
+    // microseconds), but it is known to be a hot path in the driver and is expected to be
+    // optimized.
+    //
+    // Note that we have to pass a queue family when we create the command buffer. The command
+    // buffer will only be executable on that given queue family.
+    let mut builder = AutoCommandBufferBuilder::primary(
+        queue.device().clone(),
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+
+    record(&mut builder, target, &image, resources);
+
+    // Finish building the command buffer by calling `build`.
+    let command_buffer = builder.build().unwrap();
+
+    // The fence of the frame is kept in its slot, the next frame starts after it, or after the
+    // last frame which was submitted if this one fails.
+    let submit_start = Instant::now();
+    let fence = target.present(frames.previous_future(), image, command_buffer);
+    frames.submitted(fence);
+
+    Some(FrameTimes {
+        cpu: start.elapsed(),
+        acquire,
+        submit: submit_start.elapsed(),
+    })
+}
+
+/// What is drawn on top of the scene of a frame, in the overlay subpass.
+#[derive(Default)]
+pub struct Overlay<'a> {
+    /// Draws what has been queued in it, e.g. a panel with the frame rate.
+    pub text: Option<&'a mut TextRenderer>,
+    /// Draws the last frame of the gui, over the text so it doesn't hide the gui.
+    pub gui: Option<(&'a mut GuiPainter, &'a Gui)>,
+}
+
+/// Records the render pass of a frame of `target` into `builder`, for the `record` function of
+/// `draw_frame`: the attachments of `image` are cleared to `clear_color`, `draw` draws the scene
+/// in the first subpass with the commands declared by `contents`, and `overlay` is drawn on top
+/// of it in the second subpass.
+pub fn record_frame<T, F>(
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    target: &T,
+    image: &T::Image,
+    clear_color: [f32; 4],
+    contents: SubpassContents,
+    draw: F,
+    overlay: Overlay,
+) where
+    T: PresentTarget,
+    F: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>, &DynamicState),
+{
+    builder
+        // Before we can draw, we have to *enter a render pass*. The contents of its first
+        // subpass are either commands in this command buffer, or only secondary command
+        // buffers.
+        //
+        // The clear values are the values to clear the attachments with, in the order of the
+        // attachments of the framebuffer. Only the attachments that use `load: Clear` are
+        // cleared.
+        .begin_render_pass(image.framebuffer(), contents, target.clear_values(clear_color))
+        .unwrap();
+
+    // We are now inside the first subpass of the render pass, the scene is drawn.
+    let dynamic_state = target.dynamic_state();
+    draw(builder, dynamic_state);
+
+    // The overlay is drawn on top of the scene in the second subpass, into the single sampled
+    // image.
+    builder.next_subpass(SubpassContents::Inline).unwrap();
+    if let Some(text) = overlay.text {
+        text.draw(builder, dynamic_state);
+    }
+    if let Some((painter, gui)) = overlay.gui {
+        painter.draw(builder, dynamic_state, gui);
+    }
+
+    // We leave the render pass by calling `end_render_pass`, in its last subpass.
+    builder.end_render_pass().unwrap();
+}
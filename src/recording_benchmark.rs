@@ -0,0 +1,172 @@
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBuffer};
+use vulkano::command_buffer::SubpassContents;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::sync::GpuFuture;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::time::{Duration, Instant};
+
+use cgmath::{Matrix4, Rad};
+use image::{Rgba, ImageBuffer};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::camera::Camera;
+use crate::cli::Options;
+use crate::cube_scene::CubeScene;
+use crate::depth;
+use crate::offscreen::OffscreenTarget;
+
+/// How the draw commands of a frame are recorded.
+enum Recording {
+    // directly into the primary command buffer, on the calling thread
+    Inline,
+    // into secondary command buffers on the threads of the pool
+    Secondary(ThreadPool),
+}
+
+impl Recording {
+    fn name(&self) -> String {
+        match self {
+            Recording::Inline => String::from("inline"),
+            Recording::Secondary(pool) => format!("secondary, {} thread(s)", pool.current_num_threads()),
+        }
+    }
+}
+
+/// Renders one frame of `scene` into `target` and returns how long recording the command buffer
+/// took. Blocks until the gpu is done, the image can be read from the target afterwards.
+fn render_frame(
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    target: &OffscreenTarget,
+    scene: &CubeScene,
+    recording: &Recording,
+    view_projection: Matrix4<f32>,
+) -> Duration {
+    let start = Instant::now();
+    let dynamic_state = target.dynamic_state();
+
+    let mut builder = AutoCommandBufferBuilder::primary(
+        device,
+        queue.family(),
+        CommandBufferUsage::OneTimeSubmit,
+    )
+    .unwrap();
+
+    // the contents of the subpass have to be declared when it is started: either commands in
+    // the primary command buffer, or only secondary command buffers
+    let contents = match recording {
+        Recording::Inline => SubpassContents::Inline,
+        Recording::Secondary(_) => SubpassContents::SecondaryCommandBuffers,
+    };
+    builder
+        .begin_render_pass(
+            target.framebuffer(),
+            contents,
+            target.clear_values([0.0, 0.0, 0.0, 1.0]),
+        )
+        .unwrap();
+
+    match recording {
+        Recording::Inline => scene.draw(&mut builder, &dynamic_state, view_projection),
+        Recording::Secondary(pool) => {
+            let secondaries = scene.record_secondary(pool, &queue, &target.subpass(), &dynamic_state, view_projection);
+            builder.execute_commands_from_vec(secondaries).unwrap();
+        }
+    }
+
+    builder.end_render_pass().unwrap();
+    target.record_readback(&mut builder);
+
+    let command = builder.build().unwrap();
+    let recorded = start.elapsed();
+
+    let finished = command.execute(queue).unwrap();
+    finished.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+    recorded
+}
+
+/// Renders `frames` frames of `scene` and prints the average time spent recording and per frame.
+/// Returns the last frame.
+fn run(
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    target: &OffscreenTarget,
+    scene: &CubeScene,
+    recording: &Recording,
+    frames: u32,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let [width, height] = target.dimensions();
+    let camera = Camera::default();
+    let view_projection = camera.view_projection(width as f32 / height as f32);
+
+    // the first frame creates the command pools of the threads, it isn't measured
+    render_frame(queue.clone(), device.clone(), target, scene, recording, view_projection);
+
+    let start = Instant::now();
+    let mut recording_time = Duration::default();
+    for frame in 0..frames {
+        // the same rotation in every mode, so their images can be compared
+        let rotation = Matrix4::from_angle_y(Rad(frame as f32 * 0.01));
+        recording_time += render_frame(
+            queue.clone(),
+            device.clone(),
+            target,
+            scene,
+            recording,
+            view_projection * rotation,
+        );
+    }
+    let frames = frames.max(1);
+
+    println!(
+        "{}: recording {:.2?}, frame {:.2?} on average",
+        recording.name(),
+        recording_time / frames,
+        start.elapsed() / frames,
+    );
+
+    target.read()
+}
+
+/// Compares recording a scene of `--instances` cubes, one draw call each, into the primary
+/// command buffer with recording it into secondary command buffers on one and on `--threads`
+/// threads, over `--frames` frames. Every mode has to render the same image.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let target = OffscreenTarget::start(device.clone(), options.size)
+        .samples(samples)
+        .depth_format(depth::choose_depth_format(device.physical_device()))
+        .build();
+
+    let scene = CubeScene::new(device.clone(), target.subpass(), options.instances);
+    println!("recording {} draw calls per frame", scene.len());
+
+    // without `--threads` rayon uses one thread per cpu core
+    let threads = options.threads.unwrap_or(0);
+    let recordings = [
+        Recording::Inline,
+        Recording::Secondary(ThreadPoolBuilder::new().num_threads(1).build().unwrap()),
+        Recording::Secondary(ThreadPoolBuilder::new().num_threads(threads).build().unwrap()),
+    ];
+
+    let images: Vec<_> = recordings
+        .iter()
+        .map(|recording| run(queue.clone(), device.clone(), &target, &scene, recording, options.frames))
+        .collect();
+
+    for (recording, image) in recordings.iter().zip(&images).skip(1) {
+        assert!(
+            image == &images[0],
+            "{} rendered a different image than inline recording",
+            recording.name(),
+        );
+    }
+
+    images[0]
+        .save(options.output.as_deref().unwrap_or("scene.png"))
+        .unwrap();
+}
@@ -0,0 +1,969 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, PrimaryCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::command_buffer::SubpassContents;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::format::{ClearValue, Format, FormatTy};
+use vulkano::image::AttachmentImage;
+use vulkano::image::ImageLayout;
+use vulkano::image::ImageUsage;
+use vulkano::image::SampleCount;
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::render_pass::{AttachmentDesc, LoadOp, RenderPassDesc, StoreOp, SubpassDesc};
+use vulkano::render_pass::Framebuffer;
+use vulkano::render_pass::FramebufferAbstract;
+use vulkano::render_pass::RenderPass;
+use vulkano::render_pass::Subpass;
+use vulkano::sync::GpuFuture;
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::fmt::Write;
+// atomically reference counted
+use std::sync::Arc;
+
+use image::{Rgba, ImageBuffer};
+
+use crate::offscreen;
+
+// the framebuffer of a graphics pass is built with one type per number of attachments
+const MAX_ATTACHMENTS: usize = 4;
+
+/// An image allocated by a `RenderGraph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageId(usize);
+
+/// A buffer used by the passes of a `RenderGraph`, which only orders the passes using it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferId(usize);
+
+/// A pass added to a `RenderGraph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PassId(usize);
+
+/// Anything a pass can read or write outside of its attachments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Image(ImageId),
+    Buffer(BufferId),
+}
+
+impl From<ImageId> for Resource {
+    fn from(image: ImageId) -> Resource {
+        Resource::Image(image)
+    }
+}
+
+impl From<BufferId> for Resource {
+    fn from(buffer: BufferId) -> Resource {
+        Resource::Buffer(buffer)
+    }
+}
+
+/// Format, size and number of samples of an image allocated by the graph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageDesc {
+    pub format: Format,
+    pub dimensions: [u32; 2],
+    pub samples: u32,
+}
+
+impl ImageDesc {
+    /// A single sampled image.
+    pub fn new(format: Format, dimensions: [u32; 2]) -> ImageDesc {
+        ImageDesc {
+            format,
+            dimensions,
+            samples: 1,
+        }
+    }
+
+    pub fn samples(mut self, samples: u32) -> ImageDesc {
+        self.samples = samples;
+        self
+    }
+}
+
+/// The ways a `RenderGraph` can be declared wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphError {
+    // the passes depend on each other in a circle, none of them can run first
+    Cycle(Vec<String>),
+    // a pass or an image with a declaration that can't be built
+    Invalid { name: String, message: String },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::Cycle(passes) => write!(f, "cycle between the passes {}", passes.join(", ")),
+            GraphError::Invalid { name, message } => write!(f, "{}: {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PassKind {
+    Graphics,
+    Compute,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Attachment {
+    image: ImageId,
+    // the previous content is loaded if there is no clear value
+    clear: Option<ClearValue>,
+}
+
+struct ImageNode {
+    name: String,
+    desc: ImageDesc,
+    output: bool,
+}
+
+struct PassNode {
+    name: String,
+    kind: PassKind,
+    colors: Vec<Attachment>,
+    resolves: Vec<ImageId>,
+    depth: Option<Attachment>,
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+}
+
+impl PassNode {
+    fn attachments(&self) -> impl Iterator<Item = ImageId> + '_ {
+        self.colors
+            .iter()
+            .map(|color| color.image)
+            .chain(self.resolves.iter().cloned())
+            .chain(self.depth.map(|depth| depth.image))
+    }
+
+    // whether the pass needs the content `resource` has before it runs
+    fn reads(&self, resource: Resource) -> bool {
+        let loaded = |attachment: &Attachment| attachment.clear.is_none() && Resource::Image(attachment.image) == resource;
+        self.reads.contains(&resource) || self.colors.iter().any(loaded) || self.depth.iter().any(loaded)
+    }
+
+    fn writes(&self, resource: Resource) -> bool {
+        self.writes.contains(&resource) || self.attachments().any(|image| Resource::Image(image) == resource)
+    }
+}
+
+/// The passes of a frame and the resources they read and write.
+///
+/// Samples declare images, buffers and passes up front and add the commands of the passes when
+/// recording a `Frame` of the compiled graph. The graph decides the order the passes run in from
+/// their resources, leaves out passes nothing depends on, allocates the images and builds a
+/// render pass and framebuffer for every graphics pass.
+///
+/// A pass reading a resource runs after the passes added before it which write the resource, or
+/// after all passes writing it if none was added before it. Passes writing the same resource run
+/// in the order they were added, and a pass overwriting what an earlier added pass reads runs
+/// after that pass. Passes writing a buffer or an output image are always run.
+/// The barriers between the passes are still added by `AutoCommandBufferBuilder`.
+#[derive(Default)]
+pub struct RenderGraph {
+    images: Vec<ImageNode>,
+    buffers: Vec<String>,
+    passes: Vec<PassNode>,
+}
+
+impl RenderGraph {
+    pub fn new() -> RenderGraph {
+        RenderGraph::default()
+    }
+
+    /// Declares an image, which is allocated when the graph is compiled.
+    pub fn image(&mut self, name: &str, desc: ImageDesc) -> ImageId {
+        self.images.push(ImageNode {
+            name: name.to_string(),
+            desc,
+            output: false,
+        });
+        ImageId(self.images.len() - 1)
+    }
+
+    /// Declares a buffer the caller owns, so the passes using it are ordered.
+    pub fn buffer(&mut self, name: &str) -> BufferId {
+        self.buffers.push(name.to_string());
+        BufferId(self.buffers.len() - 1)
+    }
+
+    /// Marks `image` as a result of the graph, which is copied to the cpu after every frame and
+    /// read with `CompiledGraph::read`.
+    pub fn output(&mut self, image: ImageId) {
+        self.images[image.0].output = true;
+    }
+
+    /// Starts adding a pass drawing into attachments.
+    pub fn graphics_pass(&mut self, name: &str) -> GraphicsPassBuilder<'_> {
+        GraphicsPassBuilder {
+            pass: self.pass_node(name, PassKind::Graphics),
+            graph: self,
+        }
+    }
+
+    /// Starts adding a pass recording dispatches, or any other commands outside of a render pass.
+    pub fn compute_pass(&mut self, name: &str) -> ComputePassBuilder<'_> {
+        ComputePassBuilder {
+            pass: self.pass_node(name, PassKind::Compute),
+            graph: self,
+        }
+    }
+
+    fn pass_node(&self, name: &str, kind: PassKind) -> PassNode {
+        PassNode {
+            name: name.to_string(),
+            kind,
+            colors: Vec::new(),
+            resolves: Vec::new(),
+            depth: None,
+            reads: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, pass: PassNode) -> PassId {
+        self.passes.push(pass);
+        PassId(self.passes.len() - 1)
+    }
+
+    pub fn pass_name(&self, pass: PassId) -> &str {
+        &self.passes[pass.0].name
+    }
+
+    /// The passes which are run, in the order they are run in.
+    pub fn schedule(&self) -> Result<Vec<PassId>, GraphError> {
+        for image in &self.images {
+            self.validate_image(image)?;
+        }
+        for pass in &self.passes {
+            self.validate_pass(pass)?;
+        }
+
+        // walking backwards from the passes with results visible outside of the graph
+        let mut used = vec![false; self.passes.len()];
+        let mut pending: Vec<usize> = (0..self.passes.len())
+            .filter(|&index| {
+                let pass = &self.passes[index];
+                pass.writes.iter().any(|resource| matches!(resource, Resource::Buffer(_)))
+                    || pass.attachments().chain(self.written_images(pass)).any(|image| self.images[image.0].output)
+            })
+            .collect();
+        while let Some(index) = pending.pop() {
+            if used[index] {
+                continue;
+            }
+            used[index] = true;
+            pending.extend((0..self.passes.len()).filter(|&other| self.needs(index, other)));
+        }
+
+        // the first pass added of those whose dependencies have run is run next
+        let run = used.iter().filter(|&&used| used).count();
+        let mut order = Vec::new();
+        let mut scheduled = vec![false; self.passes.len()];
+        while order.len() < run {
+            let next = (0..self.passes.len()).find(|&index| {
+                used[index]
+                    && !scheduled[index]
+                    && (0..self.passes.len())
+                        .all(|other| !used[other] || scheduled[other] || !self.depends_on(index, other))
+            });
+            match next {
+                Some(index) => {
+                    scheduled[index] = true;
+                    order.push(PassId(index));
+                }
+                None => {
+                    let remaining = (0..self.passes.len()).filter(|&index| used[index] && !scheduled[index]);
+                    return Err(GraphError::Cycle(remaining.map(|index| self.passes[index].name.clone()).collect()));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    // whether the pass at `index` uses what the pass at `other` writes
+    fn needs(&self, index: usize, other: usize) -> bool {
+        if index == other {
+            return false;
+        }
+        let (pass, other_pass) = (&self.passes[index], &self.passes[other]);
+        self.resources().into_iter().any(|resource| {
+            // passes writing the same resource keep their order, even if they read it as well
+            other_pass.writes(resource)
+                && if pass.writes(resource) {
+                    other < index
+                } else {
+                    pass.reads(resource) && self.reads_from(index, other, resource)
+                }
+        })
+    }
+
+    // whether the pass at `index` has to run after the pass at `other`, which also holds if it
+    // overwrites what `other` reads
+    fn depends_on(&self, index: usize, other: usize) -> bool {
+        let (pass, other_pass) = (&self.passes[index], &self.passes[other]);
+        self.needs(index, other)
+            || index != other
+                && self.resources().into_iter().any(|resource| {
+                    pass.writes(resource)
+                        && !other_pass.writes(resource)
+                        && other_pass.reads(resource)
+                        && !self.reads_from(other, index, resource)
+                })
+    }
+
+    // whether the pass at `reader` reads what the pass at `writer` writes into `resource`: passes
+    // read what the passes added before them write, or what any pass writes if none was
+    fn reads_from(&self, reader: usize, writer: usize, resource: Resource) -> bool {
+        writer < reader || !self.passes[..reader].iter().any(|pass| pass.writes(resource))
+    }
+
+    fn resources(&self) -> Vec<Resource> {
+        let images = (0..self.images.len()).map(|index| Resource::Image(ImageId(index)));
+        let buffers = (0..self.buffers.len()).map(|index| Resource::Buffer(BufferId(index)));
+        images.chain(buffers).collect()
+    }
+
+    fn written_images<'a>(&self, pass: &'a PassNode) -> impl Iterator<Item = ImageId> + 'a {
+        pass.writes.iter().filter_map(|resource| match resource {
+            Resource::Image(image) => Some(*image),
+            Resource::Buffer(_) => None,
+        })
+    }
+
+    fn validate_image(&self, image: &ImageNode) -> Result<(), GraphError> {
+        let invalid = |message: String| GraphError::Invalid {
+            name: image.name.clone(),
+            message,
+        };
+        let [width, height] = image.desc.dimensions;
+        if width == 0 || height == 0 {
+            return Err(invalid(format!("the image has no pixels, its size is {}x{}", width, height)));
+        }
+        if SampleCount::try_from(image.desc.samples).is_err() {
+            return Err(invalid(format!("{} samples per pixel are not supported", image.desc.samples)));
+        }
+        // outputs are read back as RGBA with 8 bits per channel
+        if image.output && (image.desc.samples != 1 || !offscreen::converts_to_rgba8(image.desc.format)) {
+            return Err(invalid("outputs have to be single sampled and in an RGBA or BGRA format with 8 bits per channel".to_string()));
+        }
+        Ok(())
+    }
+
+    fn validate_pass(&self, pass: &PassNode) -> Result<(), GraphError> {
+        let invalid = |message: String| GraphError::Invalid {
+            name: pass.name.clone(),
+            message,
+        };
+        let image = |image: ImageId| &self.images[image.0];
+
+        if pass.kind == PassKind::Graphics && pass.colors.is_empty() && pass.depth.is_none() {
+            return Err(invalid("a graphics pass needs a color or depth attachment".to_string()));
+        }
+        let attachments: Vec<ImageId> = pass.attachments().collect();
+        if attachments.len() > MAX_ATTACHMENTS {
+            return Err(invalid(format!(
+                "uses {} attachments, at most {} are supported",
+                attachments.len(),
+                MAX_ATTACHMENTS
+            )));
+        }
+        for (i, &attachment) in attachments.iter().enumerate() {
+            if attachments[..i].contains(&attachment) {
+                return Err(invalid(format!("`{}` is attached more than once", image(attachment).name)));
+            }
+            if pass.reads.contains(&Resource::Image(attachment)) || pass.writes.contains(&Resource::Image(attachment)) {
+                return Err(invalid(format!("`{}` is read or written while attached", image(attachment).name)));
+            }
+            if image(attachment).desc.dimensions != image(attachments[0]).desc.dimensions {
+                return Err(invalid(format!(
+                    "`{}` doesn't have the size of `{}`",
+                    image(attachment).name,
+                    image(attachments[0]).name
+                )));
+            }
+        }
+
+        let multisampled = pass.colors.iter().chain(pass.depth.iter()).map(|attachment| image(attachment.image));
+        for attachment in multisampled.clone() {
+            if attachment.desc.samples != multisampled.clone().next().unwrap().desc.samples {
+                return Err(invalid("the color and depth attachments have different numbers of samples".to_string()));
+            }
+        }
+        for color in &pass.colors {
+            if is_depth(image(color.image).desc.format) {
+                return Err(invalid(format!("the color attachment `{}` has a depth format", image(color.image).name)));
+            }
+        }
+        if let Some(depth) = pass.depth {
+            if !is_depth(image(depth.image).desc.format) {
+                return Err(invalid(format!("the depth attachment `{}` has no depth format", image(depth.image).name)));
+            }
+        }
+
+        if !pass.resolves.is_empty() {
+            if pass.resolves.len() != pass.colors.len() {
+                return Err(invalid(format!(
+                    "resolves into {} images, but has {} color attachments",
+                    pass.resolves.len(),
+                    pass.colors.len()
+                )));
+            }
+            for (color, &resolve) in pass.colors.iter().zip(&pass.resolves) {
+                let (color, resolve) = (image(color.image), image(resolve));
+                if color.desc.samples == 1 || resolve.desc.samples != 1 || color.desc.format != resolve.desc.format {
+                    return Err(invalid(format!(
+                        "`{}` can't be resolved into `{}`, only multisampled images into single sampled ones of the same format",
+                        color.name, resolve.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The graph in the Graphviz DOT language, with passes as boxes and resources as ellipses
+    /// (double for outputs). Passes are numbered in the order they run in, passes which aren't
+    /// run are dashed. An invalid graph is drawn with the error as its label.
+    pub fn to_dot(&self) -> String {
+        let schedule = self.schedule();
+        let order = schedule.as_ref().map(Vec::as_slice).unwrap_or(&[]);
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph render_graph {{").unwrap();
+        writeln!(dot, "    rankdir=LR;").unwrap();
+        if let Err(err) = &schedule {
+            writeln!(dot, "    label={};", quote(&format!("error: {}", err))).unwrap();
+            writeln!(dot, "    fontcolor=red;").unwrap();
+        }
+
+        for (index, image) in self.images.iter().enumerate() {
+            let desc = image.desc;
+            let mut label = format!("{}\n{:?} {}x{}", image.name, desc.format, desc.dimensions[0], desc.dimensions[1]);
+            if desc.samples > 1 {
+                write!(label, " {}x", desc.samples).unwrap();
+            }
+            let peripheries = if image.output { 2 } else { 1 };
+            writeln!(dot, "    i{} [shape=ellipse, peripheries={}, label={}];", index, peripheries, quote(&label)).unwrap();
+        }
+        for (index, buffer) in self.buffers.iter().enumerate() {
+            writeln!(dot, "    b{} [shape=cylinder, label={}];", index, quote(buffer)).unwrap();
+        }
+
+        for (index, pass) in self.passes.iter().enumerate() {
+            let kind = match pass.kind {
+                PassKind::Graphics => "graphics",
+                PassKind::Compute => "compute",
+            };
+            let (label, style) = match order.iter().position(|&pass| pass == PassId(index)) {
+                Some(position) => (format!("{}. {}\n{}", position + 1, pass.name, kind), "solid"),
+                None => (format!("{}\n{}", pass.name, kind), "dashed"),
+            };
+            writeln!(dot, "    p{} [shape=box, style={}, label={}];", index, style, quote(&label)).unwrap();
+
+            let node = |resource: &Resource| match resource {
+                Resource::Image(image) => format!("i{}", image.0),
+                Resource::Buffer(buffer) => format!("b{}", buffer.0),
+            };
+            let attachments = pass
+                .colors
+                .iter()
+                .map(|color| (color, "color"))
+                .chain(pass.depth.iter().map(|depth| (depth, "depth")));
+            for (attachment, usage) in attachments {
+                if attachment.clear.is_none() {
+                    writeln!(dot, "    i{} -> p{} [label=\"load\"];", attachment.image.0, index).unwrap();
+                }
+                writeln!(dot, "    p{} -> i{} [label=\"{}\"];", index, attachment.image.0, usage).unwrap();
+            }
+            for resolve in &pass.resolves {
+                writeln!(dot, "    p{} -> i{} [label=\"resolve\"];", index, resolve.0).unwrap();
+            }
+            for resource in &pass.reads {
+                writeln!(dot, "    {} -> p{} [label=\"read\"];", node(resource), index).unwrap();
+            }
+            for resource in &pass.writes {
+                writeln!(dot, "    p{} -> {} [label=\"write\"];", index, node(resource)).unwrap();
+            }
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+
+    /// Allocates the images and builds the render passes and framebuffers of the passes which
+    /// are run.
+    pub fn compile(self, device: Arc<Device>) -> Result<CompiledGraph, GraphError> {
+        let order = self.schedule()?;
+
+        // images only used by passes which aren't run are not allocated
+        let mut usages: Vec<Option<ImageUsage>> = vec![None; self.images.len()];
+        for &pass in &order {
+            let pass = &self.passes[pass.0];
+            for image in pass.attachments() {
+                usages[image.0].get_or_insert_with(ImageUsage::none);
+            }
+            for resource in &pass.reads {
+                if let Resource::Image(image) = resource {
+                    usages[image.0].get_or_insert_with(ImageUsage::none).sampled = true;
+                }
+            }
+            for image in self.written_images(pass) {
+                usages[image.0].get_or_insert_with(ImageUsage::none).storage = true;
+            }
+        }
+
+        let images: Vec<Option<Arc<AttachmentImage>>> = self
+            .images
+            .iter()
+            .zip(usages)
+            .map(|(image, usage)| match usage {
+                Some(usage) => {
+                    let usage = ImageUsage {
+                        transfer_source: image.output,
+                        ..usage
+                    };
+                    supports(&device, image, usage)?;
+                    // the color or depth attachment usage is added depending on the format
+                    AttachmentImage::multisampled_with_usage(
+                        device.clone(),
+                        image.desc.dimensions,
+                        SampleCount::try_from(image.desc.samples).unwrap(),
+                        image.desc.format,
+                        usage,
+                    )
+                    .map(Some)
+                    .map_err(|err| GraphError::Invalid {
+                        name: image.name.clone(),
+                        message: format!("failed to create the image: {}", err),
+                    })
+                }
+                None => Ok(None),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut render_passes: Vec<Option<GraphicsPass>> = self.passes.iter().map(|_| None).collect();
+        for (position, &pass) in order.iter().enumerate() {
+            let node = &self.passes[pass.0];
+            if node.kind != PassKind::Graphics {
+                continue;
+            }
+
+            // the content of an attachment is kept if a later pass or the readback needs it
+            let stored = |image: ImageId| {
+                self.images[image.0].output
+                    || order[position + 1..].iter().any(|&later| self.passes[later.0].reads(Resource::Image(image)))
+            };
+            let render_pass = Arc::new(
+                RenderPass::new(device.clone(), self.render_pass_desc(node, stored)).map_err(|err| GraphError::Invalid {
+                    name: node.name.clone(),
+                    message: err.to_string(),
+                })?,
+            );
+
+            let views: Vec<_> = node
+                .attachments()
+                .map(|image| ImageView::new(images[image.0].clone().unwrap()).unwrap())
+                .collect();
+            let clear_values = node
+                .colors
+                .iter()
+                .map(|color| color.clear.unwrap_or(ClearValue::None))
+                .chain(node.resolves.iter().map(|_| ClearValue::None))
+                .chain(node.depth.map(|depth| depth.clear.unwrap_or(ClearValue::None)))
+                .collect();
+
+            render_passes[pass.0] = Some(GraphicsPass {
+                framebuffer: framebuffer(render_pass.clone(), &views),
+                render_pass,
+                clear_values,
+                dimensions: self.images[node.attachments().next().unwrap().0].desc.dimensions,
+            });
+        }
+
+        let readbacks = self
+            .images
+            .iter()
+            .enumerate()
+            .filter(|(index, image)| image.output && images[*index].is_some())
+            .map(|(index, image)| {
+                let [width, height] = image.desc.dimensions;
+                let size = (width * height) as usize * image.desc.format.size().unwrap() as usize;
+                let buffer =
+                    CpuAccessibleBuffer::from_iter(device.clone(), BufferUsage::all(), false, (0..size).map(|_| 0u8))
+                        .expect("failed to create readback buffer");
+                (ImageId(index), buffer)
+            })
+            .collect();
+
+        Ok(CompiledGraph {
+            device,
+            graph: self,
+            order,
+            images,
+            render_passes,
+            readbacks,
+        })
+    }
+
+    /// A render pass with a single subpass using the attachments of `pass`, in the order colors,
+    /// resolves, depth.
+    fn render_pass_desc<F>(&self, pass: &PassNode, stored: F) -> RenderPassDesc
+    where
+        F: Fn(ImageId) -> bool,
+    {
+        let attachment_desc = |attachment: &Attachment, layout: ImageLayout| {
+            let desc = self.images[attachment.image.0].desc;
+            let load = if attachment.clear.is_some() { LoadOp::Clear } else { LoadOp::Load };
+            let store = if stored(attachment.image) { StoreOp::Store } else { StoreOp::DontCare };
+            AttachmentDesc {
+                format: desc.format,
+                samples: SampleCount::try_from(desc.samples).unwrap(),
+                load,
+                store,
+                stencil_load: load,
+                stencil_store: store,
+                // the layouts `AttachmentImage` keeps its images in
+                initial_layout: layout,
+                final_layout: layout,
+            }
+        };
+
+        let mut attachments: Vec<AttachmentDesc> = pass
+            .colors
+            .iter()
+            .map(|color| attachment_desc(color, ImageLayout::ColorAttachmentOptimal))
+            .collect();
+        // resolve attachments are overwritten entirely
+        attachments.extend(pass.resolves.iter().map(|&image| {
+            let resolve = Attachment { image, clear: None };
+            AttachmentDesc {
+                load: LoadOp::DontCare,
+                stencil_load: LoadOp::DontCare,
+                ..attachment_desc(&resolve, ImageLayout::ColorAttachmentOptimal)
+            }
+        }));
+        let color_count = pass.colors.len();
+        let depth_stencil = pass.depth.map(|depth| {
+            attachments.push(attachment_desc(&depth, ImageLayout::DepthStencilAttachmentOptimal));
+            (attachments.len() - 1, ImageLayout::DepthStencilAttachmentOptimal)
+        });
+
+        let subpass = SubpassDesc {
+            color_attachments: (0..color_count).map(|index| (index, ImageLayout::ColorAttachmentOptimal)).collect(),
+            depth_stencil,
+            input_attachments: vec![],
+            resolve_attachments: (0..pass.resolves.len())
+                .map(|index| (color_count + index, ImageLayout::ColorAttachmentOptimal))
+                .collect(),
+            preserve_attachments: vec![],
+        };
+
+        RenderPassDesc::new(attachments, vec![subpass], vec![])
+    }
+}
+
+/// Adds the attachments and resources of a graphics pass, started with
+/// `RenderGraph::graphics_pass`.
+pub struct GraphicsPassBuilder<'a> {
+    graph: &'a mut RenderGraph,
+    pass: PassNode,
+}
+
+impl<'a> GraphicsPassBuilder<'a> {
+    /// Draws into `image`, on top of what earlier passes drew into it.
+    pub fn color(mut self, image: ImageId) -> Self {
+        self.pass.colors.push(Attachment { image, clear: None });
+        self
+    }
+
+    /// Draws into `image`, after clearing it to `color`.
+    pub fn clear_color(mut self, image: ImageId, color: [f32; 4]) -> Self {
+        self.pass.colors.push(Attachment {
+            image,
+            clear: Some(color.into()),
+        });
+        self
+    }
+
+    /// Tests against and writes to the depth in `image`, as left by earlier passes.
+    pub fn depth(mut self, image: ImageId) -> Self {
+        self.pass.depth = Some(Attachment { image, clear: None });
+        self
+    }
+
+    /// Tests against and writes to the depth in `image`, after clearing it to `depth`.
+    pub fn clear_depth(mut self, image: ImageId, depth: f32) -> Self {
+        self.pass.depth = Some(Attachment {
+            image,
+            clear: Some(depth.into()),
+        });
+        self
+    }
+
+    /// Resolves the multisampled color attachment added in the same position into `image`.
+    pub fn resolve(mut self, image: ImageId) -> Self {
+        self.pass.resolves.push(image);
+        self
+    }
+
+    /// Reads `resource` in a shader, images are sampled.
+    pub fn read<R: Into<Resource>>(mut self, resource: R) -> Self {
+        self.pass.reads.push(resource.into());
+        self
+    }
+
+    /// Writes `resource` in a shader, images are written as storage images.
+    pub fn write<R: Into<Resource>>(mut self, resource: R) -> Self {
+        self.pass.writes.push(resource.into());
+        self
+    }
+
+    pub fn add(self) -> PassId {
+        self.graph.add(self.pass)
+    }
+}
+
+/// Adds the resources of a compute pass, started with `RenderGraph::compute_pass`.
+pub struct ComputePassBuilder<'a> {
+    graph: &'a mut RenderGraph,
+    pass: PassNode,
+}
+
+impl<'a> ComputePassBuilder<'a> {
+    /// Reads `resource` in a shader, images are sampled.
+    pub fn read<R: Into<Resource>>(mut self, resource: R) -> Self {
+        self.pass.reads.push(resource.into());
+        self
+    }
+
+    /// Writes `resource` in a shader or with transfer commands, images are written as storage
+    /// images.
+    pub fn write<R: Into<Resource>>(mut self, resource: R) -> Self {
+        self.pass.writes.push(resource.into());
+        self
+    }
+
+    pub fn add(self) -> PassId {
+        self.graph.add(self.pass)
+    }
+}
+
+struct GraphicsPass {
+    render_pass: Arc<RenderPass>,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    clear_values: Vec<ClearValue>,
+    dimensions: [u32; 2],
+}
+
+/// A `RenderGraph` with its images, render passes and framebuffers, to build the pipelines of
+/// the passes for and record frames with.
+pub struct CompiledGraph {
+    device: Arc<Device>,
+    graph: RenderGraph,
+    order: Vec<PassId>,
+    // `None` for images no pass which is run uses
+    images: Vec<Option<Arc<AttachmentImage>>>,
+    render_passes: Vec<Option<GraphicsPass>>,
+    // host visible copies of the outputs
+    readbacks: Vec<(ImageId, Arc<CpuAccessibleBuffer<[u8]>>)>,
+}
+
+impl CompiledGraph {
+    /// Panics if no pass which is run uses `image`.
+    pub fn image(&self, image: ImageId) -> Arc<AttachmentImage> {
+        self.images[image.0]
+            .clone()
+            .unwrap_or_else(|| panic!("{} isn't used by any pass", self.graph.images[image.0].name))
+    }
+
+    fn graphics_pass(&self, pass: PassId) -> &GraphicsPass {
+        self.render_passes[pass.0]
+            .as_ref()
+            .unwrap_or_else(|| panic!("{} isn't a graphics pass which is run", self.graph.pass_name(pass)))
+    }
+
+    /// The subpass the pipelines of the graphics pass `pass` have to be built for.
+    pub fn subpass(&self, pass: PassId) -> Subpass {
+        Subpass::from(self.graphics_pass(pass).render_pass.clone(), 0).unwrap()
+    }
+
+    /// Dynamic state with a viewport covering the attachments of the graphics pass `pass`.
+    pub fn dynamic_state(&self, pass: PassId) -> DynamicState {
+        let [width, height] = self.graphics_pass(pass).dimensions;
+        DynamicState {
+            viewports: Some(vec![Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [width as f32, height as f32],
+                depth_range: 0.0..1.0,
+            }]),
+            ..DynamicState::none()
+        }
+    }
+
+    /// Starts recording a frame, the commands of every pass which is run have to be added to it.
+    pub fn frame<'a>(&'a self) -> Frame<'a> {
+        Frame {
+            graph: self,
+            passes: self.graph.passes.iter().map(|_| None).collect(),
+        }
+    }
+
+    /// The output `image` as an RGBA image. The frame copying it needs to have finished
+    /// executing.
+    pub fn read(&self, image: ImageId) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let (_, readback) = self
+            .readbacks
+            .iter()
+            .find(|(output, _)| *output == image)
+            .unwrap_or_else(|| panic!("{} isn't an output", self.graph.images[image.0].name));
+        let desc = self.graph.images[image.0].desc;
+        let content = readback.read().unwrap();
+        let [width, height] = desc.dimensions;
+        ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, offscreen::to_rgba8(desc.format, &content)).unwrap()
+    }
+}
+
+type RecordPass<'a> = Box<dyn FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) + 'a>;
+
+/// The commands of the passes of a `CompiledGraph` for one frame, started with
+/// `CompiledGraph::frame`.
+pub struct Frame<'a> {
+    graph: &'a CompiledGraph,
+    passes: Vec<Option<RecordPass<'a>>>,
+}
+
+impl<'a> Frame<'a> {
+    /// Sets the commands of `pass`, which are recorded inside of its render pass for graphics
+    /// passes. The commands of passes which aren't run are dropped.
+    pub fn pass<F>(&mut self, pass: PassId, record: F)
+    where
+        F: FnOnce(&mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) + 'a,
+    {
+        self.passes[pass.0] = Some(Box::new(record));
+    }
+
+    /// Records the passes in order and the copies of the outputs into `builder`.
+    pub fn record(mut self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) {
+        for &pass in &self.graph.order {
+            let record = self.passes[pass.0]
+                .take()
+                .unwrap_or_else(|| panic!("no commands for the pass {}", self.graph.graph.pass_name(pass)));
+            match &self.graph.render_passes[pass.0] {
+                Some(graphics_pass) => {
+                    builder
+                        .begin_render_pass(
+                            graphics_pass.framebuffer.clone(),
+                            SubpassContents::Inline,
+                            graphics_pass.clear_values.iter().cloned(),
+                        )
+                        .unwrap();
+                    record(builder);
+                    builder.end_render_pass().unwrap();
+                }
+                None => record(builder),
+            }
+        }
+
+        for (image, readback) in &self.graph.readbacks {
+            builder
+                .copy_image_to_buffer(self.graph.image(*image), readback.clone())
+                .unwrap();
+        }
+    }
+
+    /// Records the frame into a command buffer of its own and blocks until the gpu is done
+    /// with it.
+    pub fn submit(self, queue: Arc<Queue>) {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.graph.device.clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        self.record(&mut builder);
+
+        let command = builder.build().unwrap();
+        let finished = command.execute(queue).unwrap();
+        finished.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+    }
+}
+
+// the framebuffer type has the attachments as type parameters, so there is one case for every
+// number of attachments
+fn framebuffer(
+    render_pass: Arc<RenderPass>,
+    views: &[Arc<ImageView<Arc<AttachmentImage>>>],
+) -> Arc<dyn FramebufferAbstract + Send + Sync> {
+    let start = Framebuffer::start(render_pass);
+    match views {
+        [a] => Arc::new(start.add(a.clone()).unwrap().build().unwrap()),
+        [a, b] => Arc::new(start.add(a.clone()).unwrap().add(b.clone()).unwrap().build().unwrap()),
+        [a, b, c] => Arc::new(
+            start
+                .add(a.clone()).unwrap()
+                .add(b.clone()).unwrap()
+                .add(c.clone()).unwrap()
+                .build().unwrap(),
+        ),
+        [a, b, c, d] => Arc::new(
+            start
+                .add(a.clone()).unwrap()
+                .add(b.clone()).unwrap()
+                .add(c.clone()).unwrap()
+                .add(d.clone()).unwrap()
+                .build().unwrap(),
+        ),
+        _ => unreachable!("between 1 and {} attachments are checked by validate_pass", MAX_ATTACHMENTS),
+    }
+}
+
+fn is_depth(format: Format) -> bool {
+    matches!(format.ty(), FormatTy::Depth | FormatTy::DepthStencil | FormatTy::Stencil)
+}
+
+// Checks that the device can use `image` the way the passes do: as an attachment (every image
+// is one), sampled by the passes reading it and as a storage image by the ones writing it.
+fn supports(device: &Device, image: &ImageNode, usage: ImageUsage) -> Result<(), GraphError> {
+    let format = image.desc.format;
+    let features = format.properties(device.physical_device()).optimal_tiling_features;
+    let attachment = if is_depth(format) {
+        features.depth_stencil_attachment
+    } else {
+        features.color_attachment
+    };
+    let uses = [
+        (true, attachment, "attached"),
+        (usage.sampled, features.sampled_image, "read"),
+        (usage.storage, features.storage_image, "written"),
+        (usage.transfer_source, features.transfer_src, "read back"),
+    ];
+
+    let invalid = |message: String| GraphError::Invalid {
+        name: image.name.clone(),
+        message,
+    };
+    if let Some((_, _, use_)) = uses.iter().find(|(used, supported, _)| *used && !supported) {
+        return Err(invalid(format!("{:?} images can't be {} on this device", format, use_)));
+    }
+    if usage.storage && image.desc.samples > 1 && !device.enabled_features().shader_storage_image_multisample {
+        return Err(invalid(
+            "multisampled images can only be written with the shader_storage_image_multisample feature".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// a DOT string literal
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
@@ -0,0 +1,311 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::{ComputePipeline, ComputePipelineAbstract};
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::sampler::Sampler;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use cgmath::{Matrix4, SquareMatrix};
+use image::{Rgba, ImageBuffer};
+
+use crate::cli::Options;
+use crate::depth;
+use crate::pipeline_cache;
+use crate::render_graph::{ImageDesc, ImageId, PassId, RenderGraph};
+use crate::scene_file::SceneFile;
+use crate::scene_sample::SceneRenderer;
+
+mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: "
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+layout(set = 0, binding = 1) buffer Exposure {
+    // the luminance of all pixels added up, in 1/255
+    uint luminance;
+} exposure;
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = textureSize(scene, 0);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    vec3 color = texelFetch(scene, pixel, 0).rgb;
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    atomicAdd(exposure.luminance, uint(luminance * 255.0 + 0.5));
+}"
+    }
+}
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec2 position;
+
+layout(location=0) out vec2 v_uv;
+
+void main() {
+    v_uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D scene;
+
+layout(set = 0, binding = 1) readonly buffer Exposure {
+    uint luminance;
+} exposure;
+
+layout(push_constant) uniform PushConstants {
+    uint pixel_count;
+} push_constants;
+
+void main() {
+    // scales the colors, so the average luminance is about a half, within limits
+    float average = float(exposure.luminance) / (255.0 * float(push_constants.pixel_count));
+    float scale = clamp(0.5 / max(average, 0.0001), 0.25, 4.0);
+    vec3 color = texture(scene, v_uv).rgb * scale;
+    f_color = vec4(color, 1.0);
+}
+"
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct QuadVertex {
+    position: [f32; 2],
+}
+
+vulkano::impl_vertex!(QuadVertex, position);
+
+/// The passes of the sample: the scene is drawn (and resolved, if multisampled), its average
+/// luminance is added up in a compute shader and the scene is drawn again into the output with
+/// its exposure adjusted.
+pub struct SampleGraph {
+    pub graph: RenderGraph,
+    pub draw: PassId,
+    pub luminance: PassId,
+    pub tonemap: PassId,
+    pub scene: ImageId,
+    pub output: ImageId,
+}
+
+/// Declares the passes of the sample, rendering images of `dimensions`. Without a depth format
+/// the scene is drawn without the depth test.
+pub fn declare(dimensions: [u32; 2], samples: u32, depth_format: Option<Format>) -> SampleGraph {
+    let mut graph = RenderGraph::new();
+    let format = Format::R8G8B8A8Unorm;
+
+    let scene = graph.image("scene", ImageDesc::new(format, dimensions));
+    let exposure = graph.buffer("exposure");
+    let output = graph.image("output", ImageDesc::new(format, dimensions));
+    graph.output(output);
+
+    // the passes are added starting with the last one, the graph runs them in the order their
+    // resources require
+    let tonemap = graph
+        .graphics_pass("tonemap")
+        .clear_color(output, [0.0, 0.0, 0.0, 1.0])
+        .read(scene)
+        .read(exposure)
+        .add();
+    let luminance = graph.compute_pass("luminance").read(scene).write(exposure).add();
+
+    let multisampled =
+        (samples > 1).then(|| graph.image("multisampled scene", ImageDesc::new(format, dimensions).samples(samples)));
+    let depth_image =
+        depth_format.map(|depth_format| graph.image("depth", ImageDesc::new(depth_format, dimensions).samples(samples)));
+    let draw = match multisampled {
+        Some(multisampled) => graph.graphics_pass("draw").clear_color(multisampled, [0.0; 4]).resolve(scene),
+        None => graph.graphics_pass("draw").clear_color(scene, [0.0; 4]),
+    };
+    let draw = match depth_image {
+        Some(depth_image) => draw.clear_depth(depth_image, 1.0),
+        None => draw,
+    };
+    let draw = draw.add();
+
+    SampleGraph {
+        graph,
+        draw,
+        luminance,
+        tonemap,
+        scene,
+        output,
+    }
+}
+
+/// Renders `scene` through the graph of `declare` and returns the output, with its exposure
+/// adjusted.
+pub fn render(
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    scene: &SceneFile,
+    samples: u32,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let depth_format = depth::choose_depth_format(device.physical_device());
+    let declared = declare(scene.resolution, samples, Some(depth_format));
+    let graph = declared.graph.compile(device.clone()).unwrap_or_else(|err| panic!("invalid render graph: {}", err));
+
+    // the draw pass
+    let scene_renderer = SceneRenderer::new(device.clone(), graph.subpass(declared.draw), scene);
+    let [width, height] = scene.resolution;
+    let view_projection = match &scene.camera {
+        Some(camera) => camera.view_projection(width as f32 / height as f32),
+        None => Matrix4::identity(),
+    };
+
+    let sampler = Sampler::simple_repeat_linear_no_mipmap(device.clone());
+    let scene_view = ImageView::new(graph.image(declared.scene)).unwrap();
+    let exposure = CpuAccessibleBuffer::from_data(
+        device.clone(),
+        BufferUsage {
+            storage_buffer: true,
+            transfer_destination: true,
+            ..BufferUsage::none()
+        },
+        false,
+        0u32,
+    )
+    .unwrap();
+
+    // the luminance pass
+    let luminance_shader = cs::Shader::load(device.clone()).expect("failed to create compute shader");
+    let luminance_pipeline = Arc::new(
+        ComputePipeline::new(
+            device.clone(),
+            &luminance_shader.main_entry_point(),
+            &(),
+            Some(pipeline_cache::get(&device)),
+        )
+        .expect("failed to create compute pipeline"),
+    );
+    let luminance_layout = luminance_pipeline.layout().descriptor_set_layouts().first().unwrap();
+    let luminance_set = Arc::new(
+        PersistentDescriptorSet::start(luminance_layout.clone())
+            .add_sampled_image(scene_view.clone(), sampler.clone())
+            .unwrap()
+            .add_buffer(exposure.clone())
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+
+    // the tonemap pass
+    let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+    let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+    let tonemap_pipeline = Arc::new(
+        GraphicsPipeline::start()
+            .vertex_input_single_buffer::<QuadVertex>()
+            .vertex_shader(vertex_shader.main_entry_point(), ())
+            .triangle_strip()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fragment_shader.main_entry_point(), ())
+            .render_pass(graph.subpass(declared.tonemap))
+            .build_with_cache(pipeline_cache::get(&device))
+            .build(device.clone())
+            .unwrap(),
+    );
+    let tonemap_layout = tonemap_pipeline.layout().descriptor_set_layouts().first().unwrap();
+    let tonemap_set = Arc::new(
+        PersistentDescriptorSet::start(tonemap_layout.clone())
+            .add_sampled_image(scene_view, sampler)
+            .unwrap()
+            .add_buffer(exposure.clone())
+            .unwrap()
+            .build()
+            .unwrap(),
+    );
+    let quad = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage::vertex_buffer(),
+        false,
+        [[-1.0, -1.0], [-1.0, 1.0], [1.0, -1.0], [1.0, 1.0]]
+            .iter()
+            .map(|&position| QuadVertex { position }),
+    )
+    .unwrap();
+
+    let draw_state = graph.dynamic_state(declared.draw);
+    let tonemap_state = graph.dynamic_state(declared.tonemap);
+
+    let mut frame = graph.frame();
+    frame.pass(declared.draw, |builder| {
+        scene_renderer.draw(builder, &draw_state, view_projection);
+    });
+    frame.pass(declared.luminance, |builder| {
+        // the sum is reset on the gpu, so the graph can be run again
+        builder
+            .fill_buffer(exposure.clone(), 0)
+            .unwrap()
+            .dispatch([width.div_ceil(8), height.div_ceil(8), 1], luminance_pipeline.clone(), luminance_set, ())
+            .unwrap();
+    });
+    frame.pass(declared.tonemap, |builder| {
+        builder
+            .draw(
+                tonemap_pipeline.clone(),
+                &tonemap_state,
+                quad,
+                tonemap_set,
+                fs::ty::PushConstants {
+                    pixel_count: width * height,
+                },
+            )
+            .unwrap();
+    });
+    frame.submit(queue);
+
+    graph.read(declared.output)
+}
+
+/// `render-graph [SCENE]` renders a scene file like `render-scene`, through a render graph
+/// which adjusts the exposure of the image, and saves it to `render-graph.png`. With
+/// `--dot PATH` the graph is written to the file in the Graphviz DOT language, e.g. for
+/// `dot -Tsvg`.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let path = options.args.first().map(String::as_str);
+    let scene = SceneFile::load_or_default(path)
+        .unwrap_or_else(|err| panic!("{}: {}", path.unwrap_or("default scene"), err));
+
+    if let Some(dot) = &options.dot {
+        let depth_format = depth::choose_depth_format(device.physical_device());
+        let declared = declare(scene.resolution, samples, Some(depth_format));
+        std::fs::write(dot, declared.graph.to_dot()).unwrap();
+        println!("Wrote the render graph to {}", dot);
+    }
+
+    let image_buffer = render(queue, device, &scene, samples);
+
+    let output = options.output.as_deref().unwrap_or("render-graph.png");
+    image_buffer.save(output).unwrap();
+    println!("Rendered {} shapes to {}", scene.shapes.len(), output);
+}
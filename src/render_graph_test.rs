@@ -0,0 +1,157 @@
+use vulkano::format::Format;
+
+use crate::render_graph::{GraphError, ImageDesc, RenderGraph};
+use crate::render_graph_sample;
+
+// adds an invalid part to an empty graph
+type Declare = Box<dyn Fn(&mut RenderGraph)>;
+
+// the names of the passes which are run, in order
+fn schedule(graph: &RenderGraph) -> Result<Vec<&str>, GraphError> {
+    let order = graph.schedule()?;
+    Ok(order.into_iter().map(|pass| graph.pass_name(pass)).collect())
+}
+
+/// Checks the order and the left out passes of render graphs and the errors of invalid ones,
+/// which are decided before anything is allocated. Like the input test, this doesn't need the
+/// gpu.
+pub fn execute() {
+    println!("performing render graph test");
+
+    // the passes of the sample are added in reverse, they run in the order of their resources
+    for &samples in &[1, 4] {
+        let declared = render_graph_sample::declare([64, 64], samples, Some(Format::D16Unorm));
+        assert_eq!(schedule(&declared.graph).unwrap(), ["draw", "luminance", "tonemap"]);
+    }
+
+    let color = ImageDesc::new(Format::R8G8B8A8Unorm, [64, 64]);
+    let depth = ImageDesc::new(Format::D16Unorm, [64, 64]);
+
+    // passes drawing into the same image keep their order, passes reading it come after both;
+    // the debug view is left out, nothing reads it
+    let mut graph = RenderGraph::new();
+    let scene = graph.image("scene", color);
+    let debug = graph.image("debug", color);
+    let output = graph.image("output", color);
+    let scene_depth = graph.image("depth", depth);
+    graph.output(output);
+    graph.graphics_pass("post").clear_color(output, [0.0; 4]).read(scene).add();
+    graph.graphics_pass("debug view").clear_color(debug, [0.0; 4]).read(scene).add();
+    graph.graphics_pass("opaque").clear_color(scene, [0.0; 4]).clear_depth(scene_depth, 1.0).add();
+    graph.graphics_pass("transparent").color(scene).depth(scene_depth).add();
+    assert_eq!(schedule(&graph).unwrap(), ["opaque", "transparent", "post"]);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph render_graph {"), "{}", dot);
+    assert!(dot.contains("p2 [shape=box, style=solid, label=\"1. opaque\\ngraphics\"];"), "{}", dot);
+    assert!(dot.contains("p1 [shape=box, style=dashed, label=\"debug view\\ngraphics\"];"), "{}", dot);
+    assert!(dot.contains("i2 [shape=ellipse, peripheries=2, label=\"output\\nR8G8B8A8Unorm 64x64\"];"), "{}", dot);
+    assert!(dot.contains("i0 -> p3 [label=\"load\"];") && dot.contains("p3 -> i0 [label=\"color\"];"), "{}", dot);
+    assert!(dot.contains("i0 -> p0 [label=\"read\"];"), "{}", dot);
+
+    // compute passes writing buffers are run, even if no pass reads them
+    let mut graph = RenderGraph::new();
+    let counter = graph.buffer("counter");
+    graph.compute_pass("count").write(counter).add();
+    assert_eq!(schedule(&graph).unwrap(), ["count"]);
+
+    // a pass reading an image drawn before it runs before a later added pass overwrites the image
+    let mut graph = RenderGraph::new();
+    let scene = graph.image("scene", color);
+    let output = graph.image("output", color);
+    graph.output(output);
+    graph.graphics_pass("draw").clear_color(scene, [0.0; 4]).add();
+    graph.graphics_pass("copy").clear_color(output, [0.0; 4]).read(scene).add();
+    graph.graphics_pass("overwrite").clear_color(scene, [1.0; 4]).add();
+    graph.graphics_pass("blend").color(output).read(scene).add();
+    assert_eq!(schedule(&graph).unwrap(), ["draw", "copy", "overwrite", "blend"]);
+
+    // two passes needing each other's results
+    let mut graph = RenderGraph::new();
+    let a = graph.image("a", color);
+    let b = graph.image("b", color);
+    graph.output(b);
+    graph.compute_pass("first").read(b).write(a).add();
+    graph.compute_pass("second").read(a).write(b).add();
+    let cycle = GraphError::Cycle(vec!["first".to_string(), "second".to_string()]);
+    assert_eq!(schedule(&graph), Err(cycle));
+    assert!(graph.to_dot().contains("label=\"error: cycle between the passes first, second\";"));
+
+    let errors: Vec<(Declare, &str)> = vec![
+        (
+            Box::new(move |graph| {
+                graph.graphics_pass("empty").add();
+            }),
+            "empty: a graphics pass needs a color or depth attachment",
+        ),
+        (
+            Box::new(move |graph| {
+                let image = graph.image("depth", depth);
+                graph.graphics_pass("draw").clear_color(image, [0.0; 4]).add();
+            }),
+            "draw: the color attachment `depth` has a depth format",
+        ),
+        (
+            Box::new(move |graph| {
+                let image = graph.image("scene", color);
+                let small = graph.image("small depth", ImageDesc::new(Format::D16Unorm, [32, 32]));
+                graph.graphics_pass("draw").clear_color(image, [0.0; 4]).clear_depth(small, 1.0).add();
+            }),
+            "draw: `small depth` doesn't have the size of `scene`",
+        ),
+        (
+            Box::new(move |graph| {
+                let image = graph.image("scene", color);
+                graph.graphics_pass("feedback").color(image).write(image).add();
+            }),
+            "feedback: `scene` is read or written while attached",
+        ),
+        (
+            Box::new(move |graph| {
+                let image = graph.image("scene", color);
+                let resolved = graph.image("resolved", color);
+                graph.graphics_pass("draw").clear_color(image, [0.0; 4]).resolve(resolved).add();
+            }),
+            "draw: `scene` can't be resolved into `resolved`, only multisampled images into single sampled ones of the same format",
+        ),
+        (
+            Box::new(move |graph| {
+                let image = graph.image("scene", color.samples(4));
+                let image_depth = graph.image("depth", depth);
+                graph.graphics_pass("draw").clear_color(image, [0.0; 4]).clear_depth(image_depth, 1.0).add();
+            }),
+            "draw: the color and depth attachments have different numbers of samples",
+        ),
+        (
+            Box::new(move |graph| {
+                let image = graph.image("scene", color.samples(3));
+                graph.output(image);
+            }),
+            "scene: 3 samples per pixel are not supported",
+        ),
+        (
+            Box::new(move |graph| {
+                let image = graph.image("scene", color.samples(4));
+                graph.output(image);
+            }),
+            "scene: outputs have to be single sampled and in an RGBA or BGRA format with 8 bits per channel",
+        ),
+        (
+            Box::new(move |graph| {
+                let image = graph.image("hdr", ImageDesc::new(Format::R16G16B16A16Sfloat, [64, 64]));
+                graph.output(image);
+            }),
+            "hdr: outputs have to be single sampled and in an RGBA or BGRA format with 8 bits per channel",
+        ),
+    ];
+    for (declare, expected) in errors.iter() {
+        let mut graph = RenderGraph::new();
+        declare(&mut graph);
+        match graph.schedule() {
+            Ok(_) => panic!("no error, expected {}", expected),
+            Err(err) => assert_eq!(err.to_string(), *expected),
+        }
+    }
+
+    println!("render graph test successful!");
+}
@@ -0,0 +1,182 @@
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::render_pass::Subpass;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use cgmath::{Deg, Point3};
+use image::{Rgba, ImageBuffer};
+
+use crate::camera::Camera;
+use crate::cli::Options;
+use crate::depth;
+use crate::index::IndexBuffer;
+use crate::mesh::{Mesh, MeshVertex};
+use crate::offscreen::OffscreenTarget;
+use crate::pipeline_cache;
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec3 position;
+layout(location=1) in vec3 normal;
+layout(location=2) in vec2 uv;
+
+layout(location=0) out vec3 v_normal;
+
+layout(push_constant) uniform PushConstants {
+    mat4 mvp;
+} push_constants;
+
+void main() {
+    // the mesh is drawn without a model transform, so the normal is already in world space
+    v_normal = normal;
+    gl_Position = push_constants.mvp * vec4(position, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec3 v_normal;
+layout(location=0) out vec4 f_color;
+
+const vec3 ALBEDO = vec3(0.8, 0.8, 0.8);
+
+void main() {
+    // a single directional light and some ambient light, so the shape of the mesh is visible
+    vec3 light_direction = normalize(vec3(0.4, 1.0, 0.6));
+    float diffuse = max(dot(normalize(v_normal), light_direction), 0.0);
+    f_color = vec4(ALBEDO * (0.2 + 0.8 * diffuse), 1.0);
+}
+"
+    }
+}
+
+/// Pipeline, vertex and index buffer to draw a loaded mesh into a subpass.
+pub struct MeshRenderer {
+    pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[MeshVertex]>>,
+    index_buffer: IndexBuffer,
+}
+
+impl MeshRenderer {
+    pub fn new(device: Arc<Device>, subpass: Subpass, mesh: &Mesh) -> MeshRenderer {
+        // load shader for device
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        let builder = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<MeshVertex>()
+            .vertex_shader(vertex_shader.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fragment_shader.main_entry_point(), ());
+
+        let builder = if subpass.has_depth() {
+            builder.depth_stencil_simple_depth()
+        } else {
+            builder
+        };
+
+        let pipeline = Arc::new(
+            builder
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        MeshRenderer {
+            pipeline,
+            vertex_buffer: mesh.vertex_buffer(device.clone()),
+            index_buffer: mesh.index_buffer(device),
+        }
+    }
+
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+        camera: &Camera,
+        aspect: f32,
+    ) {
+        let push_constants = vs::ty::PushConstants {
+            mvp: camera.view_projection(aspect).into(),
+        };
+
+        self.index_buffer.draw(
+            builder,
+            self.pipeline.clone(),
+            dynamic_state,
+            self.vertex_buffer.clone(),
+            (),
+            push_constants,
+        );
+    }
+}
+
+/// Renders `mesh` headlessly into `target`, as seen by `camera`.
+pub fn render(
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    target: &OffscreenTarget,
+    mesh: &Mesh,
+    camera: &Camera,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let renderer = MeshRenderer::new(device, target.subpass(), mesh);
+    let dynamic_state = target.dynamic_state();
+    let [width, height] = target.dimensions();
+
+    target.render(queue, [0.1, 0.1, 0.1, 1.0], |builder| {
+        renderer.draw(builder, &dynamic_state, camera, width as f32 / height as f32);
+    })
+}
+
+/// `render-mesh MODEL` loads an OBJ or glTF file and saves an image of it to `mesh.png`.
+///
+/// The camera looks at the whole model along -z, unless overridden by `--eye`, `--target` and
+/// `--fov`.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let path = options.args.first().expect("render-mesh needs the path of a model");
+    let mesh = Mesh::load(path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+
+    let mut camera = Camera::default();
+    if let Some(fov) = options.fov {
+        camera.fovy = Deg(fov);
+    }
+    let (min, max) = mesh.bounds();
+    camera.frame_bounds(min, max);
+    if let Some(target) = options.target {
+        camera.target = Point3::from(target);
+    }
+    if let Some(eye) = options.eye {
+        camera.eye = Point3::from(eye);
+        camera.fit_depth_range(min, max);
+    }
+
+    let target = OffscreenTarget::start(device.clone(), options.size)
+        .samples(samples)
+        .depth_format(depth::choose_depth_format(device.physical_device()))
+        .build();
+
+    let image_buffer = render(queue, device, &target, &mesh, &camera);
+
+    let output = options.output.as_deref().unwrap_or("mesh.png");
+    image_buffer.save(output).unwrap();
+    println!("Rendered {} triangles to {}", mesh.indices.len() / 3, output);
+}
@@ -0,0 +1,648 @@
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, DynamicState, PrimaryAutoCommandBuffer};
+use vulkano::device::{Device, DeviceOwned, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{AttachmentImage, ImageUsage, SampleCount, SwapchainImage};
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::viewport::Viewport;
+use vulkano::render_pass::{Framebuffer, FramebufferAbstract, RenderPass, Subpass};
+use vulkano::swapchain::{AcquireError, FullscreenExclusive, PresentMode, Surface, SurfaceTransform, Swapchain};
+use vulkano::swapchain::{SwapchainAcquireFuture, SwapchainCreationError};
+use vulkano::sync::{FlushError, GpuFuture};
+
+use winit::window::{Fullscreen, Window};
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::convert::TryFrom;
+use std::mem;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+use crate::frames::FrameFence;
+use crate::present::{PresentImage, PresentTarget};
+use crate::screenshot::Screenshot;
+use crate::swapchain_config::{self, SwapchainOptions};
+
+/// Draws into a window: owns the swapchain of its surface, the render pass the windowed samples
+/// draw in and the framebuffers and viewport matching the current size of the window.
+///
+/// The swapchain is recreated whenever it no longer matches the window: after a resize, or when
+/// acquiring or presenting reports it as suboptimal or out of date. While the window is
+/// minimized no frames are drawn at all.
+///
+/// Every window has its own renderer. Renderers sharing one render pass (see `sharing`) can be
+/// drawn into with the same pipelines.
+pub struct Renderer {
+    surface: Arc<Surface<Window>>,
+    queue: Arc<Queue>,
+    swapchain: Arc<Swapchain<Window>>,
+    images: Vec<Arc<SwapchainImage<Window>>>,
+    render_pass: Arc<RenderPass>,
+    samples: u32,
+    depth_format: Format,
+    framebuffers: Vec<Arc<dyn FramebufferAbstract + Send + Sync>>,
+    dynamic_state: DynamicState,
+    recreate_swapchain: bool,
+    // the swapchain is recreated with it, see `set_present_mode`
+    present_mode: PresentMode,
+    // whether the swapchain images can be copied into screenshots
+    screenshots_supported: bool,
+    // where the next presented frame is saved to
+    screenshot_requested: Option<PathBuf>,
+    // copied by frames in flight
+    screenshots: Vec<Screenshot>,
+    saving: Vec<JoinHandle<()>>,
+}
+
+/// A swapchain image acquired for drawing the next frame.
+pub struct AcquiredImage {
+    index: usize,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+    // signaled once the image may be drawn to
+    future: SwapchainAcquireFuture<Window>,
+}
+
+impl PresentImage for AcquiredImage {
+    fn framebuffer(&self) -> Arc<dyn FramebufferAbstract + Send + Sync> {
+        self.framebuffer.clone()
+    }
+}
+
+impl Renderer {
+    /// Creates the swapchain of `surface` with the configuration negotiated from `options`, and
+    /// a render pass drawing into it with `samples` samples per pixel and a depth buffer.
+    pub fn new(
+        queue: Arc<Queue>,
+        surface: Arc<Surface<Window>>,
+        options: &SwapchainOptions,
+        samples: u32,
+        depth_format: Format,
+    ) -> Renderer {
+        let (swapchain, images) = create_swapchain(&queue, &surface, options);
+        let render_pass = create_render_pass(queue.device().clone(), swapchain.format(), samples, depth_format);
+        Renderer::with_swapchain(queue, surface, swapchain, images, render_pass, samples, depth_format)
+    }
+
+    /// Creates the swapchain of another window, which the render pass of `other` draws into as
+    /// well, so the pipelines built for one of the renderers can draw into both.
+    ///
+    /// The format of the render pass is preferred over the formats in `options`. Panics if the
+    /// surface doesn't support it.
+    pub fn sharing(surface: Arc<Surface<Window>>, options: &SwapchainOptions, other: &Renderer) -> Renderer {
+        let format = other.swapchain.format();
+        let mut options = options.clone();
+        options.formats.retain(|&preferred| preferred != format);
+        options.formats.insert(0, format);
+
+        let (swapchain, images) = create_swapchain(&other.queue, &surface, &options);
+        assert_eq!(
+            swapchain.format(),
+            format,
+            "windows sharing a render pass need surfaces supporting the same format",
+        );
+        Renderer::with_swapchain(
+            other.queue.clone(),
+            surface,
+            swapchain,
+            images,
+            other.render_pass.clone(),
+            other.samples,
+            other.depth_format,
+        )
+    }
+
+    fn with_swapchain(
+        queue: Arc<Queue>,
+        surface: Arc<Surface<Window>>,
+        swapchain: Arc<Swapchain<Window>>,
+        images: Vec<Arc<SwapchainImage<Window>>>,
+        render_pass: Arc<RenderPass>,
+        samples: u32,
+        depth_format: Format,
+    ) -> Renderer {
+        let mut dynamic_state = DynamicState::none();
+        let framebuffers = framebuffers(&images, render_pass.clone(), samples, depth_format, &mut dynamic_state);
+        let screenshots_supported =
+            swapchain_usage(&queue, &surface).transfer_source && Screenshot::supports(swapchain.format());
+
+        Renderer {
+            present_mode: swapchain.present_mode(),
+            surface,
+            queue,
+            swapchain,
+            images,
+            render_pass,
+            samples,
+            depth_format,
+            framebuffers,
+            dynamic_state,
+            recreate_swapchain: false,
+            screenshots_supported,
+            screenshot_requested: None,
+            screenshots: Vec::new(),
+            saving: Vec::new(),
+        }
+    }
+
+    pub fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
+    pub fn render_pass(&self) -> Arc<RenderPass> {
+        self.render_pass.clone()
+    }
+
+    /// The subpass of the render pass the scene is drawn in, pipelines drawing into the window
+    /// have to be built for it.
+    pub fn subpass(&self) -> Subpass {
+        Subpass::from(self.render_pass.clone(), 0).unwrap()
+    }
+
+    /// The subpass drawing on top of the scene, into the single sampled image without a depth
+    /// buffer. It is entered with `next_subpass` after the scene has been drawn.
+    pub fn overlay_subpass(&self) -> Subpass {
+        Subpass::from(self.render_pass.clone(), 1).unwrap()
+    }
+
+    /// The dimensions of the swapchain images, which the projections are calculated from.
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.swapchain.dimensions()
+    }
+
+    /// Width divided by height of the swapchain images.
+    pub fn aspect(&self) -> f32 {
+        let [width, height] = self.dimensions();
+        width as f32 / height as f32
+    }
+
+    /// Dynamic state with a viewport covering the whole window.
+    pub fn dynamic_state(&self) -> &DynamicState {
+        &self.dynamic_state
+    }
+
+    /// Clear values for `begin_render_pass`, one per attachment in render pass order.
+    pub fn clear_values(&self, clear_color: [f32; 4]) -> Vec<ClearValue> {
+        let mut clear_values = vec![clear_color.into()];
+        if self.samples > 1 {
+            // The swapchain image only receives the resolved samples, it is not cleared.
+            clear_values.push(ClearValue::None);
+        }
+        // The depth buffer is cleared to the far plane.
+        clear_values.push(1.0f32.into());
+        clear_values
+    }
+
+    /// Has to be called when the window is resized, the swapchain is recreated before the next
+    /// frame.
+    pub fn resized(&mut self) {
+        self.recreate_swapchain = true;
+    }
+
+    /// The present modes the surface supports, any of them can be given to `set_present_mode`.
+    pub fn present_modes(&self) -> Vec<PresentMode> {
+        let caps = self.surface.capabilities(self.queue.device().physical_device()).unwrap();
+        caps.present_modes.iter().collect()
+    }
+
+    pub fn present_mode(&self) -> PresentMode {
+        self.present_mode
+    }
+
+    /// Recreates the swapchain with another present mode before the next frame. Modes which the
+    /// surface doesn't support are ignored.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        if !self.present_modes().contains(&present_mode) {
+            println!("present mode {:?} is not supported", present_mode);
+            return;
+        }
+        self.present_mode = present_mode;
+        self.recreate_swapchain = true;
+    }
+
+    /// Switches the window between borderless fullscreen on its current monitor and windowed.
+    ///
+    /// With the `ext_full_screen_exclusive` extension the driver may also give the swapchain
+    /// exclusive access to the display while the window is fullscreen, so its images are
+    /// presented without going through the compositor.
+    pub fn toggle_fullscreen(&mut self) {
+        let window = self.surface.window();
+        if window.fullscreen().is_some() {
+            window.set_fullscreen(None);
+        } else {
+            window.set_fullscreen(Some(Fullscreen::Borderless(window.current_monitor())));
+        }
+        // not every platform reports the new size of the window as a resize
+        self.recreate_swapchain = true;
+    }
+
+    /// Saves the next frame presented to the window as a PNG file at `path`, once the gpu has
+    /// copied it out of the swapchain. Returns false if the swapchain images can't be copied
+    /// from, because the surface doesn't support it or their format can't be converted.
+    pub fn request_screenshot(&mut self, path: PathBuf) -> bool {
+        if !self.screenshots_supported {
+            println!("screenshots of {:?} swapchain images are not supported", self.swapchain.format());
+            return false;
+        }
+        self.screenshot_requested = Some(path);
+        true
+    }
+
+    /// Waits until all screenshots have been saved. The gpu has to be done with the frames, see
+    /// `FramesInFlight::wait_all`.
+    pub fn finish_screenshots(&mut self) {
+        self.save_screenshots();
+        for saving in self.saving.drain(..) {
+            saving.join().unwrap();
+        }
+    }
+
+    // starts saving the screenshots which the gpu is done copying
+    fn save_screenshots(&mut self) {
+        for screenshot in mem::take(&mut self.screenshots) {
+            match screenshot.save() {
+                Ok(saving) => self.saving.push(saving),
+                Err(screenshot) => self.screenshots.push(screenshot),
+            }
+        }
+    }
+
+    /// Whether the window has no area to draw into, e.g. because it is minimized.
+    pub fn is_minimized(&self) -> bool {
+        let [width, height] = window_dimensions(&self.surface);
+        width == 0 || height == 0
+    }
+}
+
+impl PresentTarget for Renderer {
+    type Image = AcquiredImage;
+
+    fn dynamic_state(&self) -> &DynamicState {
+        Renderer::dynamic_state(self)
+    }
+
+    fn clear_values(&self, clear_color: [f32; 4]) -> Vec<ClearValue> {
+        Renderer::clear_values(self, clear_color)
+    }
+
+    /// Recreates the swapchain if needed and acquires the image to draw the next frame into.
+    ///
+    /// Returns `None` if no frame can be drawn right now: while the window is minimized, while
+    /// the swapchain can't be recreated with the current size of the window, or if the swapchain
+    /// turned out to be out of date, in which case it is recreated for the next frame.
+    fn acquire(&mut self) -> Option<AcquiredImage> {
+        // the frame the image was acquired for last has finished
+        self.save_screenshots();
+
+        if self.is_minimized() {
+            return None;
+        }
+
+        if self.recreate_swapchain {
+            // A resize can make the swapchain out of date before it has been presented to, so it
+            // is only recreated while there is a size to recreate it with.
+            let (swapchain, images) = match self
+                .swapchain
+                .recreate()
+                .dimensions(window_dimensions(&self.surface))
+                .present_mode(self.present_mode)
+                .build()
+            {
+                Ok(result) => result,
+                // This error tends to happen when the user is manually resizing the window.
+                // Trying again with the next frame is the easiest way to fix this issue.
+                Err(SwapchainCreationError::UnsupportedDimensions) => return None,
+                Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
+            };
+
+            self.swapchain = swapchain;
+            self.images = images;
+            // Because framebuffers contains an Arc on the old swapchain, we need to recreate
+            // framebuffers as well.
+            self.framebuffers = framebuffers(
+                &self.images,
+                self.render_pass.clone(),
+                self.samples,
+                self.depth_format,
+                &mut self.dynamic_state,
+            );
+            self.recreate_swapchain = false;
+        }
+
+        // Before we can draw on the output, we have to *acquire* an image from the swapchain. This
+        // function can block if no image is available (which happens if you submit draw commands
+        // too quickly).
+        let (index, suboptimal, future) = match vulkano::swapchain::acquire_next_image(self.swapchain.clone(), None) {
+            Ok(result) => result,
+            // the swapchain was made unusable by some change and needs to be recreated
+            Err(AcquireError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                return None;
+            }
+            // the driver took exclusive fullscreen away, the next frame is presented without it
+            Err(AcquireError::FullscreenExclusiveLost) => return None,
+            Err(e) => panic!("Failed to acquire next image: {:?}", e),
+        };
+
+        // A suboptimal image can still be drawn to, but may not display correctly. With some
+        // drivers this is the case after a resize, without the swapchain being out of date.
+        if suboptimal {
+            self.recreate_swapchain = true;
+        }
+
+        Some(AcquiredImage {
+            index,
+            framebuffer: self.framebuffers[index].clone(),
+            future,
+        })
+    }
+
+    /// Submissions which find the swapchain out of date fail, it is recreated for the next frame.
+    fn present(
+        &mut self,
+        previous: Box<dyn GpuFuture>,
+        image: AcquiredImage,
+        command_buffer: PrimaryAutoCommandBuffer,
+    ) -> Option<FrameFence> {
+        let mut future = previous
+            .join(image.future)
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .boxed();
+
+        // A screenshot is copied out of the image after the frame has been drawn into it, and
+        // before it is presented.
+        if let Some(path) = self.screenshot_requested.take() {
+            let mut builder = AutoCommandBufferBuilder::primary(
+                self.queue.device().clone(),
+                self.queue.family(),
+                CommandBufferUsage::OneTimeSubmit,
+            )
+            .unwrap();
+            let screenshot = Screenshot::record(&mut builder, self.images[image.index].clone(), path);
+            self.screenshots.push(screenshot);
+            future = future
+                .then_execute(self.queue.clone(), builder.build().unwrap())
+                .unwrap()
+                .boxed();
+        }
+
+        let future = future
+            // The image is not presented immediately. Instead a present command is submitted at
+            // the end of the queue, so it is only presented once the command buffer is done.
+            .then_swapchain_present(self.queue.clone(), self.swapchain.clone(), image.index)
+            .boxed()
+            .then_signal_fence_and_flush();
+
+        match future {
+            Ok(future) => Some(future),
+            Err(FlushError::OutOfDate) => {
+                self.recreate_swapchain = true;
+                None
+            }
+            Err(FlushError::FullscreenExclusiveLost) => None,
+            Err(e) => {
+                println!("Failed to flush future: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Creates the swapchain of `surface` with the configuration negotiated from `options`.
+fn create_swapchain(
+    queue: &Arc<Queue>,
+    surface: &Arc<Surface<Window>>,
+    options: &SwapchainOptions,
+) -> (Arc<Swapchain<Window>>, Vec<Arc<SwapchainImage<Window>>>) {
+    let device = queue.device().clone();
+
+    // Querying the capabilities of the surface. When we create the swapchain we can only
+    // pass values that are allowed by the capabilities.
+    let caps = surface.capabilities(device.physical_device()).unwrap();
+
+    // The present mode, the internal format of the images, how many images there are and
+    // how the alpha value of the final image behaves (e.g. whether the window is opaque or
+    // transparent) are chosen from the preferences given on the command line, as far as the
+    // surface supports them.
+    let config = swapchain_config::negotiate(&caps, options);
+    println!("swapchain: {}", config);
+
+    // The swapchain always has the size of the window. A window which starts minimized gets
+    // the smallest possible swapchain, which is recreated once it is shown.
+    let [width, height] = window_dimensions(surface);
+
+    // With the `ext_full_screen_exclusive` extension (only available on Windows), the driver is
+    // allowed to give the swapchain of a borderless fullscreen window exclusive access to the
+    // display, see `toggle_fullscreen`. Controlling it from the application would need the
+    // monitor of the window from the Win32 API.
+    let fullscreen_exclusive = if device.enabled_extensions().ext_full_screen_exclusive {
+        FullscreenExclusive::Allowed
+    } else {
+        FullscreenExclusive::Default
+    };
+
+    // Creating a swapchain allocates the color buffers that will contain the image that will
+    // ultimately be visible on the screen. These images are returned alongside with the
+    // swapchain.
+    let (swapchain, images) = Swapchain::start(device.clone(), surface.clone())
+        .num_images(config.image_count)
+        .format(config.format)
+        .dimensions([width.max(1), height.max(1)])
+        .usage(swapchain_usage(queue, surface))
+        .transform(SurfaceTransform::Identity)
+        .composite_alpha(config.composite_alpha)
+        .present_mode(config.present_mode)
+        .fullscreen_exclusive(fullscreen_exclusive)
+        .clipped(true)
+        .color_space(config.color_space)
+        .layers(1)
+        .build()
+        .expect("Swapchain creation failed");
+
+    (swapchain, images)
+}
+
+// The swapchain images are drawn into, and copied from for screenshots if the surface allows it.
+fn swapchain_usage(queue: &Queue, surface: &Surface<Window>) -> ImageUsage {
+    let caps = surface.capabilities(queue.device().physical_device()).unwrap();
+    ImageUsage {
+        transfer_source: caps.supported_usage_flags.transfer_source,
+        ..ImageUsage::color_attachment()
+    }
+}
+
+// the size of the area inside of the window, zero while it is minimized
+fn window_dimensions(surface: &Surface<Window>) -> [u32; 2] {
+    surface.window().inner_size().into()
+}
+
+/// Creates the render pass the windowed samples draw in, which describes where the output of the
+/// graphics pipelines will go: into the swapchain image with the given `format`, with a depth
+/// buffer.
+///
+/// The scene is drawn in the first subpass. The second one draws into the final, single sampled
+/// image without a depth buffer, for overlays on top of the scene like the text of `text`.
+fn create_render_pass(device: Arc<Device>, format: Format, samples: u32, depth_format: Format) -> Arc<RenderPass> {
+    if samples > 1 {
+        // With multisampling we draw into an intermediary image with multiple samples per
+        // pixel, which is resolved into the single sampled swapchain image at the end of the
+        // first subpass.
+        Arc::new(
+            vulkano::ordered_passes_renderpass!(
+                device,
+                attachments: {
+                    intermediary: {
+                        load: Clear,
+                        // The multisampled content is not needed after it has been resolved.
+                        store: DontCare,
+                        format: format,
+                        samples: samples,
+                    },
+                    color: {
+                        load: DontCare,
+                        store: Store,
+                        format: format,
+                        samples: 1,
+                    },
+                    depth: {
+                        load: Clear,
+                        store: DontCare,
+                        format: depth_format,
+                        samples: samples,
+                    }
+                },
+                passes: [
+                    {
+                        color: [intermediary],
+                        depth_stencil: {depth},
+                        input: [],
+                        resolve: [color],
+                    },
+                    // the overlay is drawn into the resolved image
+                    {
+                        color: [color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            )
+            .unwrap(),
+        )
+    } else {
+        Arc::new(
+            vulkano::ordered_passes_renderpass!(
+                device,
+                attachments: {
+                    // `load: Clear` clears the image at the start of the drawing, `store: Store`
+                    // keeps the output of the draw in the actual image.
+                    color: {
+                        load: Clear,
+                        store: Store,
+                        format: format,
+                        samples: 1,
+                    },
+                    // The depth values are only needed while drawing, so they are not stored.
+                    depth: {
+                        load: Clear,
+                        store: DontCare,
+                        format: depth_format,
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [color],
+                        depth_stencil: {depth},
+                        input: []
+                    },
+                    {
+                        color: [color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            )
+            .unwrap(),
+        )
+    }
+}
+
+/// Creates a framebuffer for each swapchain image, and sets the viewport of `dynamic_state` to
+/// cover them. Called once during initialization, then again whenever the swapchain is
+/// recreated.
+fn framebuffers(
+    images: &[Arc<SwapchainImage<Window>>],
+    render_pass: Arc<RenderPass>,
+    samples: u32,
+    depth_format: Format,
+    dynamic_state: &mut DynamicState,
+) -> Vec<Arc<dyn FramebufferAbstract + Send + Sync>> {
+    let dimensions = images[0].dimensions();
+
+    dynamic_state.viewports = Some(vec![Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+        depth_range: 0.0..1.0,
+    }]);
+
+    // the multisampled image is only used during the render pass, so a single (transient) one
+    // can be shared by all framebuffers
+    let intermediary = if samples > 1 {
+        let intermediary = AttachmentImage::transient_multisampled(
+            render_pass.device().clone(),
+            dimensions,
+            SampleCount::try_from(samples).unwrap(),
+            images[0].swapchain().format(),
+        )
+        .unwrap();
+        Some(ImageView::new(intermediary).unwrap())
+    } else {
+        None
+    };
+
+    // the depth buffer has to match the dimensions as well, like the intermediary image it is
+    // only used while drawing and shared by all framebuffers
+    let depth_buffer = ImageView::new(
+        AttachmentImage::transient_multisampled(
+            render_pass.device().clone(),
+            dimensions,
+            SampleCount::try_from(samples).unwrap(),
+            depth_format,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    images
+        .iter()
+        .map(|image| {
+            let image_view = ImageView::new(image.clone()).unwrap();
+            match &intermediary {
+                // the attachments are added in the order of the render pass: first the
+                // multisampled image, then the swapchain image it is resolved into and the
+                // depth buffer last
+                Some(intermediary) => Arc::new(
+                    Framebuffer::start(render_pass.clone())
+                        .add(intermediary.clone())
+                        .unwrap()
+                        .add(image_view)
+                        .unwrap()
+                        .add(depth_buffer.clone())
+                        .unwrap()
+                        .build()
+                        .unwrap(),
+                ) as Arc<dyn FramebufferAbstract + Send + Sync>,
+                None => Arc::new(
+                    Framebuffer::start(render_pass.clone())
+                        .add(image_view)
+                        .unwrap()
+                        .add(depth_buffer.clone())
+                        .unwrap()
+                        .build()
+                        .unwrap(),
+                ) as Arc<dyn FramebufferAbstract + Send + Sync>,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,407 @@
+use std::fmt;
+use std::path::Path;
+
+use cgmath::{Deg, EuclideanSpace, Matrix4, Point3, Vector3};
+use serde_json::{Map, Value};
+
+use crate::camera::Camera;
+use crate::cube_sample;
+use crate::mesh::{Mesh, MeshVertex};
+
+/// The scene the samples render when no file is given: the red triangle on blue of the render
+/// pass sample.
+pub const DEFAULT_SCENE: &str = include_str!("../scenes/triangle.json");
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    // `field` is the path to the offending value, like `shapes[1].color`
+    Invalid { field: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "failed to read scene: {}", err),
+            SceneError::Json(err) => write!(f, "invalid JSON: {}", err),
+            SceneError::Invalid { field, message } => write!(f, "{}: {}", field, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// A shape of a scene, with the transform and the color it is drawn with.
+pub struct Shape {
+    pub mesh: Mesh,
+    pub transform: Matrix4<f32>,
+    pub color: [f32; 4],
+}
+
+/// A scene loaded from a JSON file, like the ones in `scenes/`:
+///
+/// ```json
+/// {
+///     "clear_color": [0.0, 0.0, 1.0, 1.0],
+///     "resolution": [1024, 1024],
+///     "camera": { "eye": [0.0, 0.0, 3.0], "target": [0.0, 0.0, 0.0], "fov": 45.0 },
+///     "shapes": [
+///         {
+///             "type": "triangle",
+///             "vertices": [[-0.5, -0.5], [0.0, 0.5], [0.5, -0.25]],
+///             "color": [1.0, 0.0, 0.0, 1.0],
+///             "transform": { "translation": [0.0, 0.0, 0.0], "rotation": [0.0, 0.0, 0.0], "scale": 1.0 }
+///         },
+///         { "type": "cube" },
+///         { "type": "mesh", "path": "model.obj" }
+///     ]
+/// }
+/// ```
+///
+/// Only `shapes` is required. The clear color defaults to opaque black, the resolution (of the
+/// headless image and of the window when it is opened) to 1024x1024, and the color of a shape to
+/// white. Colors have three or four components. The vertices of a triangle have two or three
+/// components, z is 0 if it is left out. The paths of meshes are relative to the scene file.
+///
+/// The rotation is given in degrees around x, y and z, in that order. Scenes without a camera
+/// are drawn unlit in clip space, like the triangle of the samples.
+pub struct SceneFile {
+    pub clear_color: [f32; 4],
+    pub resolution: [u32; 2],
+    pub camera: Option<Camera>,
+    pub shapes: Vec<Shape>,
+}
+
+impl SceneFile {
+    /// Loads the scene at `path` and the meshes it refers to.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SceneFile, SceneError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(SceneError::Io)?;
+        SceneFile::parse(&json, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    /// The scene loaded with `load`, or the default one without a path.
+    pub fn load_or_default(path: Option<&str>) -> Result<SceneFile, SceneError> {
+        match path {
+            Some(path) => SceneFile::load(path),
+            None => SceneFile::parse(DEFAULT_SCENE, Path::new("")),
+        }
+    }
+
+    /// The vertices and the color of the triangle, if the scene is a single untransformed
+    /// triangle in the xy plane without a camera, like the default scene. The samples drawing a
+    /// single triangle in clip space take it from the scene.
+    pub fn triangle(&self) -> Option<([[f32; 2]; 3], [f32; 4])> {
+        let shape = match (&self.camera, &self.shapes[..]) {
+            (None, [shape]) => shape,
+            _ => return None,
+        };
+        let vertices = &shape.mesh.vertices;
+        if vertices.len() != 3 || shape.transform != Matrix4::from_scale(1.0) {
+            return None;
+        }
+        if vertices.iter().any(|vertex| vertex.position[2] != 0.0) {
+            return None;
+        }
+
+        let xy = |i: usize| [vertices[i].position[0], vertices[i].position[1]];
+        Some(([xy(0), xy(1), xy(2)], shape.color))
+    }
+
+    /// Parses and validates a scene, the paths of meshes are relative to `dir`.
+    pub fn parse(json: &str, dir: &Path) -> Result<SceneFile, SceneError> {
+        let value: Value = serde_json::from_str(json).map_err(SceneError::Json)?;
+        let scene = Object::new(&value, ROOT, &["clear_color", "resolution", "camera", "shapes"])?;
+
+        let clear_color = match scene.get("clear_color") {
+            Some((value, field)) => color(value, &field)?,
+            None => [0.0, 0.0, 0.0, 1.0],
+        };
+        let resolution = match scene.get("resolution") {
+            Some((value, field)) => resolution(value, &field)?,
+            None => [1024, 1024],
+        };
+        let camera = match scene.get("camera") {
+            Some((value, field)) => Some(camera(value, &field)?),
+            None => None,
+        };
+        let (shapes, field) = scene.require("shapes")?;
+        let shapes = array(shapes, &field)?
+            .iter()
+            .enumerate()
+            .map(|(i, value)| shape(value, &format!("{}[{}]", field, i), dir))
+            .collect::<Result<_, _>>()?;
+
+        Ok(SceneFile {
+            clear_color,
+            resolution,
+            camera,
+            shapes,
+        })
+    }
+}
+
+// the path of errors in the top level object, its fields are named without it
+const ROOT: &str = "scene";
+
+// the fields of a JSON object, which may only be among the expected ones
+struct Object<'a> {
+    fields: &'a Map<String, Value>,
+    path: String,
+}
+
+impl<'a> Object<'a> {
+    fn new(value: &'a Value, path: &str, expected: &[&str]) -> Result<Object<'a>, SceneError> {
+        let fields = value.as_object().ok_or_else(|| invalid(path, "an object", value))?;
+        if let Some(unknown) = fields.keys().find(|key| !expected.contains(&key.as_str())) {
+            return Err(SceneError::Invalid {
+                field: path.to_string(),
+                message: format!("unknown field `{}`, expected one of {}", unknown, expected.join(", ")),
+            });
+        }
+
+        Ok(Object {
+            fields,
+            path: path.to_string(),
+        })
+    }
+
+    // the value of the field `name` and the path to it, if the field is there
+    fn get(&self, name: &str) -> Option<(&'a Value, String)> {
+        let path = if self.path == ROOT {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.path, name)
+        };
+        self.fields.get(name).map(|value| (value, path))
+    }
+
+    fn require(&self, name: &str) -> Result<(&'a Value, String), SceneError> {
+        self.get(name).ok_or_else(|| SceneError::Invalid {
+            field: self.path.clone(),
+            message: format!("missing field `{}`", name),
+        })
+    }
+}
+
+fn shape(value: &Value, path: &str, dir: &Path) -> Result<Shape, SceneError> {
+    let object = Object::new(value, path, &["type", "vertices", "path", "color", "transform"])?;
+
+    let (kind, kind_path) = object.require("type")?;
+    // the field with the geometry of the type, if it has one
+    let (mesh, geometry) = match kind.as_str() {
+        Some("triangle") => {
+            let (vertices, field) = object.require("vertices")?;
+            (triangle(vertices, &field)?, Some("vertices"))
+        }
+        Some("cube") => (cube(), None),
+        Some("mesh") => {
+            let (mesh_path, field) = object.require("path")?;
+            let mesh_path = mesh_path.as_str().ok_or_else(|| invalid(&field, "a path", mesh_path))?;
+            let mesh = Mesh::load(dir.join(mesh_path)).map_err(|err| SceneError::Invalid {
+                field,
+                message: format!("{}: {}", mesh_path, err),
+            })?;
+            (mesh, Some("path"))
+        }
+        _ => return Err(invalid(&kind_path, "`triangle`, `cube` or `mesh`", kind)),
+    };
+
+    // the geometry of the other types isn't allowed
+    for other in ["vertices", "path"].iter().filter(|&&other| Some(other) != geometry) {
+        if let Some((_, field)) = object.get(other) {
+            return Err(SceneError::Invalid {
+                field,
+                message: format!("not used by shapes of type {}", kind),
+            });
+        }
+    }
+
+    let color = match object.get("color") {
+        Some((value, field)) => color(value, &field)?,
+        None => [1.0, 1.0, 1.0, 1.0],
+    };
+    let transform = match object.get("transform") {
+        Some((value, field)) => transform(value, &field)?,
+        None => Matrix4::from_scale(1.0),
+    };
+
+    Ok(Shape { mesh, transform, color })
+}
+
+fn triangle(value: &Value, path: &str) -> Result<Mesh, SceneError> {
+    let vertices = array(value, path)?;
+    if vertices.len() != 3 {
+        return Err(SceneError::Invalid {
+            field: path.to_string(),
+            message: format!("expected 3 vertices, found {}", vertices.len()),
+        });
+    }
+
+    let mut mesh = Mesh {
+        vertices: Vec::new(),
+        indices: vec![0, 1, 2],
+    };
+    for (i, vertex) in vertices.iter().enumerate() {
+        let field = format!("{}[{}]", path, i);
+        let position = match numbers(vertex, &field, &[2, 3])?[..] {
+            [x, y] => [x, y, 0.0],
+            [x, y, z] => [x, y, z],
+            _ => unreachable!(),
+        };
+        mesh.vertices.push(MeshVertex {
+            position,
+            ..MeshVertex::default()
+        });
+    }
+    mesh.compute_normals();
+    Ok(mesh)
+}
+
+// the unit cube of the cube sample, with a normal per face
+fn cube() -> Mesh {
+    let vertices = cube_sample::cube_vertices();
+    let mut mesh = Mesh {
+        vertices: vertices
+            .iter()
+            .map(|vertex| MeshVertex {
+                position: vertex.position,
+                ..MeshVertex::default()
+            })
+            .collect(),
+        // two triangles for the four counter-clockwise corners of each face
+        indices: (0..vertices.len() as u32 / 4)
+            .flat_map(|face| [0, 1, 2, 0, 2, 3].iter().map(move |corner| face * 4 + corner))
+            .collect(),
+    };
+    mesh.compute_normals();
+    mesh
+}
+
+fn transform(value: &Value, path: &str) -> Result<Matrix4<f32>, SceneError> {
+    let object = Object::new(value, path, &["translation", "rotation", "scale"])?;
+
+    let translation = match object.get("translation") {
+        Some((value, field)) => vector3(value, &field)?,
+        None => Vector3::new(0.0, 0.0, 0.0),
+    };
+    let rotation = match object.get("rotation") {
+        Some((value, field)) => {
+            let [x, y, z]: [f32; 3] = vector3(value, &field)?.into();
+            Matrix4::from_angle_z(Deg(z)) * Matrix4::from_angle_y(Deg(y)) * Matrix4::from_angle_x(Deg(x))
+        }
+        None => Matrix4::from_scale(1.0),
+    };
+    // a single number scales uniformly
+    let scale = match object.get("scale") {
+        Some((value, field)) => match value.as_f64() {
+            Some(scale) => Matrix4::from_scale(scale as f32),
+            None => {
+                let scale = numbers(value, &field, &[3])?;
+                Matrix4::from_nonuniform_scale(scale[0], scale[1], scale[2])
+            }
+        },
+        None => Matrix4::from_scale(1.0),
+    };
+
+    Ok(Matrix4::from_translation(translation) * rotation * scale)
+}
+
+fn camera(value: &Value, path: &str) -> Result<Camera, SceneError> {
+    let object = Object::new(value, path, &["eye", "target", "fov"])?;
+    let mut camera = Camera::default();
+
+    let (eye, field) = object.require("eye")?;
+    camera.eye = Point3::from_vec(vector3(eye, &field)?);
+    if let Some((target, field)) = object.get("target") {
+        camera.target = Point3::from_vec(vector3(target, &field)?);
+    }
+    if let Some((fov, field)) = object.get("fov") {
+        let fov = number(fov, &field)?;
+        if !(fov > 0.0 && fov < 180.0) {
+            return Err(SceneError::Invalid {
+                field,
+                message: format!("expected an angle between 0 and 180 degrees, found {}", fov),
+            });
+        }
+        camera.fovy = Deg(fov);
+    }
+
+    Ok(camera)
+}
+
+fn color(value: &Value, path: &str) -> Result<[f32; 4], SceneError> {
+    match numbers(value, path, &[3, 4])?[..] {
+        [r, g, b] => Ok([r, g, b, 1.0]),
+        [r, g, b, a] => Ok([r, g, b, a]),
+        _ => unreachable!(),
+    }
+}
+
+fn resolution(value: &Value, path: &str) -> Result<[u32; 2], SceneError> {
+    let size = array(value, path)?;
+    let dimension = |i: usize| {
+        let field = format!("{}[{}]", path, i);
+        match size[i].as_u64() {
+            Some(dimension) if dimension > 0 && dimension <= u32::MAX as u64 => Ok(dimension as u32),
+            _ => Err(invalid(&field, "a positive integer", &size[i])),
+        }
+    };
+    if size.len() != 2 {
+        return Err(SceneError::Invalid {
+            field: path.to_string(),
+            message: format!("expected width and height, found {} values", size.len()),
+        });
+    }
+
+    Ok([dimension(0)?, dimension(1)?])
+}
+
+fn vector3(value: &Value, path: &str) -> Result<Vector3<f32>, SceneError> {
+    let vector = numbers(value, path, &[3])?;
+    Ok(Vector3::new(vector[0], vector[1], vector[2]))
+}
+
+// an array of numbers, with one of the given lengths
+fn numbers(value: &Value, path: &str, lengths: &[usize]) -> Result<Vec<f32>, SceneError> {
+    let values = array(value, path)?;
+    if !lengths.contains(&values.len()) {
+        let expected = lengths.iter().map(|length| length.to_string()).collect::<Vec<_>>();
+        return Err(SceneError::Invalid {
+            field: path.to_string(),
+            message: format!("expected {} numbers, found {}", expected.join(" or "), values.len()),
+        });
+    }
+
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| number(value, &format!("{}[{}]", path, i)))
+        .collect()
+}
+
+fn number(value: &Value, path: &str) -> Result<f32, SceneError> {
+    value.as_f64().map(|number| number as f32).ok_or_else(|| invalid(path, "a number", value))
+}
+
+fn array<'a>(value: &'a Value, path: &str) -> Result<&'a Vec<Value>, SceneError> {
+    value.as_array().ok_or_else(|| invalid(path, "an array", value))
+}
+
+// the error of a value of the wrong type
+fn invalid(path: &str, expected: &str, found: &Value) -> SceneError {
+    let found = match found {
+        Value::Null => String::from("null"),
+        Value::Bool(value) => format!("`{}`", value),
+        Value::Number(value) => format!("the number {}", value),
+        Value::String(value) => format!("the string \"{}\"", value),
+        Value::Array(_) => String::from("an array"),
+        Value::Object(_) => String::from("an object"),
+    };
+
+    SceneError::Invalid {
+        field: path.to_string(),
+        message: format!("expected {}, found {}", expected, found),
+    }
+}
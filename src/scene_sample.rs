@@ -0,0 +1,215 @@
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::render_pass::Subpass;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4};
+use image::{Rgba, ImageBuffer};
+
+use crate::cli::Options;
+use crate::depth;
+use crate::index::IndexBuffer;
+use crate::mesh::MeshVertex;
+use crate::offscreen::OffscreenTarget;
+use crate::pipeline_cache;
+use crate::scene_file::SceneFile;
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec3 position;
+layout(location=1) in vec3 normal;
+layout(location=2) in vec2 uv;
+
+layout(location=0) out vec3 v_normal;
+layout(location=1) out vec4 v_color;
+layout(location=2) out vec4 v_light;
+
+layout(push_constant) uniform PushConstants {
+    mat4 mvp;
+    vec4 color;
+    // the direction to the light in model space, w is 0 for unlit shapes
+    vec4 light;
+} push_constants;
+
+void main() {
+    v_normal = normal;
+    v_color = push_constants.color;
+    v_light = push_constants.light;
+    gl_Position = push_constants.mvp * vec4(position, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec3 v_normal;
+layout(location=1) in vec4 v_color;
+layout(location=2) in vec4 v_light;
+layout(location=0) out vec4 f_color;
+
+void main() {
+    // the same light as the meshes of render_mesh, if the shape is lit at all
+    float diffuse = max(dot(normalize(v_normal), normalize(v_light.xyz)), 0.0);
+    float shade = mix(1.0, 0.2 + 0.8 * diffuse, v_light.w);
+    f_color = vec4(v_color.rgb * shade, v_color.a);
+}
+"
+    }
+}
+
+// the direction to the light of render_mesh, in world space
+fn light_direction() -> Vector3<f32> {
+    Vector3::new(0.4, 1.0, 0.6).normalize()
+}
+
+struct ShapeBuffers {
+    vertex_buffer: Arc<CpuAccessibleBuffer<[MeshVertex]>>,
+    index_buffer: IndexBuffer,
+    transform: Matrix4<f32>,
+    color: [f32; 4],
+}
+
+/// Pipeline and buffers to draw the shapes of a scene file into a subpass, each with a draw call
+/// of its own.
+///
+/// The depth test is enabled, if the subpass has a depth attachment.
+pub struct SceneRenderer {
+    pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    shapes: Vec<ShapeBuffers>,
+    // whether the scene has a camera, the shapes are only lit then
+    lit: bool,
+}
+
+impl SceneRenderer {
+    pub fn new(device: Arc<Device>, subpass: Subpass, scene: &SceneFile) -> SceneRenderer {
+        // load shader for device
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        let builder = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<MeshVertex>()
+            .vertex_shader(vertex_shader.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fragment_shader.main_entry_point(), ());
+
+        let builder = if subpass.has_depth() {
+            builder.depth_stencil_simple_depth()
+        } else {
+            builder
+        };
+
+        let pipeline = Arc::new(
+            builder
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        let shapes = scene
+            .shapes
+            .iter()
+            .map(|shape| ShapeBuffers {
+                vertex_buffer: shape.mesh.vertex_buffer(device.clone()),
+                index_buffer: shape.mesh.index_buffer(device.clone()),
+                transform: shape.transform,
+                color: shape.color,
+            })
+            .collect();
+
+        SceneRenderer {
+            pipeline,
+            shapes,
+            lit: scene.camera.is_some(),
+        }
+    }
+
+    /// Draws the shapes as seen through `view_projection`, the identity for scenes without a
+    /// camera.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+        view_projection: Matrix4<f32>,
+    ) {
+        for shape in &self.shapes {
+            // the light is moved into model space instead of the normals into world space, which
+            // is the same as long as the shape isn't scaled unevenly
+            let light = match (self.lit, shape.transform.invert()) {
+                (true, Some(inverse)) => (inverse * light_direction().extend(0.0)).truncate().extend(1.0),
+                _ => Vector4::new(0.0, 0.0, 0.0, 0.0),
+            };
+            let push_constants = vs::ty::PushConstants {
+                mvp: (view_projection * shape.transform).into(),
+                color: shape.color,
+                light: light.into(),
+            };
+
+            shape.index_buffer.draw(
+                builder,
+                self.pipeline.clone(),
+                dynamic_state,
+                shape.vertex_buffer.clone(),
+                (),
+                push_constants,
+            );
+        }
+    }
+}
+
+/// Renders `scene` headlessly into `target`, cleared to the clear color of the scene.
+pub fn render(
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    target: &OffscreenTarget,
+    scene: &SceneFile,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let renderer = SceneRenderer::new(device, target.subpass(), scene);
+    let dynamic_state = target.dynamic_state();
+    let [width, height] = target.dimensions();
+    let view_projection = match &scene.camera {
+        Some(camera) => camera.view_projection(width as f32 / height as f32),
+        None => Matrix4::identity(),
+    };
+
+    target.render(queue, scene.clear_color, |builder| {
+        renderer.draw(builder, &dynamic_state, view_projection);
+    })
+}
+
+/// `render-scene [SCENE]` loads a scene file (see `SceneFile`) and saves an image of it, in the
+/// resolution of the scene, to `scene.png`. Without a file the default scene is rendered, the
+/// triangle of `render-pass-sample`.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let path = options.args.first().map(String::as_str);
+    let scene = SceneFile::load_or_default(path)
+        .unwrap_or_else(|err| panic!("{}: {}", path.unwrap_or("default scene"), err));
+
+    let target = OffscreenTarget::start(device.clone(), scene.resolution)
+        .samples(samples)
+        .depth_format(depth::choose_depth_format(device.physical_device()))
+        .build();
+
+    let image_buffer = render(queue, device, &target, &scene);
+
+    let output = options.output.as_deref().unwrap_or("scene.png");
+    image_buffer.save(output).unwrap();
+    println!("Rendered {} shapes to {}", scene.shapes.len(), output);
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use cgmath::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::scene_file::{self, SceneFile};
+
+/// Parses the scene files in `scenes/` and broken scenes, checking that the errors point at the
+/// offending field. Like the input test, this doesn't need the gpu.
+pub fn execute() {
+    println!("performing scene test");
+
+    // the default scene is the triangle of the render pass sample
+    let scene = SceneFile::parse(scene_file::DEFAULT_SCENE, Path::new("")).unwrap();
+    assert_eq!((scene.clear_color, scene.resolution), ([0.0, 0.0, 1.0, 1.0], [1024, 1024]));
+    assert!(scene.camera.is_none());
+    assert_eq!(scene.shapes.len(), 1);
+    let triangle = &scene.shapes[0];
+    let positions: Vec<_> = triangle.mesh.vertices.iter().map(|vertex| vertex.position).collect();
+    assert_eq!(positions, [[-0.5, -0.5, 0.0], [0.0, 0.5, 0.0], [0.5, -0.25, 0.0]]);
+    assert_eq!((triangle.mesh.indices.as_slice(), triangle.color), (&[0, 1, 2][..], [1.0, 0.0, 0.0, 1.0]));
+    assert_eq!(triangle.transform, Matrix4::from_scale(1.0));
+    let expected = ([[-0.5, -0.5], [0.0, 0.5], [0.5, -0.25]], [1.0, 0.0, 0.0, 1.0]);
+    assert_eq!(scene.triangle(), Some(expected));
+
+    let scene = SceneFile::parse(include_str!("../scenes/cubes.json"), Path::new("scenes")).unwrap();
+    assert_eq!(scene.resolution, [1280, 720]);
+    assert_eq!(scene.camera.as_ref().unwrap().eye, Point3::new(3.0, 2.5, 4.0));
+    assert_eq!(scene.shapes.len(), 3);
+    assert!(scene.triangle().is_none());
+    // two triangles for each face of the cube, with the normals pointing outwards
+    let cube = &scene.shapes[1].mesh;
+    assert_eq!(cube.indices.len(), 36);
+    for vertex in &cube.vertices {
+        let outwards: f32 = (0..3).map(|axis| vertex.position[axis] * vertex.normal[axis]).sum();
+        assert!(outwards > 0.0, "normal {:?} at {:?} points inwards", vertex.normal, vertex.position);
+    }
+    // colors without alpha are opaque, scaled about the origin and then moved
+    assert_eq!(scene.shapes[1].color, [0.2, 0.8, 0.2, 1.0]);
+    let corner = scene.shapes[1].transform * Vector4::new(0.5, 0.5, 0.5, 1.0);
+    assert_eq!(corner, Vector4::new(1.25, 0.25, 0.25, 1.0));
+    // the ground is lit from above
+    let ground = &scene.shapes[2].mesh;
+    assert!(ground.vertices.iter().all(|vertex| Vector3::from(vertex.normal) == Vector3::unit_y()));
+
+    let errors = [
+        ("[]", "scene: expected an object, found an array"),
+        ("{}", "scene: missing field `shapes`"),
+        (
+            r#"{"shapes": [], "colour": [1, 0, 0]}"#,
+            "scene: unknown field `colour`, expected one of clear_color, resolution, camera, shapes",
+        ),
+        (r#"{"shapes": [], "clear_color": [1, 0]}"#, "clear_color: expected 3 or 4 numbers, found 2"),
+        (r#"{"shapes": [], "resolution": [800, -600]}"#, "resolution[1]: expected a positive integer, found the number -600"),
+        (r#"{"shapes": [], "camera": {"target": [0, 0, 0]}}"#, "camera: missing field `eye`"),
+        (r#"{"shapes": [], "camera": {"eye": [0, 0, 1], "fov": 180}}"#, "camera.fov: expected an angle between 0 and 180 degrees, found 180"),
+        (r#"{"shapes": [{"type": "sphere"}]}"#, "shapes[0].type: expected `triangle`, `cube` or `mesh`, found the string \"sphere\""),
+        (r#"{"shapes": [{"type": "cube"}, {"type": "triangle"}]}"#, "shapes[1]: missing field `vertices`"),
+        (
+            r#"{"shapes": [{"type": "triangle", "vertices": [[0, 0], [1, 0], [0, "1"]]}]}"#,
+            "shapes[0].vertices[2][1]: expected a number, found the string \"1\"",
+        ),
+        (
+            r#"{"shapes": [{"type": "triangle", "vertices": [[0, 0], [1, 0]]}]}"#,
+            "shapes[0].vertices: expected 3 vertices, found 2",
+        ),
+        (r#"{"shapes": [{"type": "cube", "path": "cube.obj"}]}"#, "shapes[0].path: not used by shapes of type \"cube\""),
+        (
+            r#"{"shapes": [{"type": "cube", "transform": {"scale": [1, 2]}}]}"#,
+            "shapes[0].transform.scale: expected 3 numbers, found 2",
+        ),
+        (
+            r#"{"shapes": [{"type": "cube", "transform": {"rotate": [0, 90, 0]}}]}"#,
+            "shapes[0].transform: unknown field `rotate`, expected one of translation, rotation, scale",
+        ),
+    ];
+    for (json, expected) in errors.iter() {
+        match SceneFile::parse(json, Path::new("")) {
+            Ok(_) => panic!("{} was parsed without an error", json),
+            Err(err) => assert_eq!(err.to_string(), *expected, "wrong error for {}", json),
+        }
+    }
+
+    // meshes are loaded relative to the scene file, errors while loading them point at the path
+    let missing = SceneFile::parse(r#"{"shapes": [{"type": "mesh", "path": "missing.obj"}]}"#, Path::new("scenes"))
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(missing.starts_with("shapes[0].path: missing.obj: failed to load OBJ"), "{}", missing);
+
+    // syntax errors are reported with their position
+    let syntax = SceneFile::parse("{\n    \"shapes\": [,]\n}", Path::new("")).err().unwrap().to_string();
+    assert!(syntax.starts_with("invalid JSON") && syntax.contains("line 2"), "{}", syntax);
+
+    println!("scene test successful!");
+}
@@ -0,0 +1,161 @@
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::device::DeviceOwned;
+use vulkano::format::Format;
+use vulkano::image::SwapchainImage;
+
+use winit::window::Window;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::{ImageBuffer, Rgba};
+
+/// A presented swapchain image on its way into a PNG file.
+///
+/// The image is copied into a host visible buffer after the frame has been drawn into it. Once
+/// the gpu is done with the copy, `save` converts and writes it on another thread, so neither
+/// waits for the other.
+pub struct Screenshot {
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    dimensions: [u32; 2],
+    format: Format,
+    path: PathBuf,
+}
+
+impl Screenshot {
+    /// Whether screenshots can be converted from swapchain images of `format`.
+    pub fn supports(format: Format) -> bool {
+        matches!(
+            format,
+            Format::B8G8R8A8Srgb
+                | Format::B8G8R8A8Unorm
+                | Format::R8G8B8A8Srgb
+                | Format::R8G8B8A8Unorm
+                | Format::A2B10G10R10UnormPack32
+        )
+    }
+
+    /// Records copying `image` into a new buffer into `builder`, to be saved to `path`. The
+    /// image needs the `transfer_source` usage, and a format which is `supports`ed.
+    pub fn record(
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        image: Arc<SwapchainImage<Window>>,
+        path: PathBuf,
+    ) -> Screenshot {
+        let dimensions = image.dimensions();
+        let format = image.swapchain().format();
+        // all supported formats have 4 bytes per pixel
+        let buffer = CpuAccessibleBuffer::from_iter(
+            image.swapchain().device().clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            (0..(dimensions[0] * dimensions[1]) as usize * 4).map(|_| 0u8),
+        )
+        .expect("Failed to create screenshot buffer");
+
+        builder.copy_image_to_buffer(image, buffer.clone()).unwrap();
+
+        Screenshot {
+            buffer,
+            dimensions,
+            format,
+            path,
+        }
+    }
+
+    /// Starts saving the screenshot on another thread, if the gpu is done copying it. Otherwise
+    /// it is returned to try again later.
+    pub fn save(self) -> Result<JoinHandle<()>, Screenshot> {
+        // the buffer is locked until the frame which copies into it has finished
+        let raw = self.buffer.read().map(|content| content.to_vec());
+        let raw = match raw {
+            Ok(raw) => raw,
+            Err(_) => return Err(self),
+        };
+
+        let Screenshot {
+            dimensions,
+            format,
+            path,
+            ..
+        } = self;
+        Ok(thread::spawn(move || {
+            let [width, height] = dimensions;
+            let image = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, to_opaque_rgba8(format, &raw)).unwrap();
+            if let Some(dir) = path.parent() {
+                let _ = std::fs::create_dir_all(dir);
+            }
+            match image.save(&path) {
+                Ok(()) => println!("saved screenshot {}", path.display()),
+                Err(err) => println!("failed to save screenshot {}: {}", path.display(), err),
+            }
+        }))
+    }
+}
+
+/// Converts the raw bytes of a swapchain image to RGBA with 8 bits per channel.
+///
+/// The values of sRGB and UNORM formats are kept as they are: the display shows both without
+/// converting them, so they already are what the screen showed. The alpha channel is set to
+/// opaque, as the window was composited without it.
+pub fn to_opaque_rgba8(format: Format, raw: &[u8]) -> Vec<u8> {
+    match format {
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => {
+            raw.chunks_exact(4).flat_map(|rgba| [rgba[0], rgba[1], rgba[2], 255]).collect()
+        }
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => {
+            raw.chunks_exact(4).flat_map(|bgra| [bgra[2], bgra[1], bgra[0], 255]).collect()
+        }
+        // 10 bits for each of red (lowest), green and blue, packed into a little endian u32
+        Format::A2B10G10R10UnormPack32 => raw
+            .chunks_exact(4)
+            .flat_map(|bytes| {
+                let packed = u32::from_le_bytes(bytes.try_into().unwrap());
+                let channel = |shift: u32| (((packed >> shift) & 0x3ff) * 255 + 511) / 1023;
+                [channel(0) as u8, channel(10) as u8, channel(20) as u8, 255]
+            })
+            .collect(),
+        _ => panic!("can't convert screenshots of {:?}", format),
+    }
+}
+
+/// A path in `dir` named after the current time (UTC), like
+/// `screenshot_2021-06-01_12-30-05-123.png`.
+pub fn timestamped_path(dir: &Path) -> PathBuf {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_date((seconds / 86_400) as i64);
+    let time = seconds % 86_400;
+    dir.join(format!(
+        "screenshot_{:04}-{:02}-{:02}_{:02}-{:02}-{:02}-{:03}.png",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        since_epoch.subsec_millis(),
+    ))
+}
+
+// the date `days` days after 1970-01-01, in the proleptic Gregorian calendar
+// (http://howardhinnant.github.io/date_algorithms.html#civil_from_days)
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // months starting in March, so the leap day is the last day of the year
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,46 @@
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, SecondaryAutoCommandBuffer};
+use vulkano::device::{DeviceOwned, Queue};
+use vulkano::render_pass::Subpass;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use rayon::prelude::*;
+use rayon::ThreadPool;
+
+/// Records one secondary command buffer per batch on the threads of `pool`, and returns them in
+/// the order of `batches`. The command buffers can be executed in `subpass`, after the render
+/// pass has been started with `SubpassContents::SecondaryCommandBuffers`.
+///
+/// Command buffer builders can't be moved between threads, as they allocate from the command
+/// pool of the thread which created them. So every buffer is started, recorded and built on
+/// the same worker, and the workers of the pool keep their command pools between frames.
+pub fn record_batches<T, F>(
+    pool: &ThreadPool,
+    queue: &Arc<Queue>,
+    subpass: &Subpass,
+    batches: &[T],
+    record: F,
+) -> Vec<SecondaryAutoCommandBuffer>
+where
+    T: Sync,
+    F: Fn(&mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>, &T) + Sync,
+{
+    pool.install(|| {
+        batches
+            .par_iter()
+            .map(|batch| {
+                let mut builder = AutoCommandBufferBuilder::secondary_graphics(
+                    queue.device().clone(),
+                    queue.family(),
+                    CommandBufferUsage::OneTimeSubmit,
+                    subpass.clone(),
+                )
+                .unwrap();
+
+                record(&mut builder, batch);
+                builder.build().unwrap()
+            })
+            .collect()
+    })
+}
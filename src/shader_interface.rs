@@ -0,0 +1,412 @@
+use vulkano::descriptor_set::layout::{DescriptorDesc, DescriptorType};
+use vulkano::format::Format;
+use vulkano::pipeline::shader::{EntryPointAbstract, GraphicsEntryPoint, ShaderInterface};
+
+use rspirv::dr::{self, Instruction, Operand};
+use rspirv::spirv::{Decoration, Dim, Op, StorageClass, Word};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// What a descriptor binding of a shader holds. Images are only compared by their descriptor
+/// type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Descriptor {
+    pub ty: DescriptorType,
+    pub array_count: u32,
+    pub readonly: bool,
+}
+
+impl From<&DescriptorDesc> for Descriptor {
+    fn from(desc: &DescriptorDesc) -> Descriptor {
+        Descriptor {
+            ty: desc.ty.ty(),
+            array_count: desc.array_count,
+            readonly: desc.readonly,
+        }
+    }
+}
+
+/// An input or output of a graphics shader, covering `locations` locations starting at
+/// `location`, each of them in `format`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attribute {
+    pub location: u32,
+    pub locations: u32,
+    pub format: Format,
+}
+
+/// The parts of a shader a reloaded module has to keep, because the pipeline layout and the
+/// neighbouring stages are built from the entry points `vulkano_shaders` generated: descriptors
+/// by set and binding, the size of the push constants and the inputs and outputs.
+///
+/// The layout of the members of buffers isn't part of it, vulkano doesn't describe them.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Interface {
+    pub descriptors: BTreeMap<(u32, u32), Descriptor>,
+    pub push_constants: Option<usize>,
+    pub inputs: Vec<Attribute>,
+    pub outputs: Vec<Attribute>,
+}
+
+impl Interface {
+    /// The interface of a graphics entry point generated by `vulkano_shaders`.
+    pub fn of_graphics(entry_point: &GraphicsEntryPoint) -> Interface {
+        Interface {
+            inputs: attributes(entry_point.input()),
+            outputs: attributes(entry_point.output()),
+            ..Interface::of_entry_point(entry_point)
+        }
+    }
+
+    /// The interface of a compute entry point, or of the descriptors and push constants of any
+    /// other entry point.
+    pub fn of_entry_point<E: EntryPointAbstract>(entry_point: &E) -> Interface {
+        let mut descriptors = BTreeMap::new();
+        for (set, desc) in entry_point.descriptor_set_layout_descs().iter().enumerate() {
+            for (binding, desc) in desc.bindings().iter().enumerate() {
+                if let Some(desc) = desc {
+                    descriptors.insert((set as u32, binding as u32), Descriptor::from(desc));
+                }
+            }
+        }
+        Interface {
+            descriptors,
+            push_constants: entry_point.push_constant_range().as_ref().map(|range| range.size),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Reads the interface of the first entry point of a SPIR-V module. Descriptors are taken
+    /// from the whole module, like `vulkano_shaders` does.
+    pub fn reflect(words: &[u32]) -> Result<Interface, String> {
+        let module = dr::load_words(words).map_err(|err| err.to_string())?;
+        Reflection::new(&module).interface()
+    }
+
+    /// Describes the first difference of `self` to `expected`, if there is one.
+    pub fn compare(&self, expected: &Interface) -> Result<(), String> {
+        let bindings: BTreeSet<_> = self.descriptors.keys().chain(expected.descriptors.keys()).collect();
+        for &(set, binding) in bindings {
+            let (actual, wanted) = (self.descriptors.get(&(set, binding)), expected.descriptors.get(&(set, binding)));
+            if actual != wanted {
+                return Err(format!(
+                    "set {} binding {} is {}, expected {}",
+                    set,
+                    binding,
+                    describe(actual),
+                    describe(wanted)
+                ));
+            }
+        }
+
+        if self.push_constants != expected.push_constants {
+            let size = |size: Option<usize>| size.map_or("none".to_string(), |size| format!("{} bytes", size));
+            return Err(format!(
+                "the push constants are {}, expected {}",
+                size(self.push_constants),
+                size(expected.push_constants)
+            ));
+        }
+
+        let attributes = [("inputs", &self.inputs, &expected.inputs), ("outputs", &self.outputs, &expected.outputs)];
+        for (kind, actual, wanted) in attributes.iter() {
+            if actual != wanted {
+                return Err(format!("the {} are {}, expected {}", kind, list(actual), list(wanted)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn attributes(interface: &ShaderInterface) -> Vec<Attribute> {
+    let mut attributes: Vec<Attribute> = interface
+        .elements()
+        .iter()
+        .map(|element| Attribute {
+            location: element.location.start,
+            locations: element.location.end - element.location.start,
+            format: element.format,
+        })
+        .collect();
+    attributes.sort_by_key(|attribute| attribute.location);
+    attributes
+}
+
+fn describe(descriptor: Option<&Descriptor>) -> String {
+    match descriptor {
+        Some(descriptor) => {
+            let mut description = format!("{:?}", descriptor.ty);
+            if descriptor.array_count != 1 {
+                description += &format!("[{}]", descriptor.array_count);
+            }
+            if !descriptor.readonly {
+                description += " (written)";
+            }
+            description
+        }
+        None => "unused".to_string(),
+    }
+}
+
+fn list(attributes: &[Attribute]) -> String {
+    let attributes: Vec<String> = attributes
+        .iter()
+        .map(Attribute::to_string)
+        .collect();
+    format!("[{}]", attributes.join(", "))
+}
+
+/// The types, variables and decorations of a SPIR-V module parsed by rspirv, looked up by id.
+struct Reflection<'a> {
+    module: &'a dr::Module,
+    definitions: HashMap<Word, &'a Instruction>,
+}
+
+impl<'a> Reflection<'a> {
+    fn new(module: &'a dr::Module) -> Reflection<'a> {
+        let definitions = module
+            .types_global_values
+            .iter()
+            .filter_map(|instruction| Some((instruction.result_id?, instruction)))
+            .collect();
+        Reflection { module, definitions }
+    }
+
+    fn interface(&self) -> Result<Interface, String> {
+        let mut interface = Interface::default();
+
+        for variable in self.instructions(Op::Variable) {
+            let id = variable.result_id.unwrap_or(0);
+            let storage = match variable.operands.first() {
+                Some(Operand::StorageClass(storage)) => *storage,
+                _ => return Err(format!("variable %{} has no storage class", id)),
+            };
+            let pointee = self.pointee(variable)?;
+
+            if let Some(set) = self.decoration(id, Decoration::DescriptorSet) {
+                let binding = self
+                    .decoration(id, Decoration::Binding)
+                    .ok_or_else(|| format!("descriptor %{} has no binding", id))?;
+                let mut descriptor = self.descriptor(pointee, storage)?;
+                descriptor.readonly |= self.decoration(id, Decoration::NonWritable).is_some();
+                interface.descriptors.insert((set, binding), descriptor);
+            } else if storage == StorageClass::PushConstant {
+                // like for `vulkano_shaders`, the push constants are as large as the largest block
+                let size = self.size(pointee)?;
+                interface.push_constants = Some(interface.push_constants.unwrap_or(0).max(size));
+            }
+        }
+
+        // the variables the entry point uses follow its execution model, function and name
+        let entry_point = self.module.entry_points.first().ok_or("the module has no entry point")?;
+        for operand in entry_point.operands.iter().skip(3) {
+            let variable = match operand {
+                Operand::IdRef(id) => self.definition(*id)?,
+                _ => continue,
+            };
+            let id = variable.result_id.unwrap_or(0);
+            let pointee = self.pointee(variable)?;
+            if self.is_built_in(id, pointee) {
+                continue;
+            }
+            let location = self
+                .decoration(id, Decoration::Location)
+                .ok_or_else(|| format!("interface variable %{} has no location", id))?;
+            let (format, locations) = self.format(pointee)?;
+            let attribute = Attribute { location, locations, format };
+            match variable.operands.first() {
+                Some(Operand::StorageClass(StorageClass::Input)) => interface.inputs.push(attribute),
+                Some(Operand::StorageClass(StorageClass::Output)) => interface.outputs.push(attribute),
+                _ => (),
+            }
+        }
+        interface.inputs.sort_by_key(|attribute| attribute.location);
+        interface.outputs.sort_by_key(|attribute| attribute.location);
+
+        Ok(interface)
+    }
+
+    fn instructions(&self, opcode: Op) -> impl Iterator<Item = &'a Instruction> {
+        self.module
+            .types_global_values
+            .iter()
+            .filter(move |instruction| instruction.class.opcode == opcode)
+    }
+
+    fn definition(&self, id: Word) -> Result<&'a Instruction, String> {
+        self.definitions.get(&id).cloned().ok_or_else(|| format!("unknown id %{}", id))
+    }
+
+    // the operand at `index`, which is an id or a 32 bit literal
+    fn word(&self, instruction: &Instruction, index: usize) -> Result<Word, String> {
+        match instruction.operands.get(index) {
+            Some(Operand::IdRef(word)) | Some(Operand::LiteralInt32(word)) => Ok(*word),
+            _ => Err(format!("operand {} of %{} is missing", index, instruction.result_id.unwrap_or(0))),
+        }
+    }
+
+    // the type a variable points to
+    fn pointee(&self, variable: &Instruction) -> Result<Word, String> {
+        let pointer = self.definition(variable.result_type.unwrap_or(0))?;
+        match pointer.class.opcode {
+            Op::TypePointer => self.word(pointer, 1),
+            _ => Err(format!("%{} isn't a pointer", variable.result_id.unwrap_or(0))),
+        }
+    }
+
+    // the first literal of `decoration` on `target`, or 0 for decorations without one
+    fn decoration(&self, target: Word, decoration: Decoration) -> Option<u32> {
+        self.module.annotations.iter().find_map(|instruction| match instruction.operands.as_slice() {
+            [Operand::IdRef(id), Operand::Decoration(found), literals @ ..]
+                if instruction.class.opcode == Op::Decorate && *id == target && *found == decoration =>
+            {
+                Some(literal(literals))
+            }
+            _ => None,
+        })
+    }
+
+    fn member_decoration(&self, target: Word, member: u32, decoration: Decoration) -> Option<u32> {
+        self.module.annotations.iter().find_map(|instruction| match instruction.operands.as_slice() {
+            [Operand::IdRef(id), Operand::LiteralInt32(index), Operand::Decoration(found), literals @ ..]
+                if instruction.class.opcode == Op::MemberDecorate
+                    && *id == target
+                    && *index == member
+                    && *found == decoration =>
+            {
+                Some(literal(literals))
+            }
+            _ => None,
+        })
+    }
+
+    fn constant(&self, id: Word) -> Result<u32, String> {
+        let constant = self.definition(id)?;
+        match constant.class.opcode {
+            Op::Constant => self.word(constant, 0),
+            _ => Err(format!("%{} isn't a constant", id)),
+        }
+    }
+
+    // the same rules as `vulkano_shaders` uses to describe the descriptors of its entry points
+    fn descriptor(&self, ty: Word, storage: StorageClass) -> Result<Descriptor, String> {
+        let definition = self.definition(ty)?;
+        let simple = |ty: DescriptorType, readonly: bool| Descriptor {
+            ty,
+            array_count: 1,
+            readonly,
+        };
+        Ok(match definition.class.opcode {
+            Op::TypeStruct => {
+                let buffer_block = self.decoration(ty, Decoration::BufferBlock).is_some();
+                let nonwritable = (0..definition.operands.len() as u32)
+                    .all(|member| self.member_decoration(ty, member, Decoration::NonWritable).is_some());
+                if buffer_block || storage == StorageClass::StorageBuffer {
+                    simple(DescriptorType::StorageBuffer, nonwritable)
+                } else {
+                    simple(DescriptorType::UniformBuffer, true)
+                }
+            }
+            Op::TypeImage => {
+                // sampled is 1 for sampled images and 2 for storage images
+                let storage = self.word(definition, 5)? != 1;
+                match definition.operands.get(1) {
+                    Some(Operand::Dim(Dim::DimSubpassData)) => simple(DescriptorType::InputAttachment, true),
+                    Some(Operand::Dim(Dim::DimBuffer)) if storage => simple(DescriptorType::StorageTexelBuffer, false),
+                    Some(Operand::Dim(Dim::DimBuffer)) => simple(DescriptorType::UniformTexelBuffer, true),
+                    _ if storage => simple(DescriptorType::StorageImage, false),
+                    _ => simple(DescriptorType::SampledImage, true),
+                }
+            }
+            Op::TypeSampledImage => simple(DescriptorType::CombinedImageSampler, true),
+            Op::TypeSampler => simple(DescriptorType::Sampler, true),
+            Op::TypeArray => Descriptor {
+                array_count: self.constant(self.word(definition, 1)?)?,
+                ..self.descriptor(self.word(definition, 0)?, storage)?
+            },
+            _ => return Err(format!("unsupported descriptor type %{}", ty)),
+        })
+    }
+
+    fn is_built_in(&self, variable: Word, ty: Word) -> bool {
+        if self.decoration(variable, Decoration::BuiltIn).is_some() {
+            return true;
+        }
+        match self.definitions.get(&ty) {
+            Some(definition) if definition.class.opcode == Op::TypeStruct => (0..definition.operands.len() as u32)
+                .any(|member| self.member_decoration(ty, member, Decoration::BuiltIn).is_some()),
+            _ => false,
+        }
+    }
+
+    // the size `vulkano_shaders` gives the type in push constants: arrays are as long as their
+    // elements, structs end after their last member
+    fn size(&self, ty: Word) -> Result<usize, String> {
+        let definition = self.definition(ty)?;
+        let word = |index| self.word(definition, index);
+        Ok(match definition.class.opcode {
+            Op::TypeInt | Op::TypeFloat => word(0)? as usize / 8,
+            Op::TypeVector | Op::TypeMatrix => self.size(word(0)?)? * word(1)? as usize,
+            Op::TypeArray => self.size(word(0)?)? * self.constant(word(1)?)? as usize,
+            Op::TypeStruct => {
+                let mut end = 0;
+                for member in 0..definition.operands.len() {
+                    let offset = self
+                        .member_decoration(ty, member as u32, Decoration::Offset)
+                        .ok_or_else(|| format!("member {} of %{} has no offset", member, ty))?;
+                    end = end.max(offset as usize + self.size(word(member)?)?);
+                }
+                end
+            }
+            _ => return Err(format!("type %{} has no fixed size", ty)),
+        })
+    }
+
+    // the format of each location of an input or output, and the number of locations
+    fn format(&self, ty: Word) -> Result<(Format, u32), String> {
+        let definition = self.definition(ty)?;
+        let word = |index| self.word(definition, index);
+        let unsupported = || format!("unsupported input or output type %{}", ty);
+        match definition.class.opcode {
+            Op::TypeInt | Op::TypeFloat => Ok((self.component_format(ty, 1).ok_or_else(unsupported)?, 1)),
+            Op::TypeVector => Ok((self.component_format(word(0)?, word(1)?).ok_or_else(unsupported)?, 1)),
+            Op::TypeMatrix => {
+                let (format, locations) = self.format(word(0)?)?;
+                Ok((format, locations * word(1)?))
+            }
+            Op::TypeArray => {
+                let (format, locations) = self.format(word(0)?)?;
+                Ok((format, locations * self.constant(word(1)?)?))
+            }
+            _ => Err(unsupported()),
+        }
+    }
+
+    fn component_format(&self, component: Word, count: u32) -> Option<Format> {
+        let definition = self.definitions.get(&component)?;
+        let (width, signed) = (self.word(definition, 0).ok()?, self.word(definition, 1).ok());
+        let formats = match (definition.class.opcode, width, signed) {
+            (Op::TypeFloat, 32, _) => [Format::R32Sfloat, Format::R32G32Sfloat, Format::R32G32B32Sfloat, Format::R32G32B32A32Sfloat],
+            (Op::TypeInt, 32, Some(1)) => [Format::R32Sint, Format::R32G32Sint, Format::R32G32B32Sint, Format::R32G32B32A32Sint],
+            (Op::TypeInt, 32, Some(0)) => [Format::R32Uint, Format::R32G32Uint, Format::R32G32B32Uint, Format::R32G32B32A32Uint],
+            _ => return None,
+        };
+        formats.get(count.checked_sub(1)? as usize).cloned()
+    }
+}
+
+// the first literal of a decoration, 0 if it has none
+fn literal(literals: &[Operand]) -> u32 {
+    match literals.first() {
+        Some(Operand::LiteralInt32(literal)) => *literal,
+        _ => 0,
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?} at {}", self.format, self.location)
+    }
+}
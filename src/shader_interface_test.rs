@@ -0,0 +1,97 @@
+use vulkano::descriptor_set::layout::DescriptorType;
+use vulkano::format::Format;
+
+use shaderc::ShaderKind;
+
+use crate::shader_interface::{Attribute, Descriptor, Interface};
+
+const VERTEX: &str = include_str!("../shaders/triangle.vert");
+const COMPUTE: &str = include_str!("../shaders/particles.comp");
+
+fn reflect(source: &str, kind: ShaderKind) -> Interface {
+    let mut compiler = shaderc::Compiler::new().expect("failed to create shader compiler");
+    let artifact = compiler.compile_into_spirv(source, kind, "test", "main", None).unwrap();
+    Interface::reflect(artifact.as_binary()).unwrap()
+}
+
+// the first difference of `source` to the interface of `expected`
+fn compare(source: &str, kind: ShaderKind, expected: &Interface) -> Result<(), String> {
+    reflect(source, kind).compare(expected)
+}
+
+/// Checks the interfaces read from the reloadable shaders, and that edits changing them are
+/// found. Like the input test, this doesn't need the gpu.
+pub fn execute() {
+    println!("performing shader interface test");
+
+    let buffer = |ty, readonly| Descriptor {
+        ty,
+        array_count: 1,
+        readonly,
+    };
+
+    let vertex = reflect(VERTEX, ShaderKind::Vertex);
+    assert_eq!(
+        vertex,
+        Interface {
+            descriptors: vec![((0, 0), buffer(DescriptorType::UniformBuffer, true))].into_iter().collect(),
+            push_constants: None,
+            inputs: vec![Attribute { location: 0, locations: 1, format: Format::R32G32Sfloat }],
+            outputs: vec![Attribute { location: 0, locations: 1, format: Format::R32Sfloat }],
+        }
+    );
+
+    let compute = reflect(COMPUTE, ShaderKind::Compute);
+    assert_eq!(
+        compute,
+        Interface {
+            descriptors: vec![((0, 0), buffer(DescriptorType::StorageBuffer, false))].into_iter().collect(),
+            push_constants: Some(16),
+            ..Interface::default()
+        }
+    );
+
+    // a truncated module is an error, the first instruction after the header is cut off
+    let mut compiler = shaderc::Compiler::new().expect("failed to create shader compiler");
+    let artifact = compiler.compile_into_spirv(VERTEX, ShaderKind::Vertex, "test", "main", None).unwrap();
+    assert!(Interface::reflect(&artifact.as_binary()[..6]).is_err());
+
+    // changes to the body are fine
+    let brighter = VERTEX.replace("0.75 + 0.25", "0.9 + 0.1");
+    assert_eq!(compare(&brighter, ShaderKind::Vertex, &vertex), Ok(()));
+
+    let changes = [
+        (
+            VERTEX.replace("binding = 0", "binding = 1"),
+            "set 0 binding 0 is unused, expected UniformBuffer",
+        ),
+        (
+            VERTEX.replace("out float v_brightness", "out vec2 v_brightness").replace("v_brightness = ", "v_brightness.x = "),
+            "the outputs are [R32G32Sfloat at 0], expected [R32Sfloat at 0]",
+        ),
+        (
+            VERTEX.replace("layout(location = 0) in vec2", "layout(location = 1) in vec2"),
+            "the inputs are [R32G32Sfloat at 1], expected [R32G32Sfloat at 0]",
+        ),
+    ];
+    for (source, expected) in changes.iter() {
+        assert_eq!(compare(source, ShaderKind::Vertex, &vertex), Err(expected.to_string()));
+    }
+
+    let changes = [
+        (
+            COMPUTE.replace("    uint particle_count;", "    uint particle_count;\n    float drag;"),
+            "the push constants are 20 bytes, expected 16 bytes",
+        ),
+        (
+            COMPUTE.replace("layout(set = 0, binding = 0) buffer", "layout(set = 0, binding = 0) readonly buffer")
+                .replace("    particles.data[idx] = particle;\n", ""),
+            "set 0 binding 0 is StorageBuffer, expected StorageBuffer (written)",
+        ),
+    ];
+    for (source, expected) in changes.iter() {
+        assert_eq!(compare(source, ShaderKind::Compute, &compute), Err(expected.to_string()));
+    }
+
+    println!("shader interface test successful!");
+}
@@ -0,0 +1,214 @@
+use vulkano::device::Device;
+use vulkano::pipeline::ComputePipeline;
+use vulkano::pipeline::ComputePipelineCreationError;
+use vulkano::pipeline::GraphicsPipelineCreationError;
+use vulkano::pipeline::shader::{ComputeEntryPoint, EntryPointAbstract, GraphicsEntryPoint, ShaderModule};
+use vulkano::OomError;
+
+use winit::window::Window;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use std::ffi::CStr;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use shaderc::ShaderKind;
+
+use crate::pipeline_cache;
+use crate::shader_interface::Interface;
+
+// the name of the entry point of every shader in this program
+const MAIN: &[u8] = b"main\0";
+
+#[derive(Debug)]
+pub enum ShaderError {
+    UnknownStage(PathBuf),
+    Read(PathBuf, io::Error),
+    Compile(shaderc::Error),
+    Interface(PathBuf, String),
+    Module(OomError),
+    GraphicsPipeline(GraphicsPipelineCreationError),
+    ComputePipeline(ComputePipelineCreationError),
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShaderError::UnknownStage(path) => write!(f, "unknown shader stage: {}", path.display()),
+            ShaderError::Read(path, err) => write!(f, "failed to read {}: {}", path.display(), err),
+            ShaderError::Compile(err) => write!(f, "failed to compile shader: {}", err),
+            ShaderError::Interface(path, err) => {
+                write!(f, "{} doesn't match the shader compiled into the program: {}", path.display(), err)
+            }
+            ShaderError::Module(err) => write!(f, "failed to create shader module: {}", err),
+            ShaderError::GraphicsPipeline(err) => write!(f, "failed to create graphics pipeline: {}", err),
+            ShaderError::ComputePipeline(err) => write!(f, "failed to create compute pipeline: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// A GLSL file which is compiled at runtime, and recompiled whenever it changes on disk.
+///
+/// Changes are found by comparing the modification time of the file every time it is polled,
+/// which is cheap enough to do once per frame.
+pub struct WatchedShader {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl WatchedShader {
+    /// Watches the file at `path`. The shader stage is chosen by the file extension (`.vert`,
+    /// `.frag` or `.comp`).
+    pub fn new<P: Into<PathBuf>>(path: P) -> WatchedShader {
+        WatchedShader {
+            path: path.into(),
+            modified: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file has been modified since the last poll. The first poll of an existing
+    /// file returns true, so it replaces the shader which was compiled into the program.
+    pub fn poll(&mut self) -> bool {
+        // a file which can't be read (yet) is checked again on the next poll
+        let modified = match std::fs::metadata(&self.path).and_then(|metadata| metadata.modified()) {
+            Ok(modified) => Some(modified),
+            Err(_) => return false,
+        };
+
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+
+    /// Compiles the current content of the file into a shader module, if it has the
+    /// `expected` interface.
+    pub fn compile(&self, device: Arc<Device>, expected: &Interface) -> Result<Arc<ShaderModule>, ShaderError> {
+        let kind = match self.path.extension().and_then(|extension| extension.to_str()) {
+            Some("vert") => ShaderKind::Vertex,
+            Some("frag") => ShaderKind::Fragment,
+            Some("comp") => ShaderKind::Compute,
+            _ => return Err(ShaderError::UnknownStage(self.path.clone())),
+        };
+
+        let source = std::fs::read_to_string(&self.path)
+            .map_err(|err| ShaderError::Read(self.path.clone(), err))?;
+
+        let mut compiler = shaderc::Compiler::new().expect("failed to create shader compiler");
+        let artifact = compiler
+            .compile_into_spirv(&source, kind, &self.path.to_string_lossy(), "main", None)
+            .map_err(ShaderError::Compile)?;
+
+        let interface_error = |err| ShaderError::Interface(self.path.clone(), err);
+        Interface::reflect(artifact.as_binary())
+            .and_then(|interface| interface.compare(expected))
+            .map_err(interface_error)?;
+
+        // shaderc only produces valid SPIR-V
+        unsafe { ShaderModule::from_words(device, artifact.as_binary()).map_err(ShaderError::Module) }
+    }
+}
+
+/// The `main` entry point of `module`, with the interface of `reference`.
+///
+/// vulkano doesn't describe shaders compiled at runtime, so the description is taken from the
+/// shader which was compiled into the program. `WatchedShader::compile` only creates modules
+/// with the same inputs, outputs, descriptors and push constants. Specialization constants keep
+/// their default values, describing them would need a `'static` description.
+fn graphics_entry_point<'a>(module: &'a ShaderModule, reference: &GraphicsEntryPoint) -> GraphicsEntryPoint<'a> {
+    unsafe {
+        module.graphics_entry_point(
+            CStr::from_bytes_with_nul_unchecked(MAIN),
+            reference.descriptor_set_layout_descs().iter().cloned(),
+            reference.push_constant_range().clone(),
+            &[],
+            reference.input().clone(),
+            reference.output().clone(),
+            reference.ty(),
+        )
+    }
+}
+
+/// Like `graphics_entry_point`, for compute shaders.
+fn compute_entry_point<'a>(module: &'a ShaderModule, reference: &ComputeEntryPoint) -> ComputeEntryPoint<'a> {
+    unsafe {
+        module.compute_entry_point(
+            CStr::from_bytes_with_nul_unchecked(MAIN),
+            reference.descriptor_set_layout_descs().iter().cloned(),
+            reference.push_constant_range().clone(),
+            &[],
+        )
+    }
+}
+
+/// Compiles `vertex` and `fragment` and passes them to `build`, which creates the pipeline.
+///
+/// The shaders must have the same interface as `reference_vertex` and `reference_fragment`,
+/// the entry points of the shaders compiled into the program, otherwise they are rejected.
+pub fn graphics_pipeline<P, F>(
+    device: Arc<Device>,
+    vertex: &WatchedShader,
+    fragment: &WatchedShader,
+    reference_vertex: &GraphicsEntryPoint,
+    reference_fragment: &GraphicsEntryPoint,
+    build: F,
+) -> Result<P, ShaderError>
+where
+    F: FnOnce(GraphicsEntryPoint, GraphicsEntryPoint) -> Result<P, GraphicsPipelineCreationError>,
+{
+    let vertex_module = vertex.compile(device.clone(), &Interface::of_graphics(reference_vertex))?;
+    let fragment_module = fragment.compile(device, &Interface::of_graphics(reference_fragment))?;
+
+    // the pipeline keeps what it needs, the modules can be dropped once it has been built
+    build(
+        graphics_entry_point(&vertex_module, reference_vertex),
+        graphics_entry_point(&fragment_module, reference_fragment),
+    )
+    .map_err(ShaderError::GraphicsPipeline)
+}
+
+/// Compiles `shader` into a compute pipeline. The shader must have the same interface as
+/// `reference`, the entry point of the shader compiled into the program, otherwise it is
+/// rejected.
+pub fn compute_pipeline(
+    device: Arc<Device>,
+    shader: &WatchedShader,
+    reference: &ComputeEntryPoint,
+) -> Result<Arc<ComputePipeline>, ShaderError> {
+    let module = shader.compile(device.clone(), &Interface::of_entry_point(reference))?;
+    let cache = pipeline_cache::get(&device);
+    let pipeline = ComputePipeline::new(device, &compute_entry_point(&module, reference), &(), Some(cache))
+        .map_err(ShaderError::ComputePipeline)?;
+    Ok(Arc::new(pipeline))
+}
+
+/// Prints the outcome of reloading `shaders`. Errors are also shown in the title of `window`
+/// until the next successful reload, as the console may not be visible.
+pub fn report(window: &Window, shaders: &[&WatchedShader], result: &Result<(), ShaderError>) {
+    let names: Vec<String> = shaders
+        .iter()
+        .map(|shader| shader.path().display().to_string())
+        .collect();
+
+    match result {
+        Ok(()) => {
+            println!("reloaded {}", names.join(", "));
+            window.set_title("rust-vulkan");
+        }
+        Err(err) => {
+            eprintln!("failed to reload {}: {}", names.join(", "), err);
+            // compiler errors span several lines, the first one is the most relevant
+            let first_line = err.to_string().lines().next().unwrap_or_default().to_string();
+            window.set_title(&format!("shader error: {}", first_line));
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use vulkano::format::Format;
+use vulkano::swapchain::{Capabilities, ColorSpace, CompositeAlpha, PresentMode};
+
+use std::fmt;
+
+/// What the windowed samples would like their swapchain to be, in order of preference. What is
+/// actually used is negotiated against the capabilities of the surface with `negotiate`.
+#[derive(Debug, Clone)]
+pub struct SwapchainOptions {
+    // the first supported one is used, fifo if none is supported
+    pub present_modes: Vec<PresentMode>,
+    // formats with the sRGB color space, the first supported one is used
+    pub formats: Vec<Format>,
+    // clamped to the image counts the surface supports
+    pub image_count: u32,
+}
+
+impl Default for SwapchainOptions {
+    fn default() -> SwapchainOptions {
+        SwapchainOptions {
+            // mailbox doesn't block and doesn't tear, fifo is always supported
+            present_modes: vec![PresentMode::Mailbox, PresentMode::Fifo],
+            formats: vec![Format::B8G8R8A8Srgb, Format::R8G8B8A8Srgb],
+            // triple buffering, so one image can be drawn while one is shown and one waits
+            image_count: 3,
+        }
+    }
+}
+
+/// The configuration the swapchain is created with.
+#[derive(Debug, Clone, Copy)]
+pub struct SwapchainConfig {
+    pub present_mode: PresentMode,
+    pub format: Format,
+    pub color_space: ColorSpace,
+    pub image_count: u32,
+    pub composite_alpha: CompositeAlpha,
+}
+
+impl fmt::Display for SwapchainConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} with {} images of {:?} ({:?}), {:?} alpha",
+            self.present_mode, self.image_count, self.format, self.color_space, self.composite_alpha,
+        )
+    }
+}
+
+/// Chooses the configuration closest to `options` which the surface supports. Preferences which
+/// can't be met are printed along with what is used instead.
+pub fn negotiate(caps: &Capabilities, options: &SwapchainOptions) -> SwapchainConfig {
+    let present_mode = options
+        .present_modes
+        .iter()
+        .copied()
+        .find(|&mode| caps.present_modes.supports(mode))
+        .unwrap_or(PresentMode::Fifo);
+    if options.present_modes.first() != Some(&present_mode) {
+        println!(
+            "present modes {:?} not all supported (supported: {:?}), using {:?}",
+            options.present_modes,
+            caps.present_modes.iter().collect::<Vec<_>>(),
+            present_mode,
+        );
+    }
+
+    // every surface supports at least one format
+    let (format, color_space) = options
+        .formats
+        .iter()
+        .find_map(|&format| {
+            caps.supported_formats
+                .iter()
+                .copied()
+                .find(|&supported| supported == (format, ColorSpace::SrgbNonLinear))
+        })
+        .unwrap_or(caps.supported_formats[0]);
+    if options.formats.first() != Some(&format) {
+        println!(
+            "formats {:?} not all supported (supported: {:?}), using {:?}",
+            options.formats, caps.supported_formats, format,
+        );
+    }
+
+    // without a maximum, any number of images above the minimum is supported
+    let max_image_count = caps.max_image_count.unwrap_or(u32::MAX);
+    let image_count = options.image_count.max(caps.min_image_count).min(max_image_count);
+    if image_count != options.image_count {
+        println!(
+            "{} images not supported (supported: {} to {:?}), using {}",
+            options.image_count, caps.min_image_count, caps.max_image_count, image_count,
+        );
+    }
+
+    let composite_alpha = if caps.supported_composite_alpha.supports(CompositeAlpha::Opaque) {
+        CompositeAlpha::Opaque
+    } else {
+        caps.supported_composite_alpha.iter().next().unwrap()
+    };
+
+    SwapchainConfig {
+        present_mode,
+        format,
+        color_space,
+        image_count,
+        composite_alpha,
+    }
+}
+
+/// The present mode named `name` on the command line.
+pub fn parse_present_mode(name: &str) -> Option<PresentMode> {
+    match name {
+        "immediate" => Some(PresentMode::Immediate),
+        "mailbox" => Some(PresentMode::Mailbox),
+        "fifo" => Some(PresentMode::Fifo),
+        "relaxed" => Some(PresentMode::Relaxed),
+        _ => None,
+    }
+}
+
+/// The swapchain format named `name` on the command line.
+pub fn parse_format(name: &str) -> Option<Format> {
+    match name {
+        "bgra8-srgb" => Some(Format::B8G8R8A8Srgb),
+        "rgba8-srgb" => Some(Format::R8G8B8A8Srgb),
+        "bgra8-unorm" => Some(Format::B8G8R8A8Unorm),
+        "rgba8-unorm" => Some(Format::R8G8B8A8Unorm),
+        "a2b10g10r10-unorm" => Some(Format::A2B10G10R10UnormPack32),
+        _ => None,
+    }
+}
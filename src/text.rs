@@ -0,0 +1,448 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::{ImageDimensions, ImmutableImage, MipmapsCount};
+use vulkano::image::view::ImageView;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::render_pass::Subpass;
+use vulkano::sampler::{Filter, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use rusttype::{point, Font, Scale};
+
+use crate::pipeline_cache;
+use crate::texture::SamplerOptions;
+
+/// The font the overlays are drawn with when no other one is given with `--font`.
+pub const DEFAULT_FONT: &[u8] = include_bytes!("../assets/DejaVuSansMono.ttf");
+
+/// Height of the text of the overlays, in pixels.
+pub const DEFAULT_SIZE: f32 = 16.0;
+
+// the characters in the atlas, the printable ASCII ones, others are drawn as `REPLACEMENT`
+const FIRST_CHAR: char = ' ';
+const LAST_CHAR: char = '~';
+const REPLACEMENT: char = '?';
+
+// texels left empty between the glyphs in the atlas
+const PADDING: u32 = 1;
+// size of the fully covered block in the top left corner of the atlas, rectangles are drawn with it
+const SOLID: u32 = 2;
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec2 position;
+layout(location=1) in vec2 uv;
+layout(location=2) in vec4 color;
+
+layout(location=0) out vec2 v_uv;
+layout(location=1) out vec4 v_color;
+
+// the positions are in pixels from the top left corner of the viewport
+layout(push_constant) uniform PushConstants {
+    vec2 viewport;
+} push_constants;
+
+void main() {
+    v_uv = uv;
+    v_color = color;
+    gl_Position = vec4(position / push_constants.viewport * 2.0 - 1.0, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec4 v_color;
+layout(location=0) out vec4 f_color;
+
+// how much of each texel is covered by a glyph
+layout(set=0, binding=0) uniform sampler2D atlas;
+
+void main() {
+    f_color = vec4(v_color.rgb, v_color.a * texture(atlas, v_uv).r);
+}
+"
+    }
+}
+
+// where a glyph is in the atlas and how it is placed relative to the pen
+#[derive(Debug, Clone, Copy, Default)]
+struct Glyph {
+    // corners of the bitmap in the atlas, in texels, equal for glyphs without one (like the space)
+    min: [u32; 2],
+    max: [u32; 2],
+    // from the pen on the baseline to the top left corner of the bitmap, in pixels
+    offset: [f32; 2],
+    // how far the pen moves on after the glyph, in pixels
+    advance: f32,
+}
+
+/// A glyph placed by `GlyphAtlas::layout`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphQuad {
+    // corners in the viewport, in pixels from its top left corner
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    // corners in the atlas, in texels
+    pub atlas_min: [u32; 2],
+    pub atlas_max: [u32; 2],
+}
+
+/// The printable ASCII characters of a font, rasterized once at a fixed size into a single
+/// channel bitmap with the coverage of every texel.
+///
+/// The glyphs are packed into rows, and drawn with their texels mapped to whole pixels, so they
+/// are as sharp as they were rasterized.
+pub struct GlyphAtlas {
+    pixels: Vec<u8>,
+    dimensions: [u32; 2],
+    glyphs: Vec<Glyph>,
+    // from the top of a line to its baseline
+    ascent: f32,
+    // from one baseline to the next
+    line_height: f32,
+}
+
+impl GlyphAtlas {
+    /// Rasterizes the TrueType (or OpenType) font in `bytes` with a height of `size` pixels.
+    /// Returns `None` if `bytes` is not a font.
+    pub fn new(bytes: &[u8], size: f32) -> Option<GlyphAtlas> {
+        let font = Font::try_from_bytes(bytes)?;
+        let scale = Scale::uniform(size);
+        let v_metrics = font.v_metrics(scale);
+
+        // every bitmap is kept with the position of its top left corner relative to the pen
+        let rasterized: Vec<_> = (FIRST_CHAR..=LAST_CHAR)
+            .map(|c| {
+                let glyph = font.glyph(c).scaled(scale);
+                let advance = glyph.h_metrics().advance_width;
+                let glyph = glyph.positioned(point(0.0, 0.0));
+                let bitmap = glyph.pixel_bounding_box().map(|bounds| {
+                    let [width, height] = [bounds.width() as u32, bounds.height() as u32];
+                    let mut coverage = vec![0u8; (width * height) as usize];
+                    glyph.draw(|x, y, value| {
+                        coverage[(y * width + x) as usize] = (value.min(1.0) * 255.0).round() as u8;
+                    });
+                    ([bounds.min.x as f32, bounds.min.y as f32], [width, height], coverage)
+                });
+                (advance, bitmap)
+            })
+            .collect();
+
+        // wide enough for 16 glyphs a row, none of them is wider than the font is high
+        let width = (size.ceil() as u32 * 16).next_power_of_two();
+        let mut cursor = [SOLID + PADDING, 0];
+        let mut row_height = SOLID;
+        let glyphs: Vec<Glyph> = rasterized
+            .iter()
+            .map(|(advance, bitmap)| {
+                let mut glyph = Glyph {
+                    advance: *advance,
+                    ..Glyph::default()
+                };
+                if let Some((offset, [glyph_width, glyph_height], _)) = bitmap {
+                    if cursor[0] + glyph_width > width {
+                        cursor = [0, cursor[1] + row_height + PADDING];
+                        row_height = 0;
+                    }
+                    glyph.min = cursor;
+                    glyph.max = [cursor[0] + glyph_width, cursor[1] + glyph_height];
+                    glyph.offset = *offset;
+                    cursor[0] += glyph_width + PADDING;
+                    row_height = row_height.max(*glyph_height);
+                }
+                glyph
+            })
+            .collect();
+
+        let dimensions = [width, cursor[1] + row_height];
+        let mut pixels = vec![0u8; (dimensions[0] * dimensions[1]) as usize];
+        for y in 0..SOLID {
+            for x in 0..SOLID {
+                pixels[(y * width + x) as usize] = 255;
+            }
+        }
+        for (glyph, (_, bitmap)) in glyphs.iter().zip(&rasterized) {
+            if let Some((_, [glyph_width, _], coverage)) = bitmap {
+                for (y, row) in coverage.chunks_exact(*glyph_width as usize).enumerate() {
+                    let start = ((glyph.min[1] + y as u32) * width + glyph.min[0]) as usize;
+                    pixels[start..start + row.len()].copy_from_slice(row);
+                }
+            }
+        }
+
+        Some(GlyphAtlas {
+            pixels,
+            dimensions,
+            glyphs,
+            ascent: v_metrics.ascent,
+            line_height: v_metrics.ascent - v_metrics.descent + v_metrics.line_gap,
+        })
+    }
+
+    /// The coverage of every texel, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// Distance between the baselines of two lines, in pixels.
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Places the glyphs of `text` with the top left corner of its first line at `position`, in
+    /// pixels from the top left corner of the viewport. Lines are separated by `\n`.
+    pub fn layout(&self, text: &str, position: [f32; 2]) -> Vec<GlyphQuad> {
+        let mut quads = Vec::with_capacity(text.len());
+        let mut pen = [position[0], position[1] + self.ascent];
+        for c in text.chars() {
+            if c == '\n' {
+                pen = [position[0], pen[1] + self.line_height];
+                continue;
+            }
+
+            let glyph = self.glyph(c);
+            if glyph.min != glyph.max {
+                let min = [(pen[0] + glyph.offset[0]).round(), (pen[1] + glyph.offset[1]).round()];
+                quads.push(GlyphQuad {
+                    min,
+                    max: [
+                        min[0] + (glyph.max[0] - glyph.min[0]) as f32,
+                        min[1] + (glyph.max[1] - glyph.min[1]) as f32,
+                    ],
+                    atlas_min: glyph.min,
+                    atlas_max: glyph.max,
+                });
+            }
+            pen[0] += glyph.advance;
+        }
+        quads
+    }
+
+    /// Width of the longest line and height of all lines of `text`, in pixels.
+    pub fn measure(&self, text: &str) -> [f32; 2] {
+        let lines = text.split('\n');
+        let width = lines
+            .clone()
+            .map(|line| line.chars().map(|c| self.glyph(c).advance).sum::<f32>())
+            .fold(0.0, f32::max);
+        [width, lines.count() as f32 * self.line_height]
+    }
+
+    fn glyph(&self, c: char) -> &Glyph {
+        let c = if (FIRST_CHAR..=LAST_CHAR).contains(&c) { c } else { REPLACEMENT };
+        &self.glyphs[(c as u32 - FIRST_CHAR as u32) as usize]
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct TextVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [f32; 4],
+}
+
+vulkano::impl_vertex!(TextVertex, position, uv, color);
+
+/// Draws text from a glyph atlas on top of what has been drawn before, e.g. the frame rate and
+/// other debug information.
+///
+/// Strings (and rectangles behind them) are queued during a frame as quads in pixels, `draw`
+/// draws all of them at once with a single draw call. The pipeline has no depth test, so it is
+/// built for a subpass without a depth buffer, like the overlay subpasses of the renderer and
+/// the offscreen targets.
+pub struct TextRenderer {
+    device: Arc<Device>,
+    pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    set: Arc<dyn DescriptorSet + Send + Sync>,
+    atlas: GlyphAtlas,
+    // the quads queued since the last draw, six vertices each
+    vertices: Vec<TextVertex>,
+}
+
+impl TextRenderer {
+    /// Uploads the atlas into a texture, waiting until the upload has finished.
+    pub fn new(queue: Arc<Queue>, subpass: Subpass, atlas: GlyphAtlas) -> TextRenderer {
+        let device = queue.device().clone();
+
+        // load shader for device
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        let pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input_single_buffer::<TextVertex>()
+                .vertex_shader(vertex_shader.main_entry_point(), ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fragment_shader.main_entry_point(), ())
+                // the glyphs are blended over the image by their coverage
+                .blend_alpha_blending()
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        let [width, height] = atlas.dimensions();
+        let (texture, upload) = ImmutableImage::from_iter(
+            atlas.pixels().iter().cloned(),
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8Unorm,
+            queue.clone(),
+        )
+        .expect("failed to create glyph atlas");
+
+        upload
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        // every texel of the atlas ends up on exactly one pixel, so nothing has to be filtered
+        let sampler = SamplerOptions {
+            filter: Filter::Nearest,
+            address_mode: SamplerAddressMode::ClampToEdge,
+            anisotropy: 1.0,
+        }
+        .build(&queue);
+
+        let layout = pipeline.layout().descriptor_set_layouts().first().unwrap();
+        let set = Arc::new(
+            PersistentDescriptorSet::start(layout.clone())
+                .add_sampled_image(ImageView::new(texture).unwrap(), sampler)
+                .unwrap()
+                .build()
+                .unwrap(),
+        );
+
+        TextRenderer {
+            device,
+            pipeline,
+            set,
+            atlas,
+            vertices: Vec::new(),
+        }
+    }
+
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
+
+    /// Queues `text` with the top left corner of its first line at `position`, in pixels from
+    /// the top left corner of the viewport. Lines are separated by `\n`.
+    pub fn text(&mut self, text: &str, position: [f32; 2], color: [f32; 4]) {
+        for quad in self.atlas.layout(text, position) {
+            self.quad(quad.min, quad.max, quad.atlas_min, quad.atlas_max, color);
+        }
+    }
+
+    /// Queues a rectangle filled with `color`, from its top left corner `min` to its bottom right
+    /// corner `max`, in pixels.
+    pub fn rect(&mut self, min: [f32; 2], max: [f32; 2], color: [f32; 4]) {
+        // the texels in the middle of the fully covered block
+        let center = [SOLID / 2, SOLID / 2];
+        self.quad(min, max, center, center, color);
+    }
+
+    /// Queues `text` on a rectangle of `background`, which leaves a margin of `margin` pixels
+    /// around it. `position` is the top left corner of the rectangle.
+    pub fn panel(&mut self, text: &str, position: [f32; 2], margin: f32, color: [f32; 4], background: [f32; 4]) {
+        let [width, height] = self.atlas.measure(text);
+        let max = [position[0] + width + margin * 2.0, position[1] + height + margin * 2.0];
+        self.rect(position, max, background);
+        self.text(text, [position[0] + margin, position[1] + margin], color);
+    }
+
+    /// Draws everything queued since the last draw, in the order it was queued, into the
+    /// viewport of `dynamic_state`.
+    pub fn draw(
+        &mut self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let viewport = dynamic_state
+            .viewports
+            .as_ref()
+            .and_then(|viewports| viewports.first())
+            .expect("text is drawn into a dynamic viewport")
+            .dimensions;
+
+        // the vertices change every frame, a new buffer is cheap compared to waiting for the
+        // frame which used the previous one
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            self.device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            self.vertices.drain(..),
+        )
+        .unwrap();
+
+        builder
+            .draw(
+                self.pipeline.clone(),
+                dynamic_state,
+                vertex_buffer,
+                self.set.clone(),
+                vs::ty::PushConstants { viewport },
+            )
+            .unwrap();
+    }
+
+    // queues two triangles covering the rectangle from `min` to `max`, with the texels from
+    // `atlas_min` to `atlas_max` stretched over it
+    fn quad(&mut self, min: [f32; 2], max: [f32; 2], atlas_min: [u32; 2], atlas_max: [u32; 2], color: [f32; 4]) {
+        let [width, height] = self.atlas.dimensions();
+        let uv = |[x, y]: [u32; 2]| [x as f32 / width as f32, y as f32 / height as f32];
+        let [uv_min, uv_max] = [uv(atlas_min), uv(atlas_max)];
+
+        let vertex = |position: [f32; 2], uv: [f32; 2]| TextVertex { position, uv, color };
+        let top_left = vertex(min, uv_min);
+        let top_right = vertex([max[0], min[1]], [uv_max[0], uv_min[1]]);
+        let bottom_left = vertex([min[0], max[1]], [uv_min[0], uv_max[1]]);
+        let bottom_right = vertex(max, uv_max);
+        self.vertices
+            .extend_from_slice(&[top_left, bottom_left, top_right, top_right, bottom_left, bottom_right]);
+    }
+}
+
+/// Reads the font at `path`, or returns the default one without a path.
+pub fn load_font(path: Option<&str>) -> Vec<u8> {
+    match path {
+        Some(path) => std::fs::read(path).unwrap_or_else(|err| panic!("{}: {}", path, err)),
+        None => DEFAULT_FONT.to_vec(),
+    }
+}
@@ -0,0 +1,103 @@
+use vulkano::device::Device;
+use vulkano::device::Queue;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use image::Rgba;
+
+use crate::msaa;
+use crate::offscreen::OffscreenTarget;
+use crate::text::{self, GlyphAtlas, GlyphQuad, TextRenderer};
+
+const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>) {
+    println!("performing text test");
+
+    let atlas = GlyphAtlas::new(text::DEFAULT_FONT, text::DEFAULT_SIZE).unwrap();
+    assert!(GlyphAtlas::new(b"not a font", text::DEFAULT_SIZE).is_none());
+
+    // the space has no bitmap, but moves the pen on by as much as any glyph of the monospaced
+    // default font
+    let tight = atlas.layout("Hi", [0.0, 0.0]);
+    let spaced = atlas.layout("H i", [0.0, 0.0]);
+    assert_eq!((tight.len(), spaced.len()), (2, 2));
+    let advance = atlas.measure(" ")[0];
+    assert!(advance > 0.0);
+    // the glyphs are moved to whole pixels
+    assert!((spaced[1].min[0] - tight[1].min[0] - advance).abs() <= 1.0);
+
+    // lines start below each other, at the same x
+    let lines = atlas.layout("H\nH", [10.0, 20.0]);
+    assert!((lines[1].min[1] - lines[0].min[1] - atlas.line_height()).abs() <= 1.0);
+    assert_eq!(lines[1].min[0], lines[0].min[0]);
+    assert_eq!(atlas.measure("Hi\ni"), [advance * 2.0, atlas.line_height() * 2.0]);
+
+    // characters which aren't in the atlas are drawn as a question mark
+    assert_eq!(atlas.layout("\u{e9}", [0.0, 0.0]), atlas.layout("?", [0.0, 0.0]));
+
+    // every glyph covers some of its texels
+    for quad in &atlas.layout("Hi?", [0.0, 0.0]) {
+        assert!(covers(&atlas, quad), "glyph at {:?} is empty", quad.atlas_min);
+    }
+
+    // the overlay is drawn after the multisampled subpass, into the resolved image
+    let samples = msaa::clamp_samples(device.physical_device(), 4);
+    let target = OffscreenTarget::start(device.clone(), [128, 64])
+        .samples(samples)
+        .overlay()
+        .build();
+    let mut text = TextRenderer::new(queue.clone(), target.overlay_subpass().unwrap(), atlas);
+    let quads = text.atlas().layout("Hi", [8.0, 8.0]);
+    let dynamic_state = target.dynamic_state();
+
+    // drawing without anything queued leaves the overlay untouched
+    let empty = target.render_with_overlay(queue.clone(), [0.0, 0.0, 0.0, 1.0], |_| (), |builder| {
+        text.draw(builder, &dynamic_state);
+    });
+    assert!(empty.pixels().all(|&pixel| pixel == BLACK), "the text renderer drew without anything queued");
+
+    let image = target.render_with_overlay(
+        queue,
+        [0.0, 0.0, 0.0, 1.0],
+        |_| (),
+        |builder| {
+            text.text("Hi", [8.0, 8.0], [1.0, 1.0, 1.0, 1.0]);
+            text.rect([96.0, 40.0], [112.0, 56.0], [1.0, 0.0, 0.0, 1.0]);
+            text.draw(builder, &dynamic_state);
+        },
+    );
+
+    assert_eq!(*image.get_pixel(104, 48), RED);
+    for quad in &quads {
+        let lit = pixels_in(quad.min, quad.max).any(|(x, y)| image.get_pixel(x, y)[0] > 128);
+        assert!(lit, "glyph at {:?} wasn't drawn", quad.min);
+    }
+
+    // nothing but the glyphs and the rectangle is drawn
+    let inside = |x: u32, y: u32, min: [f32; 2], max: [f32; 2]| {
+        (x as f32) >= min[0] && (x as f32) < max[0] && (y as f32) >= min[1] && (y as f32) < max[1]
+    };
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let drawn = inside(x, y, [96.0, 40.0], [112.0, 56.0])
+            || quads.iter().any(|quad| inside(x, y, quad.min, quad.max));
+        assert!(drawn || *pixel == BLACK, "pixel {}, {} was drawn to: {:?}", x, y, pixel);
+    }
+
+    println!("text test successful!");
+}
+
+// whether any texel of the glyph in the atlas is covered
+fn covers(atlas: &GlyphAtlas, quad: &GlyphQuad) -> bool {
+    let width = atlas.dimensions()[0];
+    let min = [quad.atlas_min[0] as f32, quad.atlas_min[1] as f32];
+    let max = [quad.atlas_max[0] as f32, quad.atlas_max[1] as f32];
+    pixels_in(min, max).any(|(x, y)| atlas.pixels()[(y * width + x) as usize] > 0)
+}
+
+fn pixels_in(min: [f32; 2], max: [f32; 2]) -> impl Iterator<Item = (u32, u32)> {
+    let (x0, x1) = (min[0] as u32, max[0] as u32);
+    (min[1] as u32..max[1] as u32).flat_map(move |y| (x0..x1).map(move |x| (x, y)))
+}
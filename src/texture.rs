@@ -0,0 +1,100 @@
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::ImageDimensions;
+use vulkano::image::ImmutableImage;
+use vulkano::image::MipmapsCount;
+use vulkano::image::view::ImageView;
+use vulkano::sampler::{Filter, MipmapMode, Sampler, SamplerAddressMode};
+use vulkano::sync::GpuFuture;
+
+// atomically reference counted
+use std::sync::Arc;
+
+/// How a texture is filtered and repeated when sampled.
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerOptions {
+    pub filter: Filter,
+    pub address_mode: SamplerAddressMode,
+    // 1.0 disables anisotropic filtering
+    pub anisotropy: f32,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> SamplerOptions {
+        SamplerOptions {
+            filter: Filter::Linear,
+            address_mode: SamplerAddressMode::Repeat,
+            anisotropy: 1.0,
+        }
+    }
+}
+
+impl SamplerOptions {
+    /// Creates the sampler, the anisotropy is limited to what the device supports (and to 1.0
+    /// if the `sampler_anisotropy` feature is not enabled).
+    pub fn build(&self, queue: &Queue) -> Arc<Sampler> {
+        let device = queue.device();
+        let max_anisotropy = if device.enabled_features().sampler_anisotropy {
+            self.anisotropy
+                .min(device.physical_device().properties().max_sampler_anisotropy)
+                .max(1.0)
+        } else {
+            1.0
+        };
+
+        Sampler::new(
+            device.clone(),
+            self.filter,
+            self.filter,
+            MipmapMode::Nearest,
+            self.address_mode,
+            self.address_mode,
+            self.address_mode,
+            0.0,
+            max_anisotropy,
+            0.0,
+            0.0,
+        )
+        .expect("failed to create sampler")
+    }
+}
+
+/// Decodes a PNG (or any other format the `image` crate knows) and uploads it into an
+/// `ImmutableImage`, waiting until the upload has finished.
+///
+/// `srgb` selects whether the texels are stored as sRGB, which has to match the color space of
+/// the attachment the texture ends up in, or the colors are off after sampling.
+pub fn load_png(queue: Arc<Queue>, bytes: &[u8], srgb: bool) -> Arc<ImageView<Arc<ImmutableImage>>> {
+    let image = image::load_from_memory(bytes).expect("failed to decode texture").to_rgba8();
+    let (width, height) = image.dimensions();
+
+    let format = if srgb { Format::R8G8B8A8Srgb } else { Format::R8G8B8A8Unorm };
+    let (texture, upload) = ImmutableImage::from_iter(
+        image.into_raw().into_iter(),
+        ImageDimensions::Dim2d {
+            width,
+            height,
+            array_layers: 1,
+        },
+        MipmapsCount::One,
+        format,
+        queue,
+    )
+    .expect("failed to create texture");
+
+    upload
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    ImageView::new(texture).unwrap()
+}
+
+/// Whether `format` stores its colors in the sRGB color space.
+pub fn is_srgb(format: Format) -> bool {
+    matches!(
+        format,
+        Format::R8G8B8A8Srgb | Format::B8G8R8A8Srgb | Format::A8B8G8R8SrgbPack32
+    )
+}
@@ -0,0 +1,212 @@
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::CpuAccessibleBuffer;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::descriptor_set::{DescriptorSet, PersistentDescriptorSet};
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::vertex::BuffersDefinition;
+use vulkano::render_pass::Subpass;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use image::{Rgba, ImageBuffer};
+
+use crate::cli::Options;
+use crate::offscreen::OffscreenTarget;
+use crate::pipeline_cache;
+use crate::texture::{self, SamplerOptions};
+
+/// The texture drawn when no other one is given on the command line.
+pub const DEFAULT_TEXTURE: &[u8] = include_bytes!("../assets/texture.png");
+
+mod vs {
+    vulkano_shaders::shader!{
+        ty: "vertex",
+        src: "
+#version 450
+
+layout(location=0) in vec2 position;
+layout(location=1) in vec2 uv;
+
+layout(location=0) out vec2 v_uv;
+
+// scales the quad, to keep the texels square in a viewport which isn't
+layout(push_constant) uniform PushConstants {
+    vec2 scale;
+} push_constants;
+
+void main() {
+    v_uv = uv;
+    gl_Position = vec4(position * push_constants.scale, 0.0, 1.0);
+}
+"
+    }
+}
+
+mod fs {
+    vulkano_shaders::shader!{
+        ty: "fragment",
+        src: "
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform sampler2D tex;
+
+void main() {
+    f_color = texture(tex, v_uv);
+}
+"
+    }
+}
+
+#[derive(Default, Copy, Clone)]
+pub struct TexturedVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+
+vulkano::impl_vertex!(TexturedVertex, position, uv);
+
+/// Pipeline, vertex buffer and descriptor set to draw a textured quad into a subpass.
+///
+/// The quad covers the whole viewport, with the top left corner of the texture in the top left
+/// corner of the viewport.
+pub struct TexturedQuad {
+    pipeline: Arc<GraphicsPipeline<BuffersDefinition>>,
+    vertex_buffer: Arc<CpuAccessibleBuffer<[TexturedVertex]>>,
+    set: Arc<dyn DescriptorSet + Send + Sync>,
+}
+
+impl TexturedQuad {
+    /// Loads the PNG in `bytes` into a texture sampled with `sampler_options`.
+    pub fn new(
+        queue: Arc<Queue>,
+        device: Arc<Device>,
+        subpass: Subpass,
+        bytes: &[u8],
+        sampler_options: &SamplerOptions,
+    ) -> TexturedQuad {
+        // load shader for device
+        let vertex_shader = vs::Shader::load(device.clone()).expect("failed to create vertex shader");
+        let fragment_shader = fs::Shader::load(device.clone()).expect("failed to create fragment shader");
+
+        // the texture is stored in the same color space as the attachment it is drawn into, so
+        // the colors are written back unchanged
+        let srgb = texture::is_srgb(subpass.render_pass().desc().attachments()[0].format);
+
+        let pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input_single_buffer::<TexturedVertex>()
+                .vertex_shader(vertex_shader.main_entry_point(), ())
+                .triangle_strip()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fragment_shader.main_entry_point(), ())
+                .render_pass(subpass)
+                .build_with_cache(pipeline_cache::get(&device))
+                .build(device.clone())
+                .unwrap(),
+        );
+
+        let vertex_buffer = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::vertex_buffer(),
+            false,
+            [
+                TexturedVertex { position: [-1.0, -1.0], uv: [0.0, 0.0] },
+                TexturedVertex { position: [-1.0, 1.0], uv: [0.0, 1.0] },
+                TexturedVertex { position: [1.0, -1.0], uv: [1.0, 0.0] },
+                TexturedVertex { position: [1.0, 1.0], uv: [1.0, 1.0] },
+            ]
+            .iter()
+            .cloned(),
+        )
+        .unwrap();
+
+        let texture = texture::load_png(queue.clone(), bytes, srgb);
+        let sampler = sampler_options.build(&queue);
+
+        let layout = pipeline.layout().descriptor_set_layouts().get(0).unwrap();
+        let set = Arc::new(
+            PersistentDescriptorSet::start(layout.clone())
+                .add_sampled_image(texture, sampler)
+                .unwrap()
+                .build()
+                .unwrap(),
+        );
+
+        TexturedQuad {
+            pipeline,
+            vertex_buffer,
+            set,
+        }
+    }
+
+    /// Draws the quad, scaled by `scale` in x and y.
+    pub fn draw(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        dynamic_state: &DynamicState,
+        scale: [f32; 2],
+    ) {
+        builder
+            .draw(
+                self.pipeline.clone(),
+                dynamic_state,
+                self.vertex_buffer.clone(),
+                self.set.clone(),
+                vs::ty::PushConstants { scale },
+            )
+            .unwrap();
+    }
+}
+
+/// The scale which makes the quad as large as possible in a viewport with the given
+/// dimensions, while keeping it square.
+pub fn square_scale(dimensions: [u32; 2]) -> [f32; 2] {
+    let [width, height] = [dimensions[0] as f32, dimensions[1] as f32];
+    if width > height {
+        [height / width, 1.0]
+    } else {
+        [1.0, width / height]
+    }
+}
+
+/// Renders the texture in `bytes` headlessly into `target`, stretched over the whole image.
+pub fn render(
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    target: &OffscreenTarget,
+    bytes: &[u8],
+    sampler_options: &SamplerOptions,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let quad = TexturedQuad::new(queue.clone(), device, target.subpass(), bytes, sampler_options);
+    let dynamic_state = target.dynamic_state();
+
+    target.render(queue, [0.0, 0.0, 0.0, 1.0], |builder| {
+        quad.draw(builder, &dynamic_state, [1.0, 1.0]);
+    })
+}
+
+/// `texture-sample [TEXTURE]` renders a PNG (the default texture without an argument) with
+/// the sampler given by `--filter`, `--address-mode` and `--anisotropy` into `texture.png`.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let bytes = match options.args.first() {
+        Some(path) => std::fs::read(path).unwrap_or_else(|err| panic!("{}: {}", path, err)),
+        None => DEFAULT_TEXTURE.to_vec(),
+    };
+
+    let target = OffscreenTarget::start(device.clone(), options.size)
+        .samples(samples)
+        .build();
+
+    let image_buffer = render(queue, device, &target, &bytes, &options.sampler);
+
+    image_buffer
+        .save(options.output.as_deref().unwrap_or("texture.png"))
+        .unwrap();
+}
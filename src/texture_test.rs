@@ -0,0 +1,59 @@
+use vulkano::device::Device;
+use vulkano::device::Queue;
+use vulkano::sampler::{Filter, SamplerAddressMode};
+
+// atomically reference counted
+use std::sync::Arc;
+
+use image::{Rgba, ImageBuffer};
+
+use crate::offscreen::OffscreenTarget;
+use crate::texture::SamplerOptions;
+use crate::texture_sample;
+
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>) {
+    println!("performing texture test");
+
+    // the golden image is the texture itself: drawn over a target of the same size, every pixel
+    // is sampled at the center of exactly one texel
+    let golden = image::load_from_memory(texture_sample::DEFAULT_TEXTURE)
+        .unwrap()
+        .to_rgba8();
+    let (width, height) = golden.dimensions();
+    let target = OffscreenTarget::start(device.clone(), [width, height]).build();
+
+    // at texel centers the linear filter doesn't blend neighbouring texels either, so both
+    // filters reproduce the texture
+    for &filter in &[Filter::Nearest, Filter::Linear] {
+        let sampler_options = SamplerOptions {
+            filter,
+            address_mode: SamplerAddressMode::ClampToEdge,
+            anisotropy: 1.0,
+        };
+        let image = texture_sample::render(
+            queue.clone(),
+            device.clone(),
+            &target,
+            texture_sample::DEFAULT_TEXTURE,
+            &sampler_options,
+        );
+
+        let differences = count_differences(&image, &golden);
+        assert_eq!(differences, 0, "{} pixels differ with {:?} filter", differences, filter);
+    }
+
+    println!("Texture test successful!");
+}
+
+// counts the pixels, which differ by more than rounding errors
+fn count_differences(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, golden: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> usize {
+    image
+        .pixels()
+        .zip(golden.pixels())
+        .filter(|(a, b)| {
+            a.0.iter()
+                .zip(b.0.iter())
+                .any(|(&a, &b)| (a as i32 - b as i32).abs() > 1)
+        })
+        .count()
+}
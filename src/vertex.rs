@@ -0,0 +1,50 @@
+use vulkano::pipeline::vertex::BuffersDefinition;
+
+/// Only the position of a vertex, for geometry whose other attributes are stored in a second
+/// buffer (see `Attributes`).
+///
+/// Passes which only need the positions (like depth-only passes) can then bind just this
+/// buffer, and read less memory per vertex.
+#[derive(Default, Debug, Copy, Clone)]
+pub struct Position {
+    pub position: [f32; 3],
+}
+
+vulkano::impl_vertex!(Position, position);
+
+/// The per-vertex attributes besides the position.
+#[derive(Default, Debug, Copy, Clone)]
+pub struct Attributes {
+    pub color: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+vulkano::impl_vertex!(Attributes, color, normal, uv);
+
+/// Per-instance data: a 2D transform and a color multiplied with the vertex color.
+///
+/// The attribute names differ from those in `Position` and `Attributes`, because the shader
+/// inputs are matched with the members of all buffers by name.
+#[derive(Default, Debug, Copy, Clone)]
+pub struct Instance {
+    pub offset: [f32; 2],
+    pub scale: f32,
+    // rotation around the z axis, in radians
+    pub rotation: f32,
+    pub tint: [f32; 3],
+}
+
+vulkano::impl_vertex!(Instance, offset, scale, rotation, tint);
+
+/// Positions in the first and the other attributes in the second vertex buffer.
+pub fn split_definition() -> BuffersDefinition {
+    BuffersDefinition::new()
+        .vertex::<Position>()
+        .vertex::<Attributes>()
+}
+
+/// Like `split_definition`, with a third buffer advancing once per instance.
+pub fn instanced_definition() -> BuffersDefinition {
+    split_definition().instance::<Instance>()
+}
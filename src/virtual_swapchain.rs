@@ -0,0 +1,292 @@
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer};
+use vulkano::command_buffer::DynamicState;
+use vulkano::command_buffer::SubpassContents;
+use vulkano::device::{Device, DeviceOwned, Queue};
+use vulkano::format::{ClearValue, Format};
+use vulkano::render_pass::{FramebufferAbstract, Subpass};
+use vulkano::sync::GpuFuture;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use cgmath::Rad;
+use image::{Rgba, ImageBuffer};
+
+use crate::camera::Camera;
+use crate::cli::Options;
+use crate::cube_sample::Cube;
+use crate::depth;
+use crate::frame_stats::FrameStats;
+use crate::frames::{FrameFence, FramesInFlight};
+use crate::offscreen::OffscreenTarget;
+use crate::particle_sample::frame_path;
+use crate::present::{self, Overlay, PresentImage, PresentTarget};
+use crate::text::{self, GlyphAtlas, TextRenderer};
+
+/// Time between two frames drawn by the sample and the test, in seconds.
+pub const FRAME_TIME: f32 = 1.0 / 60.0;
+
+/// Stands in for the swapchain of a window without a display: a ring of offscreen images which
+/// are acquired in turn, with a depth buffer each and an overlay subpass like the windows.
+///
+/// Presenting an image copies it into the host visible buffer of its target, where it can be
+/// read until the image is presented to again. Acquiring never fails, the virtual swapchain is
+/// never out of date.
+pub struct VirtualSwapchain {
+    queue: Arc<Queue>,
+    images: Vec<OffscreenTarget>,
+    dynamic_state: DynamicState,
+    // the image acquired next
+    next: usize,
+    // the number of the frame which was presented last into each image
+    presented: Vec<Option<u32>>,
+    frame: u32,
+}
+
+/// An image acquired from a `VirtualSwapchain`.
+pub struct VirtualImage {
+    index: usize,
+    framebuffer: Arc<dyn FramebufferAbstract + Send + Sync>,
+}
+
+impl PresentImage for VirtualImage {
+    fn framebuffer(&self) -> Arc<dyn FramebufferAbstract + Send + Sync> {
+        self.framebuffer.clone()
+    }
+}
+
+impl VirtualSwapchain {
+    /// `image_count` images of the given size and samples per pixel, with a depth buffer and an
+    /// overlay subpass, which share one render pass.
+    pub fn new(
+        queue: Arc<Queue>,
+        dimensions: [u32; 2],
+        image_count: u32,
+        samples: u32,
+        depth_format: Format,
+    ) -> VirtualSwapchain {
+        let device = queue.device().clone();
+        let start = || {
+            OffscreenTarget::start(device.clone(), dimensions)
+                .samples(samples)
+                .depth_format(depth_format)
+                .overlay()
+        };
+
+        let first = start().build();
+        let render_pass = first.render_pass();
+        let mut images = vec![first];
+        for _ in 1..image_count.max(1) {
+            images.push(start().render_pass(render_pass.clone()).build());
+        }
+
+        VirtualSwapchain {
+            dynamic_state: images[0].dynamic_state(),
+            presented: vec![None; images.len()],
+            queue,
+            images,
+            next: 0,
+            frame: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    /// The subpass of the render pass all images share.
+    pub fn subpass(&self) -> Subpass {
+        self.images[0].subpass()
+    }
+
+    /// The subpass drawing on top of `subpass` into the single sampled image.
+    pub fn overlay_subpass(&self) -> Subpass {
+        self.images[0].overlay_subpass().unwrap()
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.images[0].dimensions()
+    }
+
+    /// Width divided by height of the images.
+    pub fn aspect(&self) -> f32 {
+        let [width, height] = self.dimensions();
+        width as f32 / height as f32
+    }
+
+    /// Dynamic state with a viewport covering the whole image.
+    pub fn dynamic_state(&self) -> &DynamicState {
+        &self.dynamic_state
+    }
+
+    /// Clear values for `begin_render_pass`, one per attachment in render pass order.
+    pub fn clear_values(&self, clear_color: [f32; 4]) -> Vec<ClearValue> {
+        self.images[0].clear_values(clear_color)
+    }
+
+    /// The number of the frame which was presented last into the image `index`, counting every
+    /// frame presented to this swapchain.
+    pub fn presented(&self, index: usize) -> Option<u32> {
+        self.presented[index]
+    }
+
+    /// The content of the image `index` when it was presented last. The gpu has to be done with
+    /// the frame (see `FramesInFlight::wait_all`).
+    pub fn read(&self, index: usize) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        self.images[index].read()
+    }
+
+    /// Reads the image frame number `frame` was presented into, if it hasn't been overwritten by
+    /// a later frame since.
+    pub fn read_frame(&self, frame: u32) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+        let index = self.presented.iter().position(|&presented| presented == Some(frame))?;
+        Some(self.read(index))
+    }
+}
+
+impl PresentTarget for VirtualSwapchain {
+    type Image = VirtualImage;
+
+    fn dynamic_state(&self) -> &DynamicState {
+        VirtualSwapchain::dynamic_state(self)
+    }
+
+    fn clear_values(&self, clear_color: [f32; 4]) -> Vec<ClearValue> {
+        VirtualSwapchain::clear_values(self, clear_color)
+    }
+
+    /// The images are acquired in the order they were created, over and over.
+    fn acquire(&mut self) -> Option<VirtualImage> {
+        let index = self.next;
+        self.next = (self.next + 1) % self.images.len();
+
+        Some(VirtualImage {
+            index,
+            framebuffer: self.images[index].framebuffer(),
+        })
+    }
+
+    /// Presenting copies the image into its readback buffer, after `command_buffer` is done.
+    fn present(
+        &mut self,
+        previous: Box<dyn GpuFuture>,
+        image: VirtualImage,
+        command_buffer: PrimaryAutoCommandBuffer,
+    ) -> Option<FrameFence> {
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        self.images[image.index].record_readback(&mut builder);
+        let readback = builder.build().unwrap();
+
+        let future = previous
+            .then_execute(self.queue.clone(), command_buffer)
+            .unwrap()
+            .then_execute(self.queue.clone(), readback)
+            .unwrap()
+            .boxed()
+            .then_signal_fence_and_flush();
+
+        self.presented[image.index] = Some(self.frame);
+        self.frame += 1;
+
+        match future {
+            Ok(future) => Some(future),
+            Err(e) => {
+                println!("Failed to flush future: {:?}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Draws the cube rotated by `angle` into the next image of `swapchain`, through the same frame
+/// loop and render pass as the window of the `cube` command (`present::draw_frame` and
+/// `present::record_frame`), with the text queued in `text` on top. Returns
+/// whether the frame was presented.
+pub fn draw_cube_frame(
+    queue: &Arc<Queue>,
+    swapchain: &mut VirtualSwapchain,
+    frames: &mut FramesInFlight<()>,
+    stats: &mut FrameStats,
+    cube: &Cube,
+    angle: Rad<f32>,
+    text: Option<&mut TextRenderer>,
+) -> bool {
+    let camera = Camera::default();
+    let times = present::draw_frame(queue, swapchain, frames, |builder, swapchain, image, ()| {
+        let aspect = swapchain.aspect();
+        present::record_frame(
+            builder,
+            swapchain,
+            image,
+            [0.0, 0.0, 0.0, 1.0],
+            SubpassContents::Inline,
+            |builder, dynamic_state| cube.draw(builder, dynamic_state, &camera, aspect, angle),
+            Overlay { text, gui: None },
+        );
+    });
+
+    match times {
+        Some(times) => {
+            stats.record(times);
+            true
+        }
+        None => false,
+    }
+}
+
+/// `virtual-swapchain` draws `--frames N` frames of the rotating cube into a virtual swapchain of
+/// `--images N` images, with `--frames-in-flight N`, and prints the frame times. The frames given
+/// as arguments (the last one by default) are saved as `frame_NNN.png`. With `--overlay` the
+/// number of the frame and the device are drawn on top.
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>, samples: u32, options: &Options) {
+    let mut swapchain = VirtualSwapchain::new(
+        queue.clone(),
+        options.size,
+        options.swapchain.image_count,
+        samples,
+        depth::choose_depth_format(device.physical_device()),
+    );
+    let mut frames = FramesInFlight::new(device.clone(), options.frames_in_flight, |_| ());
+    let mut stats = FrameStats::default();
+    let cube = Cube::new(device.clone(), swapchain.subpass());
+    let mut text = options.overlay.then(|| {
+        let atlas = GlyphAtlas::new(&text::load_font(options.font.as_deref()), text::DEFAULT_SIZE)
+            .expect("failed to load font");
+        TextRenderer::new(queue.clone(), swapchain.overlay_subpass(), atlas)
+    });
+
+    let saved: Vec<u32> = if options.args.is_empty() {
+        vec![options.frames.saturating_sub(1)]
+    } else {
+        options
+            .args
+            .iter()
+            .map(|frame| frame.parse().unwrap_or_else(|_| panic!("invalid frame number: {}", frame)))
+            .collect()
+    };
+    let output = options.output.as_deref().unwrap_or("frame.png");
+
+    for frame in 0..options.frames {
+        let angle = Rad(frame as f32 * FRAME_TIME);
+        if let Some(text) = &mut text {
+            let info = format!("frame {}/{}\n{}", frame + 1, options.frames, device.physical_device().properties().device_name);
+            text.panel(&info, [8.0, 8.0], 4.0, [1.0, 1.0, 1.0, 1.0], [0.0, 0.0, 0.0, 0.6]);
+        }
+        assert!(draw_cube_frame(&queue, &mut swapchain, &mut frames, &mut stats, &cube, angle, text.as_mut()));
+
+        // the image is overwritten once it is acquired again, it is read before that
+        if saved.contains(&frame) {
+            frames.wait_all();
+            swapchain.read_frame(frame).unwrap().save(frame_path(output, frame)).unwrap();
+        }
+    }
+
+    println!("{} images, {} frames in flight", swapchain.len(), frames.len());
+    println!("{}", frames.stats());
+    println!("{}", stats.summary());
+}
@@ -0,0 +1,60 @@
+use vulkano::device::Device;
+use vulkano::device::Queue;
+
+// atomically reference counted
+use std::sync::Arc;
+
+use cgmath::Rad;
+
+use crate::cube_sample::{self, Cube};
+use crate::depth;
+use crate::frame_stats::FrameStats;
+use crate::frames::FramesInFlight;
+use crate::offscreen::OffscreenTarget;
+use crate::virtual_swapchain::{self, VirtualSwapchain, FRAME_TIME};
+
+// more frames than images and frames in flight, so every image is acquired several times
+const FRAMES: u32 = 10;
+const IMAGES: u32 = 3;
+const FRAMES_IN_FLIGHT: usize = 2;
+
+pub fn execute(queue: Arc<Queue>, device: Arc<Device>) {
+    println!("performing virtual swapchain test");
+
+    let depth_format = depth::choose_depth_format(device.physical_device());
+    let mut swapchain = VirtualSwapchain::new(queue.clone(), [256, 256], IMAGES, 1, depth_format);
+    let mut frames = FramesInFlight::new(device.clone(), FRAMES_IN_FLIGHT, |_| ());
+    let mut stats = FrameStats::default();
+    let cube = Cube::new(device.clone(), swapchain.subpass());
+
+    let target = OffscreenTarget::start(device.clone(), [256, 256])
+        .depth_format(depth_format)
+        .build();
+
+    // the frames are drawn like the window does, without waiting for the gpu in between
+    let angle = |frame: u32| Rad(frame as f32 * FRAME_TIME * 20.0);
+    for frame in 0..FRAMES {
+        let presented = virtual_swapchain::draw_cube_frame(&queue, &mut swapchain, &mut frames, &mut stats, &cube, angle(frame), None);
+        assert!(presented, "frame {} wasn't presented", frame);
+
+        // the images are acquired round robin
+        let index = frame as usize % IMAGES as usize;
+        assert_eq!(swapchain.presented(index), Some(frame), "frame {} presented into the wrong image", frame);
+    }
+    frames.wait_all();
+
+    // the last frame of every image looks like the cube rendered directly into an offscreen
+    // target, the earlier ones have been overwritten
+    for frame in FRAMES - IMAGES..FRAMES {
+        let expected = cube_sample::render(queue.clone(), device.clone(), &target, angle(frame));
+        assert!(
+            swapchain.read_frame(frame) == Some(expected),
+            "frame {} differs from the offscreen render",
+            frame,
+        );
+    }
+    assert!(swapchain.read_frame(FRAMES - 1 - IMAGES).is_none());
+
+    println!("{}", stats.summary());
+    println!("virtual swapchain test successful!");
+}
@@ -0,0 +1,105 @@
+use winit::window::WindowId;
+
+use crate::frames::FramesInFlight;
+use crate::renderer::Renderer;
+
+/// What a window shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    // the sample as seen by the camera moved with the input
+    Scene,
+    // the sample from a fixed camera further away, e.g. to see the scene camera from outside
+    Debug,
+}
+
+/// A window of the program, with its own swapchain and frames in flight.
+pub struct AppWindow<R> {
+    pub view: View,
+    pub renderer: Renderer,
+    pub frames: FramesInFlight<R>,
+}
+
+impl<R> AppWindow<R> {
+    pub fn id(&self) -> WindowId {
+        self.renderer.window().id()
+    }
+}
+
+/// The windows of the program. They are drawn with the same device and queue, and with the same
+/// pipelines if their renderers share a render pass. The events of winit are routed to the window
+/// they are for by its `WindowId`.
+///
+/// The first window is the main one: it can't be closed without ending the program.
+pub struct Windows<R> {
+    windows: Vec<AppWindow<R>>,
+    // the window which received the keyboard focus last
+    focused: Option<WindowId>,
+}
+
+impl<R> Windows<R> {
+    pub fn new(main: AppWindow<R>) -> Windows<R> {
+        Windows {
+            windows: vec![main],
+            focused: None,
+        }
+    }
+
+    pub fn add(&mut self, window: AppWindow<R>) {
+        self.windows.push(window);
+    }
+
+    pub fn main(&self) -> &AppWindow<R> {
+        &self.windows[0]
+    }
+
+    pub fn main_mut(&mut self) -> &mut AppWindow<R> {
+        &mut self.windows[0]
+    }
+
+    pub fn is_main(&self, id: WindowId) -> bool {
+        self.main().id() == id
+    }
+
+    pub fn get_mut(&mut self, id: WindowId) -> Option<&mut AppWindow<R>> {
+        self.windows.iter_mut().find(|window| window.id() == id)
+    }
+
+    /// Closes the window `id`, after the gpu is done with its frames. The main window is kept.
+    pub fn close(&mut self, id: WindowId) {
+        if self.is_main(id) {
+            return;
+        }
+        if let Some(index) = self.windows.iter().position(|window| window.id() == id) {
+            let mut window = self.windows.remove(index);
+            window.frames.wait_all();
+        }
+    }
+
+    /// Has to be called with the `Focused` events of the windows.
+    pub fn focused(&mut self, id: WindowId, focused: bool) {
+        if focused {
+            self.focused = Some(id);
+        } else if self.focused == Some(id) {
+            self.focused = None;
+        }
+    }
+
+    /// The window with the keyboard focus, or the main one if none of them has it.
+    pub fn focused_mut(&mut self) -> &mut AppWindow<R> {
+        let index = self
+            .windows
+            .iter()
+            .position(|window| Some(window.id()) == self.focused)
+            .unwrap_or(0);
+        &mut self.windows[index]
+    }
+
+    /// Whether none of the windows has an area to draw into.
+    pub fn all_minimized(&self) -> bool {
+        self.windows.iter().all(|window| window.renderer.is_minimized())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut AppWindow<R>> {
+        self.windows.iter_mut()
+    }
+}